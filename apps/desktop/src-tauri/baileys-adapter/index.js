@@ -1,21 +1,38 @@
 import makeWASocket, {
     DisconnectReason,
     fetchLatestBaileysVersion,
-    makeCacheableSignalKeyStore
+    makeCacheableSignalKeyStore,
+    downloadMediaMessage,
+    proto
 } from '@whiskeysockets/baileys';
 import { Boom } from '@hapi/boom';
 import pino from 'pino';
 import readline from 'readline';
+import { createWriteStream } from 'fs';
+import { mkdtemp, rm } from 'fs/promises';
+import { tmpdir } from 'os';
+import { basename, join } from 'path';
+import { pipeline } from 'stream/promises';
+import { createRequire } from 'module';
+
+// Must match PROTOCOL_VERSION in src/backend/ipc.rs
+const PROTOCOL_VERSION = 2;
+// Must match EXIT_CODE_LOGGED_OUT in src/backend/ipc.rs
+const EXIT_CODE_LOGGED_OUT = 3;
+const { version: ADAPTER_VERSION } = createRequire(import.meta.url)('./package.json');
 
 // Simple Logger
 const logger = pino({ level: 'silent' });
 
 // State
 let sock = null;
+// Private (0700) directory for decrypted media waiting to be cached by Rust
+let mediaDir = null;
 
 // IPC Helper
-function sendEvent(type, payload) {
-    console.log(JSON.stringify({ type, payload }));
+// `id` echoes the id of the command being answered (ack/error replies only)
+function sendEvent(type, payload, id) {
+    console.log(JSON.stringify({ type, payload, id }));
 }
 
 // In-Memory Auth Store with IPC Sync
@@ -65,21 +82,210 @@ const createIPCAuthState = (initialCreds, keysData) => {
 
 
 // Command Handlers
+async function handleHello(payload, id) {
+    sendEvent('hello', {
+        protocol_version: PROTOCOL_VERSION,
+        adapter: 'baileys',
+        adapter_version: ADAPTER_VERSION,
+        commands: Object.keys(handlers),
+    }, id);
+}
+
 async function handleInit(payload) {
     const initialCreds = payload.auth_data?.creds;
     const initialKeys = payload.auth_data?.keys;
 
-    await startSock(initialCreds, initialKeys);
+    await startSock(initialCreds, initialKeys, !!payload.mark_online);
+}
+
+async function handleSendMessage(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
+    const { jid, content, quoted } = payload;
+    try {
+        const options = quoted ? { quoted: quotedMessage(jid, quoted) } : {};
+        const sent = await sock.sendMessage(jid, { text: content }, options);
+        sendEvent('message', normalizeMessage(sent));
+        sendEvent('ack', { status: 'sent', jid, message_id: sent.key.id }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleSendMedia(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
+    const { jid, path, kind, mime_type, caption } = payload;
+    const source = { url: path };
+    let content;
+    switch (kind) {
+        case 'image':
+            content = { image: source, mimetype: mime_type, caption: caption ?? undefined };
+            break;
+        case 'video':
+            content = { video: source, mimetype: mime_type, caption: caption ?? undefined };
+            break;
+        case 'audio':
+            content = { audio: source, mimetype: mime_type };
+            break;
+        default:
+            content = { document: source, mimetype: mime_type, fileName: basename(path), caption: caption ?? undefined };
+            break;
+    }
+    try {
+        const sent = await sock.sendMessage(jid, content);
+        sendEvent('message', normalizeMessage(sent));
+        sendEvent('ack', { status: 'sent', jid, message_id: sent.key.id }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+// An empty emoji removes our reaction
+async function handleSendReaction(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
+    const { chat_id, message_id, from_me, sender_id, emoji } = payload;
+    try {
+        const sent = await sock.sendMessage(chat_id, {
+            react: {
+                text: emoji,
+                key: { remoteJid: chat_id, id: message_id, fromMe: from_me, participant: sender_id ?? undefined },
+            },
+        });
+        sendEvent('reaction', normalizeReaction(sent));
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+// Receipts, chatstate and presence are only sent when Rust's privacy policy allows them
+async function handleSendReceipt(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
+    const { chat_id, sender_id, message_ids } = payload;
+    try {
+        await sock.readMessages(message_ids.map((messageId) => ({
+            remoteJid: chat_id,
+            id: messageId,
+            participant: sender_id ?? undefined,
+            fromMe: false,
+        })));
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleSendChatState(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
+    const { chat_id, state } = payload;
+    if (!['composing', 'recording', 'paused'].includes(state)) {
+        return sendEvent('error', { message: `Not a chat state: ${state}` }, id);
+    }
+    try {
+        await sock.sendPresenceUpdate(state, chat_id);
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleSendPresence(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
+    const { state } = payload;
+    if (!['available', 'unavailable'].includes(state)) {
+        return sendEvent('error', { message: `Not a presence state: ${state}` }, id);
+    }
+    try {
+        await sock.sendPresenceUpdate(state);
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+// Group management. Metadata replies are normalised to GroupMetadata in src/backend/groups.rs
+async function handleGroupList(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
+    try {
+        const groups = await sock.groupFetchAllParticipating();
+        sendEvent('ack', { groups: Object.values(groups).map(normalizeGroup) }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleGroupMetadata(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
+    try {
+        sendEvent('ack', normalizeGroup(await sock.groupMetadata(payload.group_id)), id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleGroupCreate(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
+    const { subject, participants } = payload;
+    try {
+        sendEvent('ack', normalizeGroup(await sock.groupCreate(subject, participants)), id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleGroupUpdateParticipants(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
+    const { group_id, participants, action } = payload;
+    try {
+        const results = await sock.groupParticipantsUpdate(group_id, participants, action);
+        const failed = results.filter((r) => r.status !== '200').map((r) => `${r.jid} (${r.status})`);
+        if (failed.length) {
+            return sendEvent('error', { message: `Could not ${action} ${failed.join(', ')}` }, id);
+        }
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleGroupUpdateSubject(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
+    try {
+        await sock.groupUpdateSubject(payload.group_id, payload.subject);
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleGroupUpdateDescription(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
+    try {
+        // An undefined description removes it
+        await sock.groupUpdateDescription(payload.group_id, payload.description ?? undefined);
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleGroupLeave(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
+    try {
+        await sock.groupLeave(payload.group_id);
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
 }
 
-async function handleSendMessage(payload) {
-    if (!sock) return;
-    const { jid, content } = payload;
+// Updates arrive through `presence.update`
+async function handlePresenceSubscribe(payload, id) {
+    if (!sock) return sendEvent('error', { message: 'Not connected' }, id);
     try {
-        await sock.sendMessage(jid, { text: content });
-        sendEvent('ack', { status: 'sent', jid });
+        await sock.presenceSubscribe(payload.jid);
+        sendEvent('ack', { status: 'sent' }, id);
     } catch (e) {
-        sendEvent('error', { message: e.message });
+        sendEvent('error', { message: e.message }, id);
     }
 }
 
@@ -88,11 +294,21 @@ async function handleDisconnect() {
         sock.end(undefined);
         sock = null;
     }
+    await cleanup();
     process.exit(0); // Exit to let Rust restart us
 }
 
+// Media files not yet picked up by Rust are lost with the process anyway
+async function cleanup() {
+    if (mediaDir) {
+        const dir = await mediaDir.catch(() => null);
+        mediaDir = null;
+        if (dir) await rm(dir, { recursive: true, force: true }).catch(() => {});
+    }
+}
+
 // Main Socket Logic
-async function startSock(initialCreds, initialKeys) {
+async function startSock(initialCreds, initialKeys, markOnline) {
     const { state, saveCreds } = createIPCAuthState(initialCreds, initialKeys);
     const { version, isLatest } = await fetchLatestBaileysVersion();
 
@@ -100,11 +316,12 @@ async function startSock(initialCreds, initialKeys) {
         version,
         logger,
         printQRInTerminal: false,
+        markOnlineOnConnect: markOnline,
         auth: state,
         generateHighQualityLinkPreview: true,
     });
 
-    sock.ev.on('connection.update', (update) => {
+    sock.ev.on('connection.update', async (update) => {
         const { connection, lastDisconnect, qr } = update;
 
         if (qr) {
@@ -112,19 +329,24 @@ async function startSock(initialCreds, initialKeys) {
         }
 
         if (connection === 'close') {
-            const shouldReconnect = (lastDisconnect?.error instanceof Boom)?.output?.statusCode !== DisconnectReason.loggedOut;
+            const statusCode = lastDisconnect?.error instanceof Boom ? lastDisconnect.error.output?.statusCode : undefined;
+            const shouldReconnect = statusCode !== DisconnectReason.loggedOut;
             if (shouldReconnect) {
                 // Do NOT recurse startSock here. State (keys) would be stale/lost.
                 // Instead, notify Rust to restart the process.
                 sendEvent('connection_status', 'disconnected_reconnecting');
+                await cleanup();
                 process.exit(0); // Rust backend should detect exit and restart if desired, or we rely on 'init' again.
             } else {
                  sendEvent('connection_status', 'disconnected');
                  sendEvent('auth_failure', 'logged_out');
-                 process.exit(0);
+                 await cleanup();
+                 process.exit(EXIT_CODE_LOGGED_OUT);
             }
         } else if (connection === 'open') {
              sendEvent('connection_status', 'connected');
+        } else if (connection === 'connecting') {
+             sendEvent('connection_status', 'connecting');
         }
     });
 
@@ -133,24 +355,273 @@ async function startSock(initialCreds, initialKeys) {
     sock.ev.on('messages.upsert', async (m) => {
         if (m.type === 'notify') {
             for (const msg of m.messages) {
-                if (!msg.key.fromMe) {
-                    const jid = msg.key.remoteJid;
-                    const name = msg.pushName || jid;
-                    const content = msg.message?.conversation || msg.message?.extendedTextMessage?.text || '[Media/Other]';
-
-                    sendEvent('message', {
-                        jid,
-                        name,
-                        content,
-                        timestamp: msg.messageTimestamp,
-                        raw: msg
+                // Edits arrive wrapped in a future-proof `editedMessage` on newer clients
+                const protocol = msg.message?.protocolMessage || msg.message?.editedMessage?.message?.protocolMessage;
+                if (protocol?.type === proto.Message.ProtocolMessage.Type.REVOKE) {
+                    sendEvent('revoke', {
+                        chat_id: msg.key.remoteJid,
+                        message_id: protocol.key?.id,
+                        revoked_by: senderOf(msg),
+                        timestamp: toUnix(msg.messageTimestamp),
                     });
+                    continue;
+                }
+                if (protocol?.type === proto.Message.ProtocolMessage.Type.MESSAGE_EDIT) {
+                    const editedAt = toUnix(protocol.timestampMs);
+                    sendEvent('edit', {
+                        chat_id: msg.key.remoteJid,
+                        message_id: protocol.key?.id,
+                        edited_by: senderOf(msg),
+                        content: extractContent(protocol.editedMessage),
+                        timestamp: editedAt ? Math.floor(editedAt / 1000) : toUnix(msg.messageTimestamp),
+                    });
+                    continue;
+                }
+                if (msg.message?.reactionMessage) {
+                    sendEvent('reaction', normalizeReaction(msg));
+                    continue;
+                }
+                const normalized = normalizeMessage(msg);
+                sendEvent('message', normalized);
+                if (['image', 'video', 'audio', 'document'].includes(normalized.content.kind)) {
+                    forwardMedia(msg, normalized);
                 }
             }
         }
     });
+
+    // Chats, contacts and recent messages pushed by the phone after pairing, in batches
+    sock.ev.on('messaging-history.set', ({ chats, contacts, messages, isLatest, progress }) => {
+        sendEvent('history_sync', {
+            chats: chats.map((chat) => ({
+                id: chat.id,
+                name: chat.name || null,
+                unread_count: chat.unreadCount || 0,
+                last_message_timestamp: chat.conversationTimestamp ? toUnix(chat.conversationTimestamp) : null,
+            })),
+            contacts: contacts.map((contact) => ({
+                jid: contact.id,
+                name: contact.name || null,
+                notify_name: contact.notify || contact.verifiedName || null,
+            })),
+            // Reactions, revokes and edits in history are already folded into the messages they target
+            messages: messages
+                .filter((msg) => msg.message && !msg.message.protocolMessage && !msg.message.reactionMessage)
+                .map(normalizeMessage),
+            progress: progress ?? null,
+            is_latest: !!isLatest,
+        });
+    });
+
+    // 1:1 delivery/read updates for our own messages
+    sock.ev.on('messages.update', (updates) => {
+        for (const { key, update } of updates) {
+            const kind = receiptKindFromStatus(update.status);
+            if (!kind || !key.fromMe) continue;
+            sendEvent('receipt', {
+                chat_id: key.remoteJid,
+                sender_id: key.remoteJid,
+                message_ids: [key.id],
+                kind,
+                timestamp: Math.floor(Date.now() / 1000),
+            });
+        }
+    });
+
+    // Per-participant receipts in groups
+    sock.ev.on('message-receipt.update', (updates) => {
+        for (const { key, receipt } of updates) {
+            const kind = receipt.playedTimestamp ? 'played' : receipt.readTimestamp ? 'read' : 'delivered';
+            sendEvent('receipt', {
+                chat_id: key.remoteJid,
+                sender_id: receipt.userJid,
+                message_ids: [key.id],
+                kind,
+                timestamp: toUnix(receipt.playedTimestamp || receipt.readTimestamp || receipt.receiptTimestamp),
+            });
+        }
+    });
+
+    sock.ev.on('presence.update', ({ id, presences }) => {
+        const isChat = id.endsWith('@g.us');
+        for (const [jid, presence] of Object.entries(presences)) {
+            sendEvent('presence', {
+                jid,
+                chat_id: isChat || ['composing', 'recording', 'paused'].includes(presence.lastKnownPresence) ? id : null,
+                state: presence.lastKnownPresence,
+                last_seen: presence.lastSeen ?? null,
+            });
+        }
+    });
+}
+
+// Message normalisation (mirrors ProviderEvent payloads on the Rust side)
+function toUnix(ts) {
+    if (!ts) return 0;
+    if (typeof ts === 'number') return ts;
+    return typeof ts.toNumber === 'function' ? ts.toNumber() : Number(ts);
 }
 
+function senderOf(msg) {
+    if (msg.key.participant) return msg.key.participant;
+    return msg.key.fromMe ? (sock?.user?.id || 'me') : msg.key.remoteJid;
+}
+
+function normalizeReaction(msg) {
+    const reaction = msg.message.reactionMessage;
+    const sentAt = toUnix(reaction.senderTimestampMs);
+    return {
+        chat_id: msg.key.remoteJid,
+        message_id: reaction.key?.id,
+        sender_id: senderOf(msg),
+        // An empty reaction text is a removal
+        emoji: reaction.text || null,
+        timestamp: sentAt ? Math.floor(sentAt / 1000) : toUnix(msg.messageTimestamp),
+    };
+}
+
+function normalizeGroup(meta) {
+    return {
+        id: meta.id,
+        subject: meta.subject || '',
+        description: meta.desc || null,
+        owner: meta.owner || null,
+        created_at: meta.creation || null,
+        participants: (meta.participants || []).map((p) => ({
+            jid: p.id,
+            role: p.admin === 'superadmin' ? 'super_admin' : p.admin === 'admin' ? 'admin' : 'member',
+        })),
+    };
+}
+
+function receiptKindFromStatus(status) {
+    switch (status) {
+        case proto.WebMessageInfo.Status.DELIVERY_ACK: return 'delivered';
+        case proto.WebMessageInfo.Status.READ: return 'read';
+        case proto.WebMessageInfo.Status.PLAYED: return 'played';
+        default: return null;
+    }
+}
+
+// Baileys builds `contextInfo` from a full message; rebuild a minimal one from what Rust stored
+function quotedMessage(jid, quoted) {
+    return {
+        key: {
+            remoteJid: jid,
+            id: quoted.message_id,
+            fromMe: quoted.from_me,
+            participant: jid.endsWith('@g.us') ? quoted.sender_id : undefined,
+        },
+        message: { conversation: quoted.content },
+    };
+}
+
+// Reply context of a message, if it quotes another one
+function extractQuoted(message) {
+    if (!message) return null;
+    if (message.ephemeralMessage) return extractQuoted(message.ephemeralMessage.message);
+    const context = Object.values(message).find((part) => part?.contextInfo?.stanzaId)?.contextInfo;
+    if (!context) return null;
+    return {
+        id: context.stanzaId,
+        sender_id: context.participant || null,
+        snippet: previewOf(extractContent(context.quotedMessage)),
+    };
+}
+
+// Mirrors MessageContent::preview on the Rust side
+function previewOf(content) {
+    switch (content.kind) {
+        case 'text': return content.text;
+        case 'image': return content.caption || '[Image]';
+        case 'video': return content.caption || '[Video]';
+        case 'audio': return content.voice_note ? '[Voice note]' : '[Audio]';
+        case 'document': return content.file_name || '[Document]';
+        case 'sticker': return '[Sticker]';
+        default: return '[Unsupported message]';
+    }
+}
+
+function extractContent(message) {
+    if (!message) return { kind: 'unsupported' };
+    if (message.ephemeralMessage) return extractContent(message.ephemeralMessage.message);
+    if (message.conversation) return { kind: 'text', text: message.conversation };
+    if (message.extendedTextMessage) return { kind: 'text', text: message.extendedTextMessage.text || '' };
+    if (message.imageMessage) {
+        return { kind: 'image', caption: message.imageMessage.caption || null, mime_type: message.imageMessage.mimetype || null };
+    }
+    if (message.videoMessage) {
+        return { kind: 'video', caption: message.videoMessage.caption || null, mime_type: message.videoMessage.mimetype || null };
+    }
+    if (message.audioMessage) {
+        return { kind: 'audio', mime_type: message.audioMessage.mimetype || null, voice_note: !!message.audioMessage.ptt };
+    }
+    if (message.documentMessage) {
+        return { kind: 'document', file_name: message.documentMessage.fileName || null, mime_type: message.documentMessage.mimetype || null };
+    }
+    if (message.stickerMessage) return { kind: 'sticker' };
+    return { kind: 'unsupported' };
+}
+
+// Streams the decrypted media into a temp file and hands its path to Rust, which
+// encrypts it into the media cache and deletes the file
+async function forwardMedia(msg, normalized) {
+    mediaDir ??= mkdtemp(join(tmpdir(), 'whaswapp-media-'));
+    const path = join(await mediaDir, `${Buffer.from(normalized.id).toString('hex')}.bin`);
+    try {
+        const stream = await downloadMediaMessage(msg, 'stream', {}, {
+            logger,
+            reuploadRequest: sock.updateMediaMessage
+        });
+        await pipeline(stream, createWriteStream(path, { mode: 0o600 }));
+        sendEvent('media', {
+            message_id: normalized.id,
+            chat_id: normalized.chat_id,
+            mime_type: normalized.content.mime_type || 'application/octet-stream',
+            file_name: normalized.content.file_name || null,
+            timestamp: normalized.timestamp,
+            path,
+        });
+    } catch (e) {
+        await rm(path, { force: true }).catch(() => {});
+        sendEvent('error', { message: `Media download failed for ${normalized.id}: ${e.message}` });
+    }
+}
+
+function normalizeMessage(msg) {
+    return {
+        id: msg.key.id,
+        chat_id: msg.key.remoteJid,
+        sender_id: senderOf(msg),
+        sender_name: msg.pushName || null,
+        timestamp: toUnix(msg.messageTimestamp),
+        from_me: !!msg.key.fromMe,
+        content: extractContent(msg.message),
+        quoted: extractQuoted(msg.message),
+    };
+}
+
+// Command table; its keys are advertised to Rust in the hello reply
+const handlers = {
+    hello: handleHello,
+    init: handleInit,
+    send_message: handleSendMessage,
+    send_media: handleSendMedia,
+    send_reaction: handleSendReaction,
+    send_receipt: handleSendReceipt,
+    send_chat_state: handleSendChatState,
+    send_presence: handleSendPresence,
+    presence_subscribe: handlePresenceSubscribe,
+    group_list: handleGroupList,
+    group_metadata: handleGroupMetadata,
+    group_create: handleGroupCreate,
+    group_update_participants: handleGroupUpdateParticipants,
+    group_update_subject: handleGroupUpdateSubject,
+    group_update_description: handleGroupUpdateDescription,
+    group_leave: handleGroupLeave,
+    disconnect: handleDisconnect,
+};
+
 // Stdin Reader (IPC)
 const rl = readline.createInterface({
     input: process.stdin,
@@ -159,23 +630,18 @@ const rl = readline.createInterface({
 });
 
 rl.on('line', async (line) => {
+    let command;
     try {
         if (!line.trim()) return;
-        const command = JSON.parse(line);
-        switch (command.type) {
-            case 'init':
-                await handleInit(command.payload);
-                break;
-            case 'send_message':
-                await handleSendMessage(command.payload);
-                break;
-            case 'disconnect':
-                await handleDisconnect();
-                break;
-            default:
-                break;
+        command = JSON.parse(line);
+        const handler = handlers[command.type];
+        if (!handler) {
+            sendEvent('error', { message: `Unknown command: ${command.type}` }, command.id);
+            return;
         }
+        await handler(command.payload, command.id);
     } catch (e) {
+        sendEvent('error', { message: e.message }, command?.id);
     }
 });
 
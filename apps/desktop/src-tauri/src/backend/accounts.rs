@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+/// Account of single-number installs. Its auth keys and session file keep the
+/// names used before multiple accounts were supported, so existing pairings survive
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+/// Library an account is connected through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    Rust,
+    Baileys,
+    Wwebjs,
+}
+
+impl BackendKind {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "rust" => Some(BackendKind::Rust),
+            "baileys" => Some(BackendKind::Baileys),
+            "wwebjs" | "whatsapp-web.js" => Some(BackendKind::Wwebjs),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackendKind::Rust => "rust",
+            BackendKind::Baileys => "baileys",
+            BackendKind::Wwebjs => "wwebjs",
+        }
+    }
+}
+
+/// Account ids end up in file names and `auth_store` keys, so only a safe subset is allowed
+pub fn validate_account_id(account_id: &str) -> Result<(), String> {
+    let valid = !account_id.is_empty()
+        && account_id.len() <= 64
+        && account_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid account id '{}': use 1-64 letters, digits, '-' or '_'", account_id))
+    }
+}
+
+/// `auth_store` key of `key` within an account's namespace
+pub fn scoped_key(account_id: &str, key: &str) -> String {
+    if account_id == DEFAULT_ACCOUNT {
+        key.to_string()
+    } else {
+        format!("{}/{}", account_id, key)
+    }
+}
+
+/// Name of a per-account file, e.g. `session.db` or `session-work.db`
+pub fn scoped_file(account_id: &str, stem: &str, extension: &str) -> String {
+    if account_id == DEFAULT_ACCOUNT {
+        format!("{}.{}", stem, extension)
+    } else {
+        format!("{}-{}.{}", stem, account_id, extension)
+    }
+}
@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::broadcast;
+use crate::storage::{MediaRecord, MessageStatus, OutboxEntry, ScheduledMessage};
+
+/// Name of the Tauri event every `ProviderEvent` is emitted under.
+/// The frontend dispatches on the `type` field of the payload.
+pub const BACKEND_EVENT: &str = "backend-event";
+
+/// Capacity of each provider's broadcast channel.
+pub const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Backend-agnostic events produced by a `WhatsAppProvider`.
+/// Every backend translates its native events into this enum so that
+/// consumers (UI forwarder, storage, ...) only deal with one shape.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum ProviderEvent {
+    Qr(String),
+    ConnectionStatus { status: ConnectionState },
+    Message(MessageEvent),
+    Receipt(ReceiptEvent),
+    /// Receipts moved one of our messages to a new status. Produced by storage, which
+    /// aggregates per-participant receipts in groups
+    MessageStatusChanged(MessageStatusEvent),
+    /// An outbox entry was queued or changed state. Produced by the account's outbox worker
+    OutboxUpdated(OutboxEntry),
+    /// A scheduled message came due and was queued or skipped. Produced by the account's scheduler
+    ScheduleUpdated(ScheduledMessage),
+    /// A message was deleted for everyone. Storage keeps its content and flags it instead
+    MessageRevoked(RevokeEvent),
+    /// A message was edited by its sender. Storage keeps every revision
+    MessageEdited(EditEvent),
+    Reaction(ReactionEvent),
+    Presence(PresenceEvent),
+    MediaReady(MediaReadyEvent),
+    /// A batch of chats, contacts and past messages sent by the phone after pairing.
+    /// Only consumed by storage; the frontend is told through `HistorySyncProgress`
+    HistorySync(HistorySyncEvent),
+    /// A history batch has been written to storage
+    HistorySyncProgress(HistorySyncProgress),
+    /// Unrecoverable backend problem the user has to act on
+    Error { message: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Reconnecting,
+    Disconnected,
+    LoggedOut,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageEvent {
+    pub id: String,
+    pub chat_id: String,
+    pub sender_id: String,
+    pub sender_name: Option<String>,
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+    pub from_me: bool,
+    pub content: MessageContent,
+    /// The message this one replies to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quoted: Option<QuotedMessage>,
+}
+
+/// Reference to a replied-to message, with enough of it to render a preview
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotedMessage {
+    pub id: String,
+    /// Author of the quoted message, when known
+    pub sender_id: Option<String>,
+    pub snippet: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MessageContent {
+    Text { text: String },
+    Image { caption: Option<String>, mime_type: Option<String> },
+    Video { caption: Option<String>, mime_type: Option<String> },
+    Audio { mime_type: Option<String>, voice_note: bool },
+    Document { file_name: Option<String>, mime_type: Option<String> },
+    Sticker,
+    Unsupported,
+}
+
+impl MessageContent {
+    /// Plain-text representation used for previews and storage.
+    pub fn preview(&self) -> String {
+        match self {
+            MessageContent::Text { text } => text.clone(),
+            MessageContent::Image { caption, .. } => caption.clone().unwrap_or_else(|| "[Image]".to_string()),
+            MessageContent::Video { caption, .. } => caption.clone().unwrap_or_else(|| "[Video]".to_string()),
+            MessageContent::Audio { voice_note: true, .. } => "[Voice note]".to_string(),
+            MessageContent::Audio { .. } => "[Audio]".to_string(),
+            MessageContent::Document { file_name, .. } => file_name.clone().unwrap_or_else(|| "[Document]".to_string()),
+            MessageContent::Sticker => "[Sticker]".to_string(),
+            MessageContent::Unsupported => "[Unsupported message]".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptKind {
+    Delivered,
+    Read,
+    Played,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptEvent {
+    pub chat_id: String,
+    /// The participant who produced the receipt
+    pub sender_id: String,
+    pub message_ids: Vec<String>,
+    pub kind: ReceiptKind,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageStatusEvent {
+    pub chat_id: String,
+    pub message_id: String,
+    pub status: MessageStatus,
+}
+
+impl From<ReceiptKind> for MessageStatus {
+    fn from(kind: ReceiptKind) -> Self {
+        match kind {
+            ReceiptKind::Delivered => MessageStatus::Delivered,
+            ReceiptKind::Read => MessageStatus::Read,
+            ReceiptKind::Played => MessageStatus::Played,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevokeEvent {
+    pub chat_id: String,
+    pub message_id: String,
+    pub revoked_by: String,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditEvent {
+    pub chat_id: String,
+    pub message_id: String,
+    pub edited_by: String,
+    /// The new content
+    pub content: MessageContent,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReactionEvent {
+    pub chat_id: String,
+    /// The message being reacted to
+    pub message_id: String,
+    pub sender_id: String,
+    /// `None` when the sender removed their reaction
+    pub emoji: Option<String>,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceState {
+    Available,
+    Unavailable,
+    Composing,
+    Recording,
+    Paused,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresenceEvent {
+    pub jid: String,
+    /// Set for chatstate updates (typing/recording), which are scoped to a chat
+    pub chat_id: Option<String>,
+    pub state: PresenceState,
+    pub last_seen: Option<i64>,
+}
+
+/// Emitted once received media has been downloaded into the `MediaCache`
+/// and can be fetched with the `get_media` command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaReadyEvent {
+    pub message_id: String,
+    pub chat_id: String,
+    pub mime_type: String,
+    pub size: i64,
+}
+
+impl From<&MediaRecord> for MediaReadyEvent {
+    fn from(record: &MediaRecord) -> Self {
+        Self {
+            message_id: record.message_id.clone(),
+            chat_id: record.chat_id.clone(),
+            mime_type: record.mime_type.clone(),
+            size: record.size,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistorySyncEvent {
+    #[serde(default)]
+    pub chats: Vec<HistoryChat>,
+    #[serde(default)]
+    pub contacts: Vec<ContactInfo>,
+    #[serde(default)]
+    pub messages: Vec<MessageEvent>,
+    /// Overall sync progress in percent, when the phone reports it
+    pub progress: Option<u32>,
+    /// Set on the last batch of the initial sync
+    #[serde(default)]
+    pub is_latest: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryChat {
+    pub id: String,
+    pub name: Option<String>,
+    pub unread_count: u32,
+    pub last_message_timestamp: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContactInfo {
+    pub jid: String,
+    /// Name from the phone's address book
+    pub name: Option<String>,
+    /// Name the contact chose for themselves
+    pub notify_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistorySyncProgress {
+    pub chats: usize,
+    pub contacts: usize,
+    pub messages: usize,
+    pub progress: Option<u32>,
+    pub is_latest: bool,
+}
+
+/// A `ProviderEvent` as the frontend receives it: `type`/`payload` plus the account it came from
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountEvent<'a> {
+    pub account_id: &'a str,
+    #[serde(flatten)]
+    pub event: &'a ProviderEvent,
+}
+
+/// Emits one event of `account_id` to the frontend as a `backend-event`
+pub fn emit_event(app_handle: &AppHandle, account_id: &str, event: &ProviderEvent) {
+    let _ = app_handle.emit(BACKEND_EVENT, AccountEvent { account_id, event });
+}
+
+/// Forwards every event of a provider to the frontend as a serialized
+/// `backend-event`. The task ends once the provider drops its sender.
+pub fn spawn_event_forwarder(app_handle: AppHandle, account_id: String, mut rx: broadcast::Receiver<ProviderEvent>) {
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                // History batches can hold thousands of messages; the UI reloads from storage instead
+                Ok(ProviderEvent::HistorySync(_)) => {}
+                Ok(event) => emit_event(&app_handle, &account_id, &event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    eprintln!("Event forwarder lagged, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
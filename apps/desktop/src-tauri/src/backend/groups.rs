@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use crate::storage::{Group, GroupParticipant};
+
+/// A group as reported by WhatsApp, with its full participant list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupMetadata {
+    pub id: String,
+    pub subject: String,
+    pub description: Option<String>,
+    /// Creator of the group, when WhatsApp still reports one
+    pub owner: Option<String>,
+    /// Unix timestamp in seconds
+    pub created_at: Option<i64>,
+    pub participants: Vec<GroupMember>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupMember {
+    pub jid: String,
+    pub role: ParticipantRole,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParticipantRole {
+    Member,
+    Admin,
+    /// The creator; cannot be demoted
+    SuperAdmin,
+}
+
+impl ParticipantRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParticipantRole::Member => "member",
+            ParticipantRole::Admin => "admin",
+            ParticipantRole::SuperAdmin => "super_admin",
+        }
+    }
+}
+
+/// Change applied to a set of participants
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParticipantAction {
+    Add,
+    Remove,
+    Promote,
+    Demote,
+}
+
+impl GroupMetadata {
+    /// Splits the metadata into its `groups` and `group_participants` rows
+    pub fn to_storage(&self) -> (Group, Vec<GroupParticipant>) {
+        let group = Group {
+            id: self.id.clone(),
+            subject: self.subject.clone(),
+            description: self.description.clone(),
+            owner: self.owner.clone(),
+            created_at: self.created_at,
+        };
+        let participants = self.participants
+            .iter()
+            .map(|p| GroupParticipant {
+                group_id: self.id.clone(),
+                jid: p.jid.clone(),
+                role: p.role.as_str().to_string(),
+            })
+            .collect();
+        (group, participants)
+    }
+}
@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use crate::backend::MediaKind;
+use crate::backend::groups::{GroupMetadata, ParticipantAction};
+use crate::backend::events::{
+    EditEvent, HistorySyncEvent, MessageEvent, PresenceEvent, PresenceState, ReactionEvent, ReceiptEvent, RevokeEvent,
+};
+
+/// Version of the line-delimited JSON protocol spoken with the Node sidecars.
+/// Every command carries an `id`; the sidecar answers with `ack`/`error` (or `hello`)
+/// echoing it. Bump on any incompatible change to the types below: renamed, removed or
+/// retyped fields (v2: media arrives as a temp file `path` instead of inline data).
+/// Additive changes (new optional fields, new events) stay within a version; new
+/// commands the core depends on are gated by `REQUIRED_COMMANDS` instead.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Exit code a sidecar uses when the account was logged out. Checked in addition to
+/// the `auth_failure` event, which may not be flushed before the process exits.
+pub const EXIT_CODE_LOGGED_OUT: i32 = 3;
+
+/// Commands the core relies on; a sidecar missing any of them is rejected at handshake
+pub const REQUIRED_COMMANDS: &[&str] = &[
+    "hello",
+    "init",
+    "send_message",
+    "send_media",
+    "send_reaction",
+    "send_receipt",
+    "send_chat_state",
+    "send_presence",
+    "presence_subscribe",
+    "group_list",
+    "group_metadata",
+    "group_create",
+    "group_update_participants",
+    "group_update_subject",
+    "group_update_description",
+    "group_leave",
+    "disconnect",
+];
+
+#[derive(Debug, Serialize)]
+pub struct IpcCommand {
+    pub id: u64,
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum Command {
+    Hello { protocol_version: u32 },
+    Init {
+        auth_data: AuthData,
+        /// Whether to announce `available` on connect; false under ghost mode
+        mark_online: bool,
+    },
+    SendMessage {
+        jid: String,
+        content: String,
+        /// Set when replying to a message
+        #[serde(skip_serializing_if = "Option::is_none")]
+        quoted: Option<QuotedTarget>,
+    },
+    SendMedia {
+        jid: String,
+        path: PathBuf,
+        kind: MediaKind,
+        mime_type: String,
+        caption: Option<String>,
+    },
+    SendReaction {
+        chat_id: String,
+        message_id: String,
+        /// Whether the target message is ours; part of its key
+        from_me: bool,
+        /// Author of the target message in groups
+        sender_id: Option<String>,
+        /// Empty to remove our reaction
+        emoji: String,
+    },
+    SendReceipt {
+        chat_id: String,
+        sender_id: Option<String>,
+        message_ids: Vec<String>,
+    },
+    SendChatState { chat_id: String, state: PresenceState },
+    SendPresence { state: PresenceState },
+    PresenceSubscribe { jid: String },
+    GroupList,
+    GroupMetadata { group_id: String },
+    GroupCreate { subject: String, participants: Vec<String> },
+    GroupUpdateParticipants {
+        group_id: String,
+        participants: Vec<String>,
+        action: ParticipantAction,
+    },
+    GroupUpdateSubject { group_id: String, subject: String },
+    GroupUpdateDescription { group_id: String, description: Option<String> },
+    GroupLeave { group_id: String },
+    Disconnect,
+}
+
+impl Command {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Hello { .. } => "hello",
+            Command::Init { .. } => "init",
+            Command::SendMessage { .. } => "send_message",
+            Command::SendMedia { .. } => "send_media",
+            Command::SendReaction { .. } => "send_reaction",
+            Command::SendReceipt { .. } => "send_receipt",
+            Command::SendChatState { .. } => "send_chat_state",
+            Command::SendPresence { .. } => "send_presence",
+            Command::PresenceSubscribe { .. } => "presence_subscribe",
+            Command::GroupList => "group_list",
+            Command::GroupMetadata { .. } => "group_metadata",
+            Command::GroupCreate { .. } => "group_create",
+            Command::GroupUpdateParticipants { .. } => "group_update_participants",
+            Command::GroupUpdateSubject { .. } => "group_update_subject",
+            Command::GroupUpdateDescription { .. } => "group_update_description",
+            Command::GroupLeave { .. } => "group_leave",
+            Command::Disconnect => "disconnect",
+        }
+    }
+}
+
+/// The replied-to message, described well enough for the adapter to rebuild its key
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotedTarget {
+    pub message_id: String,
+    pub from_me: bool,
+    /// Author of the quoted message
+    pub sender_id: String,
+    /// Stored text of the quoted message, shown in the quote preview
+    pub content: String,
+}
+
+/// Persisted sidecar auth state. The contents are owned by the sidecar library
+/// and are stored verbatim, so they stay untyped. Each adapter only reads the
+/// fields of its own `AuthScheme`.
+#[derive(Debug, Default, Serialize)]
+pub struct AuthData {
+    /// Baileys credentials
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creds: Option<Value>,
+    /// Baileys Signal key set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keys: Option<Value>,
+    /// whatsapp-web.js `RemoteAuth` session archive
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IpcEvent {
+    /// Echo of the command id, set on replies only
+    #[serde(default)]
+    pub id: Option<u64>,
+    #[serde(flatten)]
+    pub event: SidecarEvent,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum SidecarEvent {
+    Hello(Hello),
+    Ack(Value),
+    Error(ErrorPayload),
+    QrCode(String),
+    ConnectionStatus(SidecarConnectionStatus),
+    AuthFailure(String),
+    AuthUpdate(AuthUpdate),
+    Message(MessageEvent),
+    Receipt(ReceiptEvent),
+    Revoke(RevokeEvent),
+    Edit(EditEvent),
+    Reaction(ReactionEvent),
+    HistorySync(HistorySyncEvent),
+    Presence(PresenceEvent),
+    Media(MediaPayload),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Hello {
+    pub protocol_version: u32,
+    pub adapter: String,
+    pub adapter_version: String,
+    pub commands: Vec<String>,
+}
+
+impl Hello {
+    /// Checks the sidecar against this core, returning a user-facing explanation on mismatch.
+    pub fn check_compatibility(&self) -> Result<(), String> {
+        if self.protocol_version != PROTOCOL_VERSION {
+            return Err(format!(
+                "The bundled {} adapter ({}) speaks IPC protocol v{}, but this WhaSwapp core expects v{}. Reinstall or update the adapter.",
+                self.adapter, self.adapter_version, self.protocol_version, PROTOCOL_VERSION
+            ));
+        }
+
+        let missing: Vec<&str> = REQUIRED_COMMANDS
+            .iter()
+            .filter(|c| !self.commands.iter().any(|s| s == *c))
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "The bundled {} adapter ({}) does not support required commands: {}",
+                self.adapter, self.adapter_version, missing.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ErrorPayload {
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SidecarConnectionStatus {
+    Connecting,
+    Connected,
+    DisconnectedReconnecting,
+    Disconnected,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum AuthUpdate {
+    Creds(Value),
+    Keys(Value),
+    Session(Value),
+    SessionRemoved,
+}
+
+/// Reply to `send_message` / `send_media`
+#[derive(Debug, Deserialize)]
+pub struct SentAck {
+    pub message_id: String,
+}
+
+/// Reply to `group_list`. `group_metadata` and `group_create` reply with a bare `GroupMetadata`
+#[derive(Debug, Deserialize)]
+pub struct GroupListAck {
+    pub groups: Vec<GroupMetadata>,
+}
+
+/// Decrypted media the adapter wrote to a file in its private `whaswapp-media-*` temp
+/// directory; files anywhere else are ignored. Sending the path keeps large files off the stdout line protocol
+#[derive(Debug, Deserialize)]
+pub struct MediaPayload {
+    pub message_id: String,
+    pub chat_id: String,
+    pub mime_type: String,
+    pub file_name: Option<String>,
+    pub timestamp: i64,
+    /// Deleted once the media is cached
+    pub path: PathBuf,
+}
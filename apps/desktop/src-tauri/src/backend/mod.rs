@@ -1,8 +1,24 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::sync::broadcast;
 
+pub mod accounts;
 pub mod rust;
 pub mod baileys;
+pub mod wwebjs;
+pub mod events;
+pub mod groups;
+pub mod ipc;
+pub mod outbox;
+pub mod scheduler;
+pub mod privacy;
+pub mod sidecar;
+pub mod supervisor;
+
+use events::{PresenceState, ProviderEvent};
+use groups::{GroupMetadata, ParticipantAction};
+use supervisor::SupervisorHealth;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
@@ -12,13 +28,89 @@ pub struct Message {
     pub from_me: bool,
 }
 
+/// Kind of media attachment, derived from its mime type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    Image,
+    Video,
+    Audio,
+    Document,
+}
+
+impl MediaKind {
+    pub fn from_mime(mime_type: &str) -> Self {
+        match mime_type.split('/').next().unwrap_or_default() {
+            "image" => MediaKind::Image,
+            "video" => MediaKind::Video,
+            "audio" => MediaKind::Audio,
+            _ => MediaKind::Document,
+        }
+    }
+}
+
+/// Current time in seconds since the Unix epoch, the unit of every stored timestamp
+pub fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
 #[async_trait]
 pub trait WhatsAppProvider: Send + Sync {
     /// Initialize the provider (e.g., spawn process, connect to WebSocket)
     async fn initialize(&self, payload: String) -> anyhow::Result<()>;
 
-    /// Send a message
-    async fn send_message(&self, jid: String, content: String) -> anyhow::Result<()>;
+    /// Send a message, returning the id WhatsApp assigned to it
+    async fn send_message(&self, jid: String, content: String) -> anyhow::Result<String>;
+
+    /// Send a text message quoting `quoted_id` in the same chat, returning its id
+    async fn send_reply(&self, jid: String, content: String, quoted_id: String) -> anyhow::Result<String>;
+
+    /// Send a local file as an image, video, audio or document message, returning its id
+    async fn send_media(&self, jid: String, path: PathBuf, mime_type: String, caption: Option<String>) -> anyhow::Result<String>;
+
+    /// React to a message with an emoji; an empty emoji removes our reaction
+    async fn react(&self, chat_id: String, message_id: String, emoji: String) -> anyhow::Result<()>;
+
+    /// Mark messages as read. Skipped when ghost mode hides read receipts for the chat
+    async fn send_read_receipt(&self, chat_id: String, sender_id: Option<String>, message_ids: Vec<String>) -> anyhow::Result<()>;
+
+    /// Send a `Composing`, `Recording` or `Paused` indicator. Skipped when ghost mode hides chatstate
+    async fn set_typing(&self, chat_id: String, state: PresenceState) -> anyhow::Result<()>;
+
+    /// Set our online status. `Available` is skipped when ghost mode hides presence
+    async fn send_presence(&self, state: PresenceState) -> anyhow::Result<()>;
+
+    /// Ask WhatsApp for `jid`'s online status and last seen, delivered as `Presence` events.
+    /// Skipped when ghost mode hides presence: WhatsApp only answers clients that are online themselves
+    async fn subscribe_presence(&self, jid: String) -> anyhow::Result<()>;
+
+    /// Every group we are a member of
+    async fn get_groups(&self) -> anyhow::Result<Vec<GroupMetadata>>;
+
+    async fn group_metadata(&self, group_id: String) -> anyhow::Result<GroupMetadata>;
+
+    async fn create_group(&self, subject: String, participants: Vec<String>) -> anyhow::Result<GroupMetadata>;
+
+    /// Add, remove, promote or demote participants. Requires admin rights
+    async fn update_group_participants(&self, group_id: String, participants: Vec<String>, action: ParticipantAction) -> anyhow::Result<()>;
+
+    async fn set_group_subject(&self, group_id: String, subject: String) -> anyhow::Result<()>;
+
+    /// `None` clears the description
+    async fn set_group_description(&self, group_id: String, description: Option<String>) -> anyhow::Result<()>;
+
+    async fn leave_group(&self, group_id: String) -> anyhow::Result<()>;
+
+    /// Subscribe to the provider's event stream
+    fn subscribe(&self) -> broadcast::Receiver<ProviderEvent>;
+
+    /// Supervision status for backends running a sidecar process
+    fn health(&self) -> Option<SupervisorHealth> {
+        None
+    }
 
     /// Cleanup
     async fn disconnect(&self) -> anyhow::Result<()>;
@@ -0,0 +1,207 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tauri::AppHandle;
+use tokio::sync::{broadcast, Notify};
+use crate::backend::{unix_now, WhatsAppProvider};
+use crate::backend::events::{emit_event, ConnectionState, ProviderEvent};
+use crate::storage::{OutboxEntry, OutboxState, SqliteStorage, Storage};
+
+/// Longest the worker sleeps without an event, so it notices its provider went away
+const IDLE_WAKEUP: Duration = Duration::from_secs(30);
+
+/// Retry rules for outbox entries that failed while the account was connected
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Attempts before an entry is marked `Failed`
+    pub max_attempts: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_secs(5),
+            max_delay: Duration::from_secs(300),
+            max_attempts: 8,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay after failed attempt number `attempt` (1-based), or `None` once out of attempts
+    pub fn backoff(&self, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        Some(self.base_delay.saturating_mul(factor).min(self.max_delay))
+    }
+}
+
+/// What a caller wants to send; becomes an `OutboxEntry` once queued
+pub struct OutgoingMessage {
+    pub chat_id: String,
+    pub content: String,
+    pub quoted_id: Option<String>,
+    pub media: Option<(PathBuf, String)>,
+}
+
+/// Persistent per-account send queue. Every outgoing message is stored first and then
+/// delivered by a worker while the provider reports `Connected`, so nothing is lost
+/// while the socket is down or a sidecar restarts. Progress is emitted as `OutboxUpdated`.
+pub struct Outbox {
+    account_id: String,
+    app_handle: AppHandle,
+    storage: Arc<SqliteStorage>,
+    policy: RetryPolicy,
+    wake: Notify,
+}
+
+impl Outbox {
+    /// Starts the worker. It holds the provider weakly and stops once the manager drops it
+    pub fn spawn(app_handle: AppHandle, account_id: String, storage: Arc<SqliteStorage>, provider: &Arc<dyn WhatsAppProvider>) -> Arc<Self> {
+        let outbox = Arc::new(Self {
+            account_id,
+            app_handle,
+            storage,
+            policy: RetryPolicy::default(),
+            wake: Notify::new(),
+        });
+
+        let worker = outbox.clone();
+        let events = provider.subscribe();
+        let provider = Arc::downgrade(provider);
+        tokio::spawn(async move {
+            worker.run(provider, events).await;
+        });
+
+        outbox
+    }
+
+    pub async fn enqueue(&self, message: OutgoingMessage) -> anyhow::Result<OutboxEntry> {
+        let now = unix_now();
+        let (media_path, mime_type) = match message.media {
+            Some((path, mime_type)) => (Some(path.to_string_lossy().into_owned()), Some(mime_type)),
+            None => (None, None),
+        };
+        let entry = self.storage.enqueue_outbox(OutboxEntry {
+            id: 0,
+            account_id: self.account_id.clone(),
+            chat_id: message.chat_id,
+            content: message.content,
+            quoted_id: message.quoted_id,
+            media_path,
+            mime_type,
+            state: OutboxState::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            message_id: None,
+            created_at: now,
+        }).await.map_err(|e| anyhow::anyhow!("Storage error: {}", e))?;
+
+        self.wake.notify_one();
+        Ok(entry)
+    }
+
+    /// Picks up an entry that storage queued directly, e.g. a scheduled message that came due
+    pub fn queued(&self, entry: &OutboxEntry) {
+        self.publish(entry);
+        self.wake.notify_one();
+    }
+
+    pub async fn retry(&self, id: i64) -> anyhow::Result<OutboxEntry> {
+        let entry = self.storage.retry_outbox(&self.account_id, id, unix_now()).await
+            .map_err(|e| anyhow::anyhow!("Storage error: {}", e))?
+            .ok_or_else(|| anyhow::anyhow!("Outbox entry {} is not waiting to be sent", id))?;
+        self.publish(&entry);
+        self.wake.notify_one();
+        Ok(entry)
+    }
+
+    async fn run(&self, provider: Weak<dyn WhatsAppProvider>, mut events: broadcast::Receiver<ProviderEvent>) {
+        // Whatever was in flight when the app stopped may not have gone out
+        if let Err(e) = self.storage.requeue_outbox(&self.account_id).await {
+            eprintln!("Failed to requeue outbox of {}: {}", self.account_id, e);
+        }
+
+        let mut connected = false;
+        loop {
+            if connected {
+                let Some(provider) = provider.upgrade() else { break };
+                self.flush(provider.as_ref()).await;
+            }
+
+            let idle = match self.storage.next_outbox_attempt(&self.account_id).await {
+                Ok(Some(due)) if connected => Duration::from_secs(due.saturating_sub(unix_now()).max(0) as u64).min(IDLE_WAKEUP),
+                _ => IDLE_WAKEUP,
+            };
+
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(ProviderEvent::ConnectionStatus { status }) => {
+                        connected = status == ConnectionState::Connected;
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = self.wake.notified() => {}
+                _ = tokio::time::sleep(idle) => {}
+            }
+
+            if provider.strong_count() == 0 {
+                break;
+            }
+        }
+    }
+
+    /// Sends every due entry, one at a time and in order
+    async fn flush(&self, provider: &dyn WhatsAppProvider) {
+        loop {
+            let entry = match self.storage.claim_outbox(&self.account_id, unix_now()).await {
+                Ok(Some(entry)) => entry,
+                Ok(None) => break,
+                Err(e) => {
+                    eprintln!("Failed to read outbox of {}: {}", self.account_id, e);
+                    break;
+                }
+            };
+            self.publish(&entry);
+
+            let updated = match send(provider, &entry).await {
+                Ok(message_id) => self.storage.finish_outbox(entry.id, &message_id).await,
+                Err(e) => {
+                    let retry_at = self.policy
+                        .backoff(entry.attempts + 1)
+                        .map(|delay| unix_now() + delay.as_secs() as i64);
+                    self.storage.fail_outbox(entry.id, &e.to_string(), retry_at).await
+                }
+            };
+            match updated {
+                Ok(Some(entry)) => self.publish(&entry),
+                Ok(None) => {}
+                Err(e) => eprintln!("Failed to update outbox entry {}: {}", entry.id, e),
+            }
+        }
+    }
+
+    fn publish(&self, entry: &OutboxEntry) {
+        emit_event(&self.app_handle, &self.account_id, &ProviderEvent::OutboxUpdated(entry.clone()));
+    }
+}
+
+async fn send(provider: &dyn WhatsAppProvider, entry: &OutboxEntry) -> anyhow::Result<String> {
+    let chat_id = entry.chat_id.clone();
+    let content = entry.content.clone();
+    match (&entry.media_path, &entry.quoted_id) {
+        (Some(path), _) => {
+            let caption = (!content.is_empty()).then_some(content);
+            let mime_type = entry.mime_type.clone().unwrap_or_else(|| "application/octet-stream".to_string());
+            provider.send_media(chat_id, PathBuf::from(path), mime_type, caption).await
+        }
+        (None, Some(quoted_id)) => provider.send_reply(chat_id, content, quoted_id.clone()).await,
+        (None, None) => provider.send_message(chat_id, content).await,
+    }
+}
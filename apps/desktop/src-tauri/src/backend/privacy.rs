@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::RwLock;
+use crate::storage::{PrivacyRule, Storage, GLOBAL_PRIVACY_SCOPE};
+
+/// Outgoing stanzas that ghost mode can suppress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrivacyAction {
+    /// Blue ticks
+    ReadReceipt,
+    /// Typing / recording indicators
+    ChatState,
+    /// Online status
+    Presence,
+}
+
+/// Ghost mode settings, consulted by every provider before it sends a read
+/// receipt, chatstate or presence stanza. Rules live in storage and are
+/// mirrored here so checks never touch the database.
+#[derive(Default)]
+pub struct PrivacyPolicy {
+    rules: RwLock<HashMap<String, PrivacyRule>>,
+}
+
+impl PrivacyPolicy {
+    pub async fn load(storage: &dyn Storage) -> anyhow::Result<Self> {
+        let rules = storage.get_privacy_rules().await
+            .map_err(|e| anyhow::anyhow!("Storage error: {}", e))?;
+        Ok(Self::from_rules(rules))
+    }
+
+    pub fn from_rules(rules: Vec<PrivacyRule>) -> Self {
+        Self {
+            rules: RwLock::new(rules.into_iter().map(|r| (r.scope.clone(), r)).collect()),
+        }
+    }
+
+    pub fn rules(&self) -> Vec<PrivacyRule> {
+        self.rules.read().unwrap().values().cloned().collect()
+    }
+
+    /// Stores a rule and applies it immediately
+    pub async fn set_rule(&self, storage: &dyn Storage, rule: PrivacyRule) -> anyhow::Result<()> {
+        storage.save_privacy_rule(rule.clone()).await
+            .map_err(|e| anyhow::anyhow!("Storage error: {}", e))?;
+        self.rules.write().unwrap().insert(rule.scope.clone(), rule);
+        Ok(())
+    }
+
+    pub async fn remove_rule(&self, storage: &dyn Storage, scope: &str) -> anyhow::Result<()> {
+        storage.remove_privacy_rule(scope).await
+            .map_err(|e| anyhow::anyhow!("Storage error: {}", e))?;
+        self.rules.write().unwrap().remove(scope);
+        Ok(())
+    }
+
+    /// Whether `action` may be sent, optionally scoped to a chat.
+    /// A chat rule wins over the global one; with neither, everything is allowed.
+    pub fn allows(&self, action: PrivacyAction, chat_id: Option<&str>) -> bool {
+        let rules = self.rules.read().unwrap();
+        let field = |rule: &PrivacyRule| match action {
+            PrivacyAction::ReadReceipt => rule.read_receipts,
+            PrivacyAction::ChatState => rule.chat_state,
+            PrivacyAction::Presence => rule.presence,
+        };
+
+        let chat_rule = match action {
+            PrivacyAction::Presence => None,
+            _ => chat_id.and_then(|id| rules.get(id)).and_then(field),
+        };
+
+        chat_rule
+            .or_else(|| rules.get(GLOBAL_PRIVACY_SCOPE).and_then(field))
+            .unwrap_or(true)
+    }
+
+    /// Runs `send` only if `action` is allowed. The future is dropped unpolled
+    /// otherwise, so a suppressed stanza never reaches the wire.
+    /// Returns whether it was sent.
+    pub async fn gate<F>(&self, action: PrivacyAction, chat_id: Option<&str>, send: F) -> anyhow::Result<bool>
+    where
+        F: Future<Output = anyhow::Result<()>>,
+    {
+        if !self.allows(action, chat_id) {
+            return Ok(false);
+        }
+        send.await?;
+        Ok(true)
+    }
+}
@@ -1,60 +1,394 @@
 use async_trait::async_trait;
-use tauri::{AppHandle, Emitter, Manager};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use std::sync::{Arc, Weak};
+use tokio::sync::{broadcast, Mutex};
 use whatsapp_rust::{Client, store::SqliteStore};
 use whatsapp_rust::store::persistence_manager::PersistenceManager;
 use whatsapp_rust::transport::{TokioWebSocketTransportFactory, UreqHttpClient};
 use whatsapp_rust::waproto::whatsapp as wa;
+use whatsapp_rust::download::MediaType;
 use wacore_binary::jid::Jid;
 use whatsapp_rust::types::events::{Event, EventHandler};
-use crate::backend::WhatsAppProvider;
+use whatsapp_rust::types::message::MessageInfo;
+use whatsapp_rust::types::presence::{ChatPresence, ChatPresenceMedia, Presence, ReceiptType};
+use whatsapp_rust::features::groups::{GroupCreateOptions, GroupInfo};
+use crate::backend::{unix_now, MediaKind, WhatsAppProvider};
+use crate::backend::groups::{GroupMember, GroupMetadata, ParticipantAction, ParticipantRole};
+use crate::backend::accounts::scoped_file;
+use crate::backend::privacy::{PrivacyAction, PrivacyPolicy};
+use crate::backend::events::{
+    ConnectionState, ContactInfo, HistoryChat, HistorySyncEvent, MessageContent, MessageEvent, PresenceEvent, PresenceState, ProviderEvent,
+    QuotedMessage, ReactionEvent, ReceiptEvent, ReceiptKind, RevokeEvent, EditEvent, EVENT_CHANNEL_CAPACITY,
+};
+use crate::storage::{MediaCache, SqliteStorage, Storage};
 use crate::utils::security::SecurityManager;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 pub struct RustBackend {
+    account_id: String,
     client: Arc<Mutex<Option<Arc<Client>>>>,
     app_handle: AppHandle,
     security: Arc<SecurityManager>,
+    events: broadcast::Sender<ProviderEvent>,
+    media: Arc<MediaCache>,
+    privacy: Arc<PrivacyPolicy>,
+    storage: Arc<SqliteStorage>,
 }
 
 impl RustBackend {
-    pub fn new(app_handle: AppHandle) -> Self {
+    pub fn new(app_handle: AppHandle, account_id: String) -> Self {
         let security = app_handle.state::<Arc<SecurityManager>>().inner().clone();
+        let media = app_handle.state::<Arc<MediaCache>>().inner().clone();
+        let privacy = app_handle.state::<Arc<PrivacyPolicy>>().inner().clone();
+        let storage = app_handle.state::<Arc<SqliteStorage>>().inner().clone();
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
         Self {
+            account_id,
             client: Arc::new(Mutex::new(None)),
             app_handle,
             security,
+            events,
+            media,
+            privacy,
+            storage,
         }
     }
+
+    /// whatsapp-rust's own session database for an account
+    pub fn session_path(app_handle: &AppHandle, account_id: &str) -> tauri::Result<PathBuf> {
+        Ok(app_handle.path().app_data_dir()?.join(scoped_file(account_id, "session", "db")))
+    }
+
+    async fn client(&self) -> anyhow::Result<Arc<Client>> {
+        self.client.lock().await.clone().ok_or_else(|| anyhow::anyhow!("Client not initialized"))
+    }
+
+    /// Publishes a message sent from this device so it reaches storage and the UI.
+    fn emit_outgoing(&self, id: String, chat_id: String, content: MessageContent, quoted: Option<QuotedMessage>) {
+        let _ = self.events.send(ProviderEvent::Message(MessageEvent {
+            id,
+            chat_id,
+            sender_id: "me".to_string(),
+            sender_name: None,
+            timestamp: unix_now(),
+            from_me: true,
+            content,
+            quoted,
+        }));
+    }
 }
 
-struct TauriEventHandler {
-    app_handle: AppHandle,
+fn parse_jid(jid: &str) -> anyhow::Result<Jid> {
+    Jid::from_str(jid).map_err(|e| anyhow::anyhow!("Invalid JID: {}", e))
 }
 
-impl EventHandler for TauriEventHandler {
+fn translate_group(info: GroupInfo) -> GroupMetadata {
+    GroupMetadata {
+        id: info.id.to_string(),
+        subject: info.subject,
+        description: info.description.filter(|d| !d.is_empty()),
+        owner: info.creator.map(|jid| jid.to_string()),
+        created_at: info.creation_time.map(|t| t as i64),
+        participants: info.participants
+            .into_iter()
+            .map(|p| GroupMember {
+                jid: p.jid.to_string(),
+                role: if p.is_super_admin {
+                    ParticipantRole::SuperAdmin
+                } else if p.is_admin {
+                    ParticipantRole::Admin
+                } else {
+                    ParticipantRole::Member
+                },
+            })
+            .collect(),
+    }
+}
+
+struct ProviderEventHandler {
+    account_id: String,
+    events: broadcast::Sender<ProviderEvent>,
+    // Weak to avoid a cycle: the client owns this handler
+    client: Weak<Client>,
+    media: Arc<MediaCache>,
+    privacy: Arc<PrivacyPolicy>,
+}
+
+impl EventHandler for ProviderEventHandler {
     fn handle_event(&self, event: &Event) {
-         match event {
-            Event::PairingQrCode { code, .. } => {
-                let _ = self.app_handle.emit("qr_code", code);
+        if let Event::Message(msg, info) = event {
+            self.spawn_media_download(msg, info);
+        }
+        if let Event::Connected(_) = event {
+            self.enforce_presence_on_connect();
+        }
+        if let Some(event) = translate_event(event) {
+            let _ = self.events.send(event);
+        }
+    }
+}
+
+impl ProviderEventHandler {
+    /// The client announces itself as available on connect; take that back under ghost mode.
+    fn enforce_presence_on_connect(&self) {
+        if self.privacy.allows(PrivacyAction::Presence, None) {
+            return;
+        }
+        let Some(client) = self.client.upgrade() else { return };
+        tokio::spawn(async move {
+            if let Err(e) = client.send_presence(Presence::Unavailable).await {
+                eprintln!("Failed to hide presence: {}", e);
             }
-            Event::Connected(_) => {
-                let _ = self.app_handle.emit("connection_status", "connected");
+        });
+    }
+
+    /// Downloads and decrypts attached media in the background, then hands it to the `MediaCache`.
+    fn spawn_media_download(&self, msg: &wa::Message, info: &MessageInfo) {
+        let has_media = msg.image_message.is_some()
+            || msg.video_message.is_some()
+            || msg.audio_message.is_some()
+            || msg.document_message.is_some();
+        if !has_media {
+            return;
+        }
+        let Some(client) = self.client.upgrade() else { return };
+
+        let msg = msg.clone();
+        let message_id = info.id.clone();
+        let chat_id = info.source.chat.to_string();
+        let timestamp = info.timestamp.timestamp();
+        let account_id = self.account_id.clone();
+        let media = self.media.clone();
+        let events = self.events.clone();
+
+        tokio::spawn(async move {
+            let (data, mime_type, file_name) = if let Some(m) = msg.image_message.as_deref() {
+                (client.download(m).await, m.mimetype.clone(), None)
+            } else if let Some(m) = msg.video_message.as_deref() {
+                (client.download(m).await, m.mimetype.clone(), None)
+            } else if let Some(m) = msg.audio_message.as_deref() {
+                (client.download(m).await, m.mimetype.clone(), None)
+            } else if let Some(m) = msg.document_message.as_deref() {
+                (client.download(m).await, m.mimetype.clone(), m.file_name.clone())
+            } else {
+                return;
+            };
+
+            let data = match data {
+                Ok(d) => d,
+                Err(e) => {
+                    eprintln!("Media download failed for {}: {}", message_id, e);
+                    return;
+                }
+            };
+            let mime_type = mime_type.unwrap_or_else(|| "application/octet-stream".to_string());
+
+            match media.store(&account_id, &message_id, &chat_id, &mime_type, file_name, timestamp, &data).await {
+                Ok(record) => {
+                    let _ = events.send(ProviderEvent::MediaReady((&record).into()));
+                }
+                Err(e) => eprintln!("Failed to cache media for {}: {}", message_id, e),
             }
-            Event::Disconnected(_) => {
-                 let _ = self.app_handle.emit("connection_status", "disconnected");
+        });
+    }
+}
+
+/// Maps a native whatsapp-rust event onto the backend-agnostic `ProviderEvent`.
+fn translate_event(event: &Event) -> Option<ProviderEvent> {
+    match event {
+        Event::PairingQrCode { code, .. } => Some(ProviderEvent::Qr(code.clone())),
+        Event::Connected(_) => Some(ProviderEvent::ConnectionStatus { status: ConnectionState::Connected }),
+        Event::Disconnected(_) => Some(ProviderEvent::ConnectionStatus { status: ConnectionState::Disconnected }),
+        Event::LoggedOut(_) => Some(ProviderEvent::ConnectionStatus { status: ConnectionState::LoggedOut }),
+        Event::Message(msg, info) => Some(translate_message(msg, info)),
+        Event::HistorySync(sync) => Some(ProviderEvent::HistorySync(translate_history(sync))),
+        Event::Receipt(receipt) => {
+            let kind = match receipt.r#type {
+                ReceiptType::Delivered => ReceiptKind::Delivered,
+                ReceiptType::Read | ReceiptType::ReadSelf => ReceiptKind::Read,
+                ReceiptType::Played => ReceiptKind::Played,
+                _ => return None,
+            };
+            Some(ProviderEvent::Receipt(ReceiptEvent {
+                chat_id: receipt.source.chat.to_string(),
+                sender_id: receipt.source.sender.to_string(),
+                message_ids: receipt.message_ids.clone(),
+                kind,
+                timestamp: receipt.timestamp.timestamp(),
+            }))
+        }
+        Event::Presence(update) => Some(ProviderEvent::Presence(PresenceEvent {
+            jid: update.from.to_string(),
+            chat_id: None,
+            state: if update.unavailable { PresenceState::Unavailable } else { PresenceState::Available },
+            last_seen: update.last_seen.map(|t| t.timestamp()),
+        })),
+        Event::ChatPresence(update) => {
+            let state = match (&update.state, &update.media) {
+                (ChatPresence::Composing, ChatPresenceMedia::Audio) => PresenceState::Recording,
+                (ChatPresence::Composing, _) => PresenceState::Composing,
+                (ChatPresence::Paused, _) => PresenceState::Paused,
+            };
+            Some(ProviderEvent::Presence(PresenceEvent {
+                jid: update.source.sender.to_string(),
+                chat_id: Some(update.source.chat.to_string()),
+                state,
+                last_seen: None,
+            }))
+        }
+        _ => None,
+    }
+}
+
+fn translate_message(msg: &wa::Message, info: &MessageInfo) -> ProviderEvent {
+    let chat_id = info.source.chat.to_string();
+    let sender_id = info.source.sender.to_string();
+    let timestamp = info.timestamp.timestamp();
+
+    if let Some(reaction) = msg.reaction_message.as_deref() {
+        return ProviderEvent::Reaction(ReactionEvent {
+            chat_id,
+            message_id: reaction.key.as_ref().and_then(|k| k.id.clone()).unwrap_or_default(),
+            sender_id,
+            emoji: reaction.text.clone().filter(|t| !t.is_empty()),
+            timestamp: reaction.sender_timestamp_ms.map(|ms| ms / 1000).unwrap_or(timestamp),
+        });
+    }
+
+    // Edits arrive wrapped in a future-proof `edited_message` on newer clients
+    let protocol = msg.protocol_message.as_deref().or_else(|| {
+        msg.edited_message
+            .as_ref()
+            .and_then(|m| m.message.as_deref())
+            .and_then(|m| m.protocol_message.as_deref())
+    });
+
+    if let Some(protocol) = protocol {
+        let message_id = protocol.key.as_ref().and_then(|k| k.id.clone()).unwrap_or_default();
+        if protocol.r#type == Some(wa::message::protocol_message::Type::Revoke as i32) {
+            return ProviderEvent::MessageRevoked(RevokeEvent {
+                chat_id,
+                message_id,
+                revoked_by: sender_id,
+                timestamp,
+            });
+        }
+        if protocol.r#type == Some(wa::message::protocol_message::Type::MessageEdit as i32) {
+            if let Some(edited) = protocol.edited_message.as_deref() {
+                return ProviderEvent::MessageEdited(EditEvent {
+                    chat_id,
+                    message_id,
+                    edited_by: sender_id,
+                    content: extract_content(edited),
+                    timestamp: protocol.timestamp_ms.map(|ms| ms / 1000).unwrap_or(timestamp),
+                });
             }
-            Event::Message(_msg, _info) => {
-                 // Simplified event emission
-                 // We should emit a JSON representation of the message
-                 // For now, just a notification string to verify connectivity
-                 // msg is Box<wa::Message>, info is MessageInfo
-                 // MessageInfo has a source field which contains the sender
-                 let _ = self.app_handle.emit("message", "New Message".to_string());
+        }
+    }
+
+    ProviderEvent::Message(MessageEvent {
+        id: info.id.clone(),
+        chat_id,
+        sender_id,
+        sender_name: Some(info.push_name.clone()).filter(|n| !n.is_empty()),
+        timestamp,
+        from_me: info.source.is_from_me,
+        content: extract_content(msg),
+        quoted: extract_quoted(msg),
+    })
+}
+
+fn translate_history(sync: &wa::HistorySync) -> HistorySyncEvent {
+    let mut chats = Vec::with_capacity(sync.conversations.len());
+    let mut messages = Vec::new();
+
+    for conversation in &sync.conversations {
+        chats.push(HistoryChat {
+            id: conversation.id.clone(),
+            name: conversation.name.clone(),
+            unread_count: conversation.unread_count.unwrap_or(0),
+            last_message_timestamp: conversation.conversation_timestamp.map(|t| t as i64),
+        });
+
+        for entry in &conversation.messages {
+            let Some(info) = entry.message.as_ref() else { continue };
+            let Some(msg) = info.message.as_ref() else { continue };
+            // Reactions, revokes and edits in history are already folded into the messages they target
+            if msg.protocol_message.is_some() || msg.reaction_message.is_some() {
+                continue;
             }
-            _ => {}
-         }
+
+            let from_me = info.key.from_me.unwrap_or(false);
+            let sender_id = match (&info.key.participant, &info.participant) {
+                (Some(participant), _) | (None, Some(participant)) => participant.clone(),
+                _ if from_me => "me".to_string(),
+                _ => conversation.id.clone(),
+            };
+            messages.push(MessageEvent {
+                id: info.key.id.clone().unwrap_or_default(),
+                chat_id: conversation.id.clone(),
+                sender_id,
+                sender_name: info.push_name.clone().filter(|n| !n.is_empty()),
+                timestamp: info.message_timestamp.map(|t| t as i64).unwrap_or_default(),
+                from_me,
+                content: extract_content(msg),
+                quoted: extract_quoted(msg),
+            });
+        }
+    }
+
+    HistorySyncEvent {
+        chats,
+        contacts: sync.pushnames
+            .iter()
+            .filter_map(|p| Some(ContactInfo {
+                jid: p.id.clone()?,
+                name: None,
+                notify_name: p.pushname.clone(),
+            }))
+            .collect(),
+        messages,
+        progress: sync.progress,
+        is_latest: sync.progress == Some(100),
+    }
+}
+
+/// The reply context of a message, carried in the `context_info` of its content
+fn extract_quoted(msg: &wa::Message) -> Option<QuotedMessage> {
+    let context = msg.extended_text_message.as_ref().and_then(|m| m.context_info.as_deref())
+        .or_else(|| msg.image_message.as_ref().and_then(|m| m.context_info.as_deref()))
+        .or_else(|| msg.video_message.as_ref().and_then(|m| m.context_info.as_deref()))
+        .or_else(|| msg.audio_message.as_ref().and_then(|m| m.context_info.as_deref()))
+        .or_else(|| msg.document_message.as_ref().and_then(|m| m.context_info.as_deref()))
+        .or_else(|| msg.sticker_message.as_ref().and_then(|m| m.context_info.as_deref()))?;
+
+    Some(QuotedMessage {
+        id: context.stanza_id.clone()?,
+        sender_id: context.participant.clone(),
+        snippet: context.quoted_message.as_deref()
+            .map(|m| extract_content(m).preview())
+            .unwrap_or_default(),
+    })
+}
+
+fn extract_content(msg: &wa::Message) -> MessageContent {
+    if let Some(text) = msg.conversation.as_ref() {
+        MessageContent::Text { text: text.clone() }
+    } else if let Some(ext) = msg.extended_text_message.as_ref() {
+        MessageContent::Text { text: ext.text.clone().unwrap_or_default() }
+    } else if let Some(img) = msg.image_message.as_ref() {
+        MessageContent::Image { caption: img.caption.clone(), mime_type: img.mimetype.clone() }
+    } else if let Some(video) = msg.video_message.as_ref() {
+        MessageContent::Video { caption: video.caption.clone(), mime_type: video.mimetype.clone() }
+    } else if let Some(audio) = msg.audio_message.as_ref() {
+        MessageContent::Audio { mime_type: audio.mimetype.clone(), voice_note: audio.ptt.unwrap_or(false) }
+    } else if let Some(doc) = msg.document_message.as_ref() {
+        MessageContent::Document { file_name: doc.file_name.clone(), mime_type: doc.mimetype.clone() }
+    } else if msg.sticker_message.is_some() {
+        MessageContent::Sticker
+    } else {
+        MessageContent::Unsupported
     }
 }
 
@@ -65,7 +399,7 @@ impl WhatsAppProvider for RustBackend {
         if !app_data_dir.exists() {
             std::fs::create_dir_all(&app_data_dir)?;
         }
-        let db_path = app_data_dir.join("session.db");
+        let db_path = Self::session_path(&self.app_handle, &self.account_id)?;
         let db_url = format!("sqlite:{}?mode=rwc", db_path.to_string_lossy());
 
         // Get Master Key for encryption
@@ -89,8 +423,12 @@ impl WhatsAppProvider for RustBackend {
         }
 
         // Register handler
-        let handler = Arc::new(TauriEventHandler {
-           app_handle: self.app_handle.clone(),
+        let handler = Arc::new(ProviderEventHandler {
+            account_id: self.account_id.clone(),
+            events: self.events.clone(),
+            client: Arc::downgrade(&client),
+            media: self.media.clone(),
+            privacy: self.privacy.clone(),
         });
         client.register_handler(handler);
 
@@ -103,26 +441,302 @@ impl WhatsAppProvider for RustBackend {
         Ok(())
     }
 
-    async fn send_message(&self, jid: String, content: String) -> anyhow::Result<()> {
-        let client_guard = self.client.lock().await;
-        if let Some(client) = client_guard.as_ref() {
-             let jid = Jid::from_str(&jid).map_err(|e| anyhow::anyhow!("Invalid JID: {}", e))?;
+    async fn send_message(&self, jid: String, content: String) -> anyhow::Result<String> {
+        let client = self.client().await?;
+        let jid = parse_jid(&jid)?;
+
+        let message = wa::Message {
+            conversation: Some(content.clone()),
+            ..Default::default()
+        };
+
+        let chat_id = jid.to_string();
+        let id = client.send_message(jid, message).await?;
+        self.emit_outgoing(id.clone(), chat_id, MessageContent::Text { text: content }, None);
+        Ok(id)
+    }
+
+    async fn send_reply(&self, jid: String, content: String, quoted_id: String) -> anyhow::Result<String> {
+        let client = self.client().await?;
+        let chat = parse_jid(&jid)?;
+
+        let target = self.storage.get_message(&self.account_id, &quoted_id).await
+            .map_err(|e| anyhow::anyhow!("Storage error: {}", e))?
+            .ok_or_else(|| anyhow::anyhow!("Quoted message {} is not in storage", quoted_id))?;
+
+        // Recipients render the quote from `quoted_message`, so it must carry the original text
+        let participant = if target.from_me { None } else { Some(target.sender_id.clone()) };
+        let message = wa::Message {
+            extended_text_message: Some(Box::new(wa::message::ExtendedTextMessage {
+                text: Some(content.clone()),
+                context_info: Some(Box::new(wa::ContextInfo {
+                    stanza_id: Some(quoted_id.clone()),
+                    participant: participant.clone(),
+                    quoted_message: Some(Box::new(wa::Message {
+                        conversation: Some(target.content.clone()),
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+
+        let chat_id = chat.to_string();
+        let id = client.send_message(chat, message).await?;
+        self.emit_outgoing(id.clone(), chat_id, MessageContent::Text { text: content }, Some(QuotedMessage {
+            id: quoted_id,
+            sender_id: participant,
+            snippet: target.content,
+        }));
+        Ok(id)
+    }
+
+    async fn send_media(&self, jid: String, path: PathBuf, mime_type: String, caption: Option<String>) -> anyhow::Result<String> {
+        let client = self.client().await?;
+        let jid = parse_jid(&jid)?;
+
+        let data = tokio::fs::read(&path).await?;
+        let kind = MediaKind::from_mime(&mime_type);
+        let content = match kind {
+            MediaKind::Image => MessageContent::Image { caption: caption.clone(), mime_type: Some(mime_type.clone()) },
+            MediaKind::Video => MessageContent::Video { caption: caption.clone(), mime_type: Some(mime_type.clone()) },
+            MediaKind::Audio => MessageContent::Audio { mime_type: Some(mime_type.clone()), voice_note: false },
+            MediaKind::Document => MessageContent::Document {
+                file_name: path.file_name().map(|n| n.to_string_lossy().to_string()),
+                mime_type: Some(mime_type.clone()),
+            },
+        };
+        let media_type = match kind {
+            MediaKind::Image => MediaType::Image,
+            MediaKind::Video => MediaType::Video,
+            MediaKind::Audio => MediaType::Audio,
+            MediaKind::Document => MediaType::Document,
+        };
+        let upload = client.upload(data, media_type).await?;
+
+        let message = match kind {
+            MediaKind::Image => wa::Message {
+                image_message: Some(Box::new(wa::message::ImageMessage {
+                    url: Some(upload.url),
+                    direct_path: Some(upload.direct_path),
+                    media_key: Some(upload.media_key),
+                    file_enc_sha256: Some(upload.file_enc_sha256),
+                    file_sha256: Some(upload.file_sha256),
+                    file_length: Some(upload.file_length),
+                    mimetype: Some(mime_type),
+                    caption,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            },
+            MediaKind::Video => wa::Message {
+                video_message: Some(Box::new(wa::message::VideoMessage {
+                    url: Some(upload.url),
+                    direct_path: Some(upload.direct_path),
+                    media_key: Some(upload.media_key),
+                    file_enc_sha256: Some(upload.file_enc_sha256),
+                    file_sha256: Some(upload.file_sha256),
+                    file_length: Some(upload.file_length),
+                    mimetype: Some(mime_type),
+                    caption,
+                    ..Default::default()
+                })),
+                ..Default::default()
+            },
+            MediaKind::Audio => wa::Message {
+                audio_message: Some(Box::new(wa::message::AudioMessage {
+                    url: Some(upload.url),
+                    direct_path: Some(upload.direct_path),
+                    media_key: Some(upload.media_key),
+                    file_enc_sha256: Some(upload.file_enc_sha256),
+                    file_sha256: Some(upload.file_sha256),
+                    file_length: Some(upload.file_length),
+                    mimetype: Some(mime_type),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            },
+            MediaKind::Document => {
+                let file_name = path.file_name().map(|n| n.to_string_lossy().to_string());
+                wa::Message {
+                    document_message: Some(Box::new(wa::message::DocumentMessage {
+                        url: Some(upload.url),
+                        direct_path: Some(upload.direct_path),
+                        media_key: Some(upload.media_key),
+                        file_enc_sha256: Some(upload.file_enc_sha256),
+                        file_sha256: Some(upload.file_sha256),
+                        file_length: Some(upload.file_length),
+                        mimetype: Some(mime_type),
+                        title: file_name.clone(),
+                        file_name,
+                        caption,
+                        ..Default::default()
+                    })),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let chat_id = jid.to_string();
+        let id = client.send_message(jid, message).await?;
+        self.emit_outgoing(id.clone(), chat_id, content, None);
+        Ok(id)
+    }
+
+    async fn react(&self, chat_id: String, message_id: String, emoji: String) -> anyhow::Result<()> {
+        let client = self.client().await?;
+        let chat = parse_jid(&chat_id)?;
+
+        // The reaction key has to match the target's own key
+        let target = self.storage.get_message(&self.account_id, &message_id).await
+            .map_err(|e| anyhow::anyhow!("Storage error: {}", e))?;
+        let from_me = target.as_ref().map(|m| m.from_me).unwrap_or(false);
+        let participant = target.filter(|m| !m.from_me && chat_id.ends_with("@g.us")).map(|m| m.sender_id);
+
+        let timestamp = unix_now();
+        let message = wa::Message {
+            reaction_message: Some(Box::new(wa::message::ReactionMessage {
+                key: Some(wa::MessageKey {
+                    remote_jid: Some(chat_id.clone()),
+                    from_me: Some(from_me),
+                    id: Some(message_id.clone()),
+                    participant,
+                }),
+                text: Some(emoji.clone()),
+                sender_timestamp_ms: Some(timestamp * 1000),
+                ..Default::default()
+            })),
+            ..Default::default()
+        };
+        client.send_message(chat, message).await?;
+
+        let _ = self.events.send(ProviderEvent::Reaction(ReactionEvent {
+            chat_id,
+            message_id,
+            sender_id: "me".to_string(),
+            emoji: Some(emoji).filter(|e| !e.is_empty()),
+            timestamp,
+        }));
+        Ok(())
+    }
+
+    async fn send_read_receipt(&self, chat_id: String, sender_id: Option<String>, message_ids: Vec<String>) -> anyhow::Result<()> {
+        let client = self.client().await?;
+        let chat = parse_jid(&chat_id)?;
+        let sender = sender_id
+            .map(|s| parse_jid(&s))
+            .transpose()?;
+
+        self.privacy.gate(PrivacyAction::ReadReceipt, Some(&chat_id), async {
+            client.mark_as_read(&chat, sender.as_ref(), message_ids).await
+        }).await?;
+        Ok(())
+    }
+
+    async fn set_typing(&self, chat_id: String, state: PresenceState) -> anyhow::Result<()> {
+        let client = self.client().await?;
+        let chat = parse_jid(&chat_id)?;
+        let (presence, media) = match state {
+            PresenceState::Composing => (ChatPresence::Composing, ChatPresenceMedia::Text),
+            PresenceState::Recording => (ChatPresence::Composing, ChatPresenceMedia::Audio),
+            PresenceState::Paused => (ChatPresence::Paused, ChatPresenceMedia::Text),
+            other => return Err(anyhow::anyhow!("{:?} is not a chat state", other)),
+        };
 
-             let message = wa::Message {
-                 conversation: Some(content),
-                 ..Default::default()
-             };
+        self.privacy.gate(PrivacyAction::ChatState, Some(&chat_id), async {
+            client.send_chat_presence(&chat, presence, media).await
+        }).await?;
+        Ok(())
+    }
 
-             client.send_message(jid, message).await?;
-        } else {
-            return Err(anyhow::anyhow!("Client not initialized"));
+    async fn send_presence(&self, state: PresenceState) -> anyhow::Result<()> {
+        let client = self.client().await?;
+        match state {
+            // Going offline is what ghost mode wants anyway, so only `available` is gated
+            PresenceState::Available => {
+                self.privacy.gate(PrivacyAction::Presence, None, async {
+                    client.send_presence(Presence::Available).await
+                }).await?;
+            }
+            PresenceState::Unavailable => client.send_presence(Presence::Unavailable).await?,
+            other => return Err(anyhow::anyhow!("{:?} is not a presence state", other)),
         }
         Ok(())
     }
 
+    async fn subscribe_presence(&self, jid: String) -> anyhow::Result<()> {
+        let client = self.client().await?;
+        let jid = parse_jid(&jid)?;
+        self.privacy.gate(PrivacyAction::Presence, None, async {
+            client.subscribe_presence(&jid).await
+        }).await?;
+        Ok(())
+    }
+
+    async fn get_groups(&self) -> anyhow::Result<Vec<GroupMetadata>> {
+        let client = self.client().await?;
+        let groups = client.groups().get_participating().await?;
+        Ok(groups.into_iter().map(translate_group).collect())
+    }
+
+    async fn group_metadata(&self, group_id: String) -> anyhow::Result<GroupMetadata> {
+        let client = self.client().await?;
+        let info = client.groups().get_metadata(&parse_jid(&group_id)?).await?;
+        Ok(translate_group(info))
+    }
+
+    async fn create_group(&self, subject: String, participants: Vec<String>) -> anyhow::Result<GroupMetadata> {
+        let client = self.client().await?;
+        let participants = participants.iter().map(|p| parse_jid(p)).collect::<anyhow::Result<Vec<_>>>()?;
+        let info = client.groups().create_group(GroupCreateOptions {
+            subject,
+            participants,
+            ..Default::default()
+        }).await?;
+        Ok(translate_group(info))
+    }
+
+    async fn update_group_participants(&self, group_id: String, participants: Vec<String>, action: ParticipantAction) -> anyhow::Result<()> {
+        let client = self.client().await?;
+        let group = parse_jid(&group_id)?;
+        let participants = participants.iter().map(|p| parse_jid(p)).collect::<anyhow::Result<Vec<_>>>()?;
+        let groups = client.groups();
+        match action {
+            ParticipantAction::Add => groups.add_participants(&group, &participants).await?,
+            ParticipantAction::Remove => groups.remove_participants(&group, &participants).await?,
+            ParticipantAction::Promote => groups.promote_participants(&group, &participants).await?,
+            ParticipantAction::Demote => groups.demote_participants(&group, &participants).await?,
+        };
+        Ok(())
+    }
+
+    async fn set_group_subject(&self, group_id: String, subject: String) -> anyhow::Result<()> {
+        let client = self.client().await?;
+        client.groups().set_subject(&parse_jid(&group_id)?, &subject).await?;
+        Ok(())
+    }
+
+    async fn set_group_description(&self, group_id: String, description: Option<String>) -> anyhow::Result<()> {
+        let client = self.client().await?;
+        client.groups().set_description(&parse_jid(&group_id)?, description.as_deref()).await?;
+        Ok(())
+    }
+
+    async fn leave_group(&self, group_id: String) -> anyhow::Result<()> {
+        let client = self.client().await?;
+        client.groups().leave(&parse_jid(&group_id)?).await?;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<ProviderEvent> {
+        self.events.subscribe()
+    }
+
     async fn disconnect(&self) -> anyhow::Result<()> {
-        let mut client_guard = self.client.lock().await;
-        if let Some(client) = client_guard.take() {
+        let client = self.client.lock().await.take();
+        if let Some(client) = client {
             client.disconnect().await;
         }
         Ok(())
@@ -0,0 +1,109 @@
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tauri::AppHandle;
+use tokio::sync::Notify;
+use crate::backend::events::{emit_event, ProviderEvent};
+use crate::backend::unix_now;
+use crate::backend::outbox::Outbox;
+use crate::storage::{MissedPolicy, ScheduledMessage, SqliteStorage, Storage};
+
+/// How late a scheduled message may fire before it counts as missed. Covers sleep and
+/// timer drift while the app is running; anything later means the app was closed
+pub const MISSED_AFTER: Duration = Duration::from_secs(120);
+
+/// Longest the worker sleeps, so it notices its outbox went away
+const IDLE_WAKEUP: Duration = Duration::from_secs(30);
+
+/// Per-account worker that hands scheduled messages to the outbox when they come due.
+/// Schedules live in storage, so messages due while the app was closed are found on the
+/// next start and sent late or skipped according to their `MissedPolicy`.
+pub struct Scheduler {
+    account_id: String,
+    app_handle: AppHandle,
+    storage: Arc<SqliteStorage>,
+    wake: Notify,
+}
+
+impl Scheduler {
+    /// Starts the worker. It stops once the account's outbox is gone
+    pub fn spawn(app_handle: AppHandle, account_id: String, storage: Arc<SqliteStorage>, outbox: &Arc<Outbox>) -> Arc<Self> {
+        let scheduler = Arc::new(Self {
+            account_id,
+            app_handle,
+            storage,
+            wake: Notify::new(),
+        });
+
+        let worker = scheduler.clone();
+        let outbox = Arc::downgrade(outbox);
+        tokio::spawn(async move {
+            worker.run(outbox).await;
+        });
+
+        scheduler
+    }
+
+    /// Re-reads the schedule after a message was added or edited
+    pub fn reschedule(&self) {
+        self.wake.notify_one();
+    }
+
+    async fn run(&self, outbox: Weak<Outbox>) {
+        loop {
+            let Some(outbox) = outbox.upgrade() else { break };
+            self.fire_due(&outbox).await;
+            drop(outbox);
+
+            let idle = match self.storage.next_schedule_at(&self.account_id).await {
+                // At least a second, so a message that keeps failing to queue does not spin the loop
+                Ok(Some(at)) => Duration::from_secs(at.saturating_sub(unix_now()).max(1) as u64).min(IDLE_WAKEUP),
+                Ok(None) => IDLE_WAKEUP,
+                Err(e) => {
+                    eprintln!("Failed to read schedule of {}: {}", self.account_id, e);
+                    IDLE_WAKEUP
+                }
+            };
+
+            tokio::select! {
+                _ = self.wake.notified() => {}
+                _ = tokio::time::sleep(idle) => {}
+            }
+        }
+    }
+
+    async fn fire_due(&self, outbox: &Outbox) {
+        let now = unix_now();
+        let due = match self.storage.due_schedules(&self.account_id, now).await {
+            Ok(due) => due,
+            Err(e) => {
+                eprintln!("Failed to read schedule of {}: {}", self.account_id, e);
+                return;
+            }
+        };
+
+        for schedule in due {
+            let missed = now - schedule.send_at > MISSED_AFTER.as_secs() as i64;
+            if missed && schedule.if_missed == MissedPolicy::Skip {
+                match self.storage.skip_schedule(schedule.id, now).await {
+                    Ok(Some(skipped)) => self.publish(&skipped),
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Failed to skip scheduled message {}: {}", schedule.id, e),
+                }
+                continue;
+            }
+
+            match self.storage.queue_schedule(schedule.id, now).await {
+                Ok(Some((queued, entry))) => {
+                    self.publish(&queued);
+                    outbox.queued(&entry);
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Failed to queue scheduled message {}: {}", schedule.id, e),
+            }
+        }
+    }
+
+    fn publish(&self, schedule: &ScheduledMessage) {
+        emit_event(&self.app_handle, &self.account_id, &ProviderEvent::ScheduleUpdated(schedule.clone()));
+    }
+}
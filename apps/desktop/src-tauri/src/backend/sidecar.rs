@@ -0,0 +1,791 @@
+use async_trait::async_trait;
+use tauri::{AppHandle, Manager};
+use std::process::Stdio;
+use tokio::process::{Child, Command};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, oneshot, Mutex};
+use crate::backend::{MediaKind, WhatsAppProvider};
+use crate::backend::accounts::scoped_key;
+use crate::backend::events::{ConnectionState, PresenceState, ProviderEvent, EVENT_CHANNEL_CAPACITY};
+use crate::backend::ipc::{
+    self, AuthData, AuthUpdate, GroupListAck, Hello, IpcCommand, IpcEvent, MediaPayload, QuotedTarget, SentAck,
+    SidecarConnectionStatus, SidecarEvent, PROTOCOL_VERSION,
+};
+use crate::backend::groups::{GroupMetadata, ParticipantAction};
+use crate::backend::privacy::{PrivacyAction, PrivacyPolicy};
+use crate::backend::supervisor::{HealthTracker, RestartPolicy, SupervisorHealth, SupervisorState};
+use crate::storage::{MediaCache, Storage, SqliteStorage};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// How long a command waits for the sidecar's `ack`/`error` reply
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long a freshly spawned sidecar has to answer `hello`
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Name prefix of the directory each adapter creates in the temp directory for media files
+const MEDIA_DIR_PREFIX: &str = "whaswapp-media-";
+
+/// Why a freshly spawned sidecar did not become ready
+enum HandshakeError {
+    /// It answered with a protocol this core cannot use; a restart would get the same answer
+    Incompatible(String),
+    /// It exited, replied with an error or stayed silent; handled like a crash
+    Failed(String),
+}
+
+/// In-flight commands, keyed by command id, waiting for the sidecar's reply
+type PendingReplies = Arc<std::sync::Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>>;
+
+/// Static description of a Node sidecar adapter
+#[derive(Debug, Clone, Copy)]
+pub struct SidecarSpec {
+    /// Human readable name used in logs and user-facing errors
+    pub name: &'static str,
+    /// Adapter directory containing `index.js`, bundled as a resource
+    pub adapter_dir: &'static str,
+    pub auth: AuthScheme,
+}
+
+/// How a sidecar's auth state is laid out in the `auth_store` table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// Baileys: a `creds` document plus a Signal key set that is updated incrementally
+    Baileys,
+    /// whatsapp-web.js: the zipped browser session produced by `RemoteAuth`
+    WWebJs,
+}
+
+impl AuthScheme {
+    /// `auth_store` keys owned by this scheme, before scoping them to an account
+    pub fn storage_keys(&self) -> &'static [&'static str] {
+        match self {
+            AuthScheme::Baileys => &["baileys_creds", "baileys_keys"],
+            AuthScheme::WWebJs => &["wwebjs_session"],
+        }
+    }
+
+    async fn load(&self, storage: &SqliteStorage, account_id: &str) -> AuthData {
+        let get = |key: &str| {
+            let key = scoped_key(account_id, key);
+            async move { storage.get_auth_data(&key).await.unwrap_or(None) }
+        };
+        match self {
+            AuthScheme::Baileys => AuthData {
+                creds: get("baileys_creds").await,
+                keys: get("baileys_keys").await,
+                ..Default::default()
+            },
+            AuthScheme::WWebJs => AuthData {
+                session: get("wwebjs_session").await,
+                ..Default::default()
+            },
+        }
+    }
+
+    async fn apply(&self, storage: &SqliteStorage, account_id: &str, update: AuthUpdate) -> anyhow::Result<()> {
+        match (self, update) {
+            (AuthScheme::Baileys, AuthUpdate::Creds(data)) => {
+                storage.save_auth_data(&scoped_key(account_id, "baileys_creds"), &data).await
+                    .map_err(|e| anyhow::anyhow!("Storage error: {}", e))
+            }
+            (AuthScheme::Baileys, AuthUpdate::Keys(data)) => {
+                merge_and_save_keys(storage, &scoped_key(account_id, "baileys_keys"), &data).await
+            }
+            (AuthScheme::WWebJs, AuthUpdate::Session(data)) => {
+                storage.save_auth_data(&scoped_key(account_id, "wwebjs_session"), &data).await
+                    .map_err(|e| anyhow::anyhow!("Storage error: {}", e))
+            }
+            (AuthScheme::WWebJs, AuthUpdate::SessionRemoved) => {
+                storage.remove_auth_data(&scoped_key(account_id, "wwebjs_session")).await
+                    .map_err(|e| anyhow::anyhow!("Storage error: {}", e))
+            }
+            (scheme, update) => Err(anyhow::anyhow!("Unexpected auth update {:?} for {:?}", update, scheme)),
+        }
+    }
+
+    /// Forgets an account's pairing
+    pub async fn clear(&self, storage: &SqliteStorage, account_id: &str) {
+        for key in self.storage_keys() {
+            let _ = storage.remove_auth_data(&scoped_key(account_id, key)).await;
+        }
+    }
+}
+
+/// A supervised Node sidecar speaking the line-delimited IPC protocol in `ipc.rs`.
+/// `SidecarBackend` exposes it as a `WhatsAppProvider`.
+#[derive(Clone)]
+pub struct SidecarBridge {
+    spec: SidecarSpec,
+    account_id: String,
+    process: Arc<Mutex<Option<Child>>>,
+    app_handle: AppHandle,
+    stdin_tx: Arc<Mutex<Option<tokio::sync::mpsc::Sender<String>>>>,
+    storage: Arc<SqliteStorage>,
+    running: Arc<AtomicBool>,
+    events: broadcast::Sender<ProviderEvent>,
+    media: Arc<MediaCache>,
+    privacy: Arc<PrivacyPolicy>,
+    next_id: Arc<AtomicU64>,
+    pending: PendingReplies,
+    logged_out: Arc<AtomicBool>,
+    health: Arc<std::sync::Mutex<HealthTracker>>,
+}
+
+impl SidecarBridge {
+    pub fn new(app_handle: AppHandle, spec: SidecarSpec, account_id: String) -> Self {
+        let storage = app_handle.state::<Arc<SqliteStorage>>().inner().clone();
+        let media = app_handle.state::<Arc<MediaCache>>().inner().clone();
+        let privacy = app_handle.state::<Arc<PrivacyPolicy>>().inner().clone();
+
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+
+        Self {
+            spec,
+            account_id,
+            process: Arc::new(Mutex::new(None)),
+            app_handle,
+            stdin_tx: Arc::new(Mutex::new(None)),
+            storage,
+            running: Arc::new(AtomicBool::new(false)),
+            events,
+            media,
+            privacy,
+            next_id: Arc::new(AtomicU64::new(1)),
+            pending: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            logged_out: Arc::new(AtomicBool::new(false)),
+            health: Arc::new(std::sync::Mutex::new(HealthTracker::new(RestartPolicy::default()))),
+        }
+    }
+
+    /// Starts the supervision loop in the background
+    pub fn start(&self) {
+        let bridge = self.clone();
+        tokio::spawn(async move {
+            bridge.run_supervisor().await;
+        });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ProviderEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn health(&self) -> SupervisorHealth {
+        self.health.lock().unwrap().snapshot()
+    }
+
+    fn command(&self, command: ipc::Command) -> IpcCommand {
+        IpcCommand {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            command,
+        }
+    }
+
+    /// Sends a command and waits for the sidecar to `ack` or `error` it.
+    pub async fn request<T: DeserializeOwned>(&self, command: ipc::Command) -> anyhow::Result<T> {
+        self.request_within(command, REQUEST_TIMEOUT).await
+    }
+
+    /// `request` with a custom reply timeout
+    async fn request_within<T: DeserializeOwned>(&self, command: ipc::Command, timeout: Duration) -> anyhow::Result<T> {
+        let name = command.name();
+        let sidecar = self.spec.name;
+        let cmd = self.command(command);
+        let line = serde_json::to_string(&cmd)?;
+        let (reply_tx, reply_rx) = oneshot::channel();
+
+        {
+            let tx_guard = self.stdin_tx.lock().await;
+            let tx = tx_guard.as_ref().ok_or_else(|| anyhow::anyhow!("{} sidecar is not running", sidecar))?;
+            self.pending.lock().unwrap().insert(cmd.id, reply_tx);
+            if let Err(e) = tx.send(line).await {
+                self.pending.lock().unwrap().remove(&cmd.id);
+                return Err(anyhow::anyhow!("Failed to reach {} sidecar: {}", sidecar, e));
+            }
+        }
+
+        let reply = match tokio::time::timeout(timeout, reply_rx).await {
+            Ok(Ok(Ok(reply))) => reply,
+            Ok(Ok(Err(message))) => return Err(anyhow::anyhow!("{} error: {}", sidecar, message)),
+            Ok(Err(_)) => return Err(anyhow::anyhow!("{} sidecar exited before replying to {}", sidecar, name)),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&cmd.id);
+                return Err(anyhow::anyhow!("Timed out waiting for {} reply to {}", sidecar, name));
+            }
+        };
+
+        serde_json::from_value(reply)
+            .map_err(|e| anyhow::anyhow!("Malformed {} reply to {}: {}", sidecar, name, e))
+    }
+
+    /// Sends a text message, optionally as a reply. Returns the new message id.
+    pub async fn send_message(&self, jid: String, content: String, quoted_id: Option<String>) -> anyhow::Result<String> {
+        let quoted = match quoted_id {
+            Some(id) => {
+                let target = self.storage.get_message(&self.account_id, &id).await
+                    .map_err(|e| anyhow::anyhow!("Storage error: {}", e))?
+                    .ok_or_else(|| anyhow::anyhow!("Quoted message {} is not in storage", id))?;
+                Some(QuotedTarget {
+                    message_id: target.id,
+                    from_me: target.from_me,
+                    sender_id: target.sender_id,
+                    content: target.content,
+                })
+            }
+            None => None,
+        };
+
+        let ack: SentAck = self.request(ipc::Command::SendMessage { jid, content, quoted }).await?;
+        Ok(ack.message_id)
+    }
+
+    pub async fn react(&self, chat_id: String, message_id: String, emoji: String) -> anyhow::Result<()> {
+        let target = self.storage.get_message(&self.account_id, &message_id).await
+            .map_err(|e| anyhow::anyhow!("Storage error: {}", e))?;
+        let from_me = target.as_ref().map(|m| m.from_me).unwrap_or(false);
+        let sender_id = target.filter(|m| !m.from_me).map(|m| m.sender_id);
+
+        self.request::<Value>(ipc::Command::SendReaction { chat_id, message_id, from_me, sender_id, emoji }).await?;
+        Ok(())
+    }
+
+    pub async fn get_groups(&self) -> anyhow::Result<Vec<GroupMetadata>> {
+        let ack: GroupListAck = self.request(ipc::Command::GroupList).await?;
+        Ok(ack.groups)
+    }
+
+    pub async fn group_metadata(&self, group_id: String) -> anyhow::Result<GroupMetadata> {
+        self.request(ipc::Command::GroupMetadata { group_id }).await
+    }
+
+    pub async fn create_group(&self, subject: String, participants: Vec<String>) -> anyhow::Result<GroupMetadata> {
+        self.request(ipc::Command::GroupCreate { subject, participants }).await
+    }
+
+    pub async fn update_group_participants(&self, group_id: String, participants: Vec<String>, action: ParticipantAction) -> anyhow::Result<()> {
+        self.request::<Value>(ipc::Command::GroupUpdateParticipants { group_id, participants, action }).await?;
+        Ok(())
+    }
+
+    pub async fn set_group_subject(&self, group_id: String, subject: String) -> anyhow::Result<()> {
+        self.request::<Value>(ipc::Command::GroupUpdateSubject { group_id, subject }).await?;
+        Ok(())
+    }
+
+    pub async fn set_group_description(&self, group_id: String, description: Option<String>) -> anyhow::Result<()> {
+        self.request::<Value>(ipc::Command::GroupUpdateDescription { group_id, description }).await?;
+        Ok(())
+    }
+
+    pub async fn leave_group(&self, group_id: String) -> anyhow::Result<()> {
+        self.request::<Value>(ipc::Command::GroupLeave { group_id }).await?;
+        Ok(())
+    }
+
+    pub async fn send_read_receipt(&self, chat_id: String, sender_id: Option<String>, message_ids: Vec<String>) -> anyhow::Result<()> {
+        let scope = chat_id.clone();
+        self.privacy.gate(PrivacyAction::ReadReceipt, Some(&scope), async {
+            self.request::<Value>(ipc::Command::SendReceipt { chat_id, sender_id, message_ids }).await.map(|_| ())
+        }).await?;
+        Ok(())
+    }
+
+    pub async fn set_typing(&self, chat_id: String, state: PresenceState) -> anyhow::Result<()> {
+        let scope = chat_id.clone();
+        self.privacy.gate(PrivacyAction::ChatState, Some(&scope), async {
+            self.request::<Value>(ipc::Command::SendChatState { chat_id, state }).await.map(|_| ())
+        }).await?;
+        Ok(())
+    }
+
+    pub async fn send_presence(&self, state: PresenceState) -> anyhow::Result<()> {
+        let send = async {
+            self.request::<Value>(ipc::Command::SendPresence { state }).await.map(|_| ())
+        };
+        // Going offline is what ghost mode wants anyway, so only `available` is gated
+        if state == PresenceState::Available {
+            self.privacy.gate(PrivacyAction::Presence, None, send).await?;
+        } else {
+            send.await?;
+        }
+        Ok(())
+    }
+
+    pub async fn subscribe_presence(&self, jid: String) -> anyhow::Result<()> {
+        self.privacy.gate(PrivacyAction::Presence, None, async {
+            self.request::<Value>(ipc::Command::PresenceSubscribe { jid }).await.map(|_| ())
+        }).await?;
+        Ok(())
+    }
+
+    /// Exchanges `hello` with a freshly spawned sidecar and checks it matches this core.
+    async fn handshake(&self) -> Result<Hello, HandshakeError> {
+        let hello = self
+            .request_within::<Hello>(ipc::Command::Hello { protocol_version: PROTOCOL_VERSION }, HANDSHAKE_TIMEOUT)
+            .await
+            .map_err(|e| HandshakeError::Failed(format!("{} adapter handshake failed: {}", self.spec.name, e)))?;
+
+        hello.check_compatibility().map_err(HandshakeError::Incompatible)?;
+        Ok(hello)
+    }
+
+    // Supervision loop
+    async fn run_supervisor(&self) {
+        let name = self.spec.name;
+        self.running.store(true, Ordering::Relaxed);
+
+        while self.running.load(Ordering::Relaxed) {
+            println!("{} Supervisor: Starting process...", name);
+
+            // Path resolution
+            let script = format!("{}/index.js", self.spec.adapter_dir);
+            let resource_dir = self.app_handle.path().resource_dir().unwrap_or(std::path::PathBuf::from("."));
+            let script_path = resource_dir.join(&script);
+            let script_path = if !script_path.exists() {
+                 std::env::current_dir().unwrap().join("apps/desktop/src-tauri").join(&script)
+            } else {
+                script_path
+            };
+
+            if !script_path.exists() {
+                eprintln!("{} script not found, supervisor exiting.", name);
+                self.stop_supervisor(SupervisorState::Failed, format!("{} adapter script not found", name));
+                break;
+            }
+
+            // Fetch latest auth data
+            let auth_data = self.spec.auth.load(&self.storage, &self.account_id).await;
+
+            let mut child = match Command::new("node")
+                .arg(&script_path)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::inherit())
+                .kill_on_drop(true)
+                .spawn() {
+                    Ok(c) => c,
+                    Err(e) => {
+                        eprintln!("Failed to spawn {}: {}", name, e);
+                        if !self.back_off(None, Some(format!("Failed to spawn node: {}", e))).await {
+                            break;
+                        }
+                        continue;
+                    }
+                };
+
+            let stdin = child.stdin.take().unwrap();
+            let stdout = child.stdout.take().unwrap();
+
+            // Set up channels
+            let (tx, mut rx) = tokio::sync::mpsc::channel::<String>(32);
+            *self.stdin_tx.lock().await = Some(tx.clone());
+            *self.process.lock().await = Some(child);
+            self.logged_out.store(false, Ordering::Relaxed);
+            self.health.lock().unwrap().process_started();
+
+            // IO Loops
+            let stdin_handle = tokio::spawn(async move {
+                let mut stdin = stdin;
+                while let Some(cmd) = rx.recv().await {
+                    if stdin.write_all(cmd.as_bytes()).await.is_err() || stdin.write_all(b"\n").await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            let ctx = SidecarContext {
+                spec: self.spec,
+                account_id: self.account_id.clone(),
+                events: self.events.clone(),
+                storage: self.storage.clone(),
+                media: self.media.clone(),
+                pending: self.pending.clone(),
+                logged_out: self.logged_out.clone(),
+            };
+
+            let mut stdout_handle = tokio::spawn(async move {
+                let reader = BufReader::new(stdout);
+                let mut lines = reader.lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match serde_json::from_str::<IpcEvent>(&line) {
+                        Ok(event) => ctx.dispatch(event).await,
+                        Err(e) => eprintln!("Unrecognised {} IPC line ({}): {}", ctx.spec.name, e, line),
+                    }
+                }
+            });
+
+            // The reader finishes when stdout closes, i.e. when the process dies before answering
+            let handshake = tokio::select! {
+                biased;
+                result = self.handshake() => result,
+                _ = &mut stdout_handle => Err(HandshakeError::Failed(format!("{} adapter exited during the protocol handshake", name))),
+            };
+
+            let mut failure = None;
+            match handshake {
+                Ok(hello) => {
+                    println!("{} adapter {} ready (protocol v{})", name, hello.adapter_version, hello.protocol_version);
+
+                    let init_cmd = self.command(ipc::Command::Init {
+                        auth_data,
+                        mark_online: self.privacy.allows(PrivacyAction::Presence, None),
+                    });
+                    if let Err(e) = tx.send(serde_json::to_string(&init_cmd).unwrap()).await {
+                        eprintln!("Failed to send init: {}", e);
+                    }
+                }
+                Err(HandshakeError::Incompatible(message)) => {
+                    // Restarting would hit the same mismatch again, so stop for good
+                    eprintln!("{}", message);
+                    self.stop_supervisor(SupervisorState::Failed, message);
+                    if let Some(mut child) = self.process.lock().await.take() {
+                        let _ = child.kill().await;
+                    }
+                }
+                Err(HandshakeError::Failed(message)) => {
+                    // Counts as a crash: backed off, and stopped by the breaker if it keeps happening
+                    eprintln!("{}", message);
+                    failure = Some(message);
+                    if let Some(child) = self.process.lock().await.as_mut() {
+                        let _ = child.start_kill();
+                    }
+                }
+            }
+
+            // Wait for process exit
+            let mut exit_code = None;
+            let mut proc_guard = self.process.lock().await;
+            if let Some(mut child) = proc_guard.take() {
+                drop(proc_guard); // Release lock while waiting
+                exit_code = child.wait().await.ok().and_then(|status| status.code());
+            } else {
+                drop(proc_guard);
+            }
+
+            // Abort handles
+            stdin_handle.abort();
+            stdout_handle.abort();
+
+            // Fail in-flight commands instead of letting them hit the timeout
+            *self.stdin_tx.lock().await = None;
+            self.pending.lock().unwrap().clear();
+
+            if !self.running.load(Ordering::Relaxed) {
+                let mut health = self.health.lock().unwrap();
+                if health.snapshot().state == SupervisorState::Running {
+                    health.finish(SupervisorState::Stopped, None);
+                }
+                break;
+            }
+
+            if self.logged_out.load(Ordering::Relaxed) || exit_code == Some(ipc::EXIT_CODE_LOGGED_OUT) {
+                println!("{} account logged out, clearing auth and stopping.", name);
+                self.spec.auth.clear(&self.storage, &self.account_id).await;
+                self.running.store(false, Ordering::Relaxed);
+                self.health.lock().unwrap().finish(SupervisorState::LoggedOut, None);
+                let _ = self.events.send(ProviderEvent::ConnectionStatus { status: ConnectionState::LoggedOut });
+                break;
+            }
+
+            if !self.back_off(exit_code, failure).await {
+                break;
+            }
+        }
+    }
+
+    /// Records an unexpected exit and sleeps before the next attempt.
+    /// Returns false once the crash-loop breaker has tripped.
+    async fn back_off(&self, exit_code: Option<i32>, error: Option<String>) -> bool {
+        let (delay, max_failures) = {
+            let mut health = self.health.lock().unwrap();
+            (health.process_exited(exit_code, error), health.policy().max_rapid_failures)
+        };
+
+        match delay {
+            Some(delay) => {
+                println!("{} process exited ({:?}). Restarting in {:.1}s...", self.spec.name, exit_code, delay.as_secs_f64());
+                tokio::time::sleep(delay).await;
+                true
+            }
+            None => {
+                let message = format!(
+                    "The {} adapter crashed {} times in a row and will not be restarted. Check the logs and start the session again.",
+                    self.spec.name, max_failures
+                );
+                eprintln!("{}", message);
+                self.stop_supervisor(SupervisorState::CrashLoop, message);
+                false
+            }
+        }
+    }
+
+    /// Stops supervision for good and tells the user why.
+    fn stop_supervisor(&self, state: SupervisorState, message: String) {
+        self.running.store(false, Ordering::Relaxed);
+        self.health.lock().unwrap().finish(state, Some(message.clone()));
+        let _ = self.events.send(ProviderEvent::Error { message });
+    }
+
+    pub async fn disconnect(&self) -> anyhow::Result<()> {
+        // Stop supervisor
+        self.running.store(false, Ordering::Relaxed);
+
+        let tx_guard = self.stdin_tx.lock().await;
+        if let Some(tx) = tx_guard.as_ref() {
+            let cmd = self.command(ipc::Command::Disconnect);
+            let _ = tx.send(serde_json::to_string(&cmd)?).await;
+        }
+
+        // Force kill if needed
+        let mut proc_guard = self.process.lock().await;
+        if let Some(mut child) = proc_guard.take() {
+            let _ = child.kill().await;
+        }
+
+        Ok(())
+    }
+}
+
+/// The `WhatsAppProvider` for every sidecar; backends only differ in their `SidecarSpec`
+pub struct SidecarBackend {
+    bridge: SidecarBridge,
+}
+
+impl SidecarBackend {
+    pub fn new(app_handle: AppHandle, spec: SidecarSpec, account_id: String) -> Self {
+        Self {
+            bridge: SidecarBridge::new(app_handle, spec, account_id),
+        }
+    }
+}
+
+#[async_trait]
+impl WhatsAppProvider for SidecarBackend {
+    async fn initialize(&self, _payload: String) -> anyhow::Result<()> {
+        // Spawn the supervisor loop in the background
+        self.bridge.start();
+        Ok(())
+    }
+
+    async fn send_message(&self, jid: String, content: String) -> anyhow::Result<String> {
+        self.bridge.send_message(jid, content, None).await
+    }
+
+    async fn send_reply(&self, jid: String, content: String, quoted_id: String) -> anyhow::Result<String> {
+        self.bridge.send_message(jid, content, Some(quoted_id)).await
+    }
+
+    async fn send_media(&self, jid: String, path: PathBuf, mime_type: String, caption: Option<String>) -> anyhow::Result<String> {
+        let ack: SentAck = self.bridge.request(ipc::Command::SendMedia {
+            jid,
+            path,
+            kind: MediaKind::from_mime(&mime_type),
+            mime_type,
+            caption,
+        }).await?;
+        Ok(ack.message_id)
+    }
+
+    async fn react(&self, chat_id: String, message_id: String, emoji: String) -> anyhow::Result<()> {
+        self.bridge.react(chat_id, message_id, emoji).await
+    }
+
+    async fn send_read_receipt(&self, chat_id: String, sender_id: Option<String>, message_ids: Vec<String>) -> anyhow::Result<()> {
+        self.bridge.send_read_receipt(chat_id, sender_id, message_ids).await
+    }
+
+    async fn set_typing(&self, chat_id: String, state: PresenceState) -> anyhow::Result<()> {
+        self.bridge.set_typing(chat_id, state).await
+    }
+
+    async fn send_presence(&self, state: PresenceState) -> anyhow::Result<()> {
+        self.bridge.send_presence(state).await
+    }
+
+    async fn subscribe_presence(&self, jid: String) -> anyhow::Result<()> {
+        self.bridge.subscribe_presence(jid).await
+    }
+
+    async fn get_groups(&self) -> anyhow::Result<Vec<GroupMetadata>> {
+        self.bridge.get_groups().await
+    }
+
+    async fn group_metadata(&self, group_id: String) -> anyhow::Result<GroupMetadata> {
+        self.bridge.group_metadata(group_id).await
+    }
+
+    async fn create_group(&self, subject: String, participants: Vec<String>) -> anyhow::Result<GroupMetadata> {
+        self.bridge.create_group(subject, participants).await
+    }
+
+    async fn update_group_participants(&self, group_id: String, participants: Vec<String>, action: ParticipantAction) -> anyhow::Result<()> {
+        self.bridge.update_group_participants(group_id, participants, action).await
+    }
+
+    async fn set_group_subject(&self, group_id: String, subject: String) -> anyhow::Result<()> {
+        self.bridge.set_group_subject(group_id, subject).await
+    }
+
+    async fn set_group_description(&self, group_id: String, description: Option<String>) -> anyhow::Result<()> {
+        self.bridge.set_group_description(group_id, description).await
+    }
+
+    async fn leave_group(&self, group_id: String) -> anyhow::Result<()> {
+        self.bridge.leave_group(group_id).await
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<ProviderEvent> {
+        self.bridge.subscribe()
+    }
+
+    fn health(&self) -> Option<SupervisorHealth> {
+        Some(self.bridge.health())
+    }
+
+    async fn disconnect(&self) -> anyhow::Result<()> {
+        self.bridge.disconnect().await
+    }
+}
+
+/// State the stdout reader needs, cloned out of the bridge so it can live in a task
+struct SidecarContext {
+    spec: SidecarSpec,
+    account_id: String,
+    events: broadcast::Sender<ProviderEvent>,
+    storage: Arc<SqliteStorage>,
+    media: Arc<MediaCache>,
+    pending: PendingReplies,
+    logged_out: Arc<AtomicBool>,
+}
+
+impl SidecarContext {
+    async fn dispatch(&self, event: IpcEvent) {
+        match event.event {
+            SidecarEvent::Hello(hello) => {
+                let reply = serde_json::to_value(hello).map_err(|e| e.to_string());
+                self.resolve_reply(event.id, reply);
+            }
+            SidecarEvent::Ack(payload) => self.resolve_reply(event.id, Ok(payload)),
+            SidecarEvent::Error(error) => self.resolve_reply(event.id, Err(error.message)),
+            SidecarEvent::Media(payload) => store_media(&self.media, &self.account_id, &self.events, payload).await,
+            SidecarEvent::AuthUpdate(update) => {
+                if let Err(e) = self.spec.auth.apply(&self.storage, &self.account_id, update).await {
+                    eprintln!("Failed to save {} auth state: {}", self.spec.name, e);
+                }
+            }
+            SidecarEvent::AuthFailure(reason) => {
+                eprintln!("{} auth failure: {}", self.spec.name, reason);
+                self.logged_out.store(true, Ordering::Relaxed);
+            }
+            other => {
+                if let Some(event) = translate_event(other) {
+                    let _ = self.events.send(event);
+                }
+            }
+        }
+    }
+
+    fn resolve_reply(&self, id: Option<u64>, reply: Result<Value, String>) {
+        let waiter = id.and_then(|id| self.pending.lock().unwrap().remove(&id));
+
+        match (waiter, reply) {
+            (Some(tx), reply) => {
+                let _ = tx.send(reply);
+            }
+            (None, Err(message)) => eprintln!("{} error: {}", self.spec.name, message),
+            (None, Ok(_)) => {}
+        }
+    }
+}
+
+/// Merges a Signal key update into the stored key set; `null` entries are deletions.
+async fn merge_and_save_keys(storage: &SqliteStorage, key: &str, updates: &Value) -> anyhow::Result<()> {
+    let mut current_data = storage.get_auth_data(key).await
+        .map_err(|e| anyhow::anyhow!("Storage error: {}", e))?
+        .unwrap_or(serde_json::json!({}));
+
+    if let (Some(update_obj), Some(current_obj)) = (updates.as_object(), current_data.as_object_mut()) {
+        for (type_key, type_val) in update_obj {
+            if !current_obj.contains_key(type_key) {
+                current_obj.insert(type_key.clone(), serde_json::json!({}));
+            }
+            if let (Some(target), Some(source)) = (current_obj.get_mut(type_key).and_then(|v| v.as_object_mut()), type_val.as_object()) {
+                for (id, val) in source {
+                    if val.is_null() { target.remove(id); } else { target.insert(id.clone(), val.clone()); }
+                }
+            }
+        }
+    }
+
+    storage.save_auth_data(key, &current_data).await
+        .map_err(|e| anyhow::anyhow!("Storage error: {}", e))?;
+    Ok(())
+}
+
+/// Resolves `path` and returns it if it names a file directly inside one of the adapters'
+/// media directories. `..` components and symlinks are resolved first, so they cannot lead out
+pub(crate) async fn adapter_media_file(path: &Path) -> Option<PathBuf> {
+    let path = tokio::fs::canonicalize(path).await.ok()?;
+    let temp_dir = tokio::fs::canonicalize(std::env::temp_dir()).await.ok()?;
+    let dir = path.parent()?;
+    let in_media_dir = dir.parent() == Some(temp_dir.as_path())
+        && dir.file_name()?.to_str()?.starts_with(MEDIA_DIR_PREFIX);
+    in_media_dir.then_some(path)
+}
+
+/// Encrypts the adapter's plaintext file into the cache, then deletes it
+async fn store_media(media: &MediaCache, account_id: &str, events: &broadcast::Sender<ProviderEvent>, payload: MediaPayload) {
+    // Only ever touch the adapter's own temp files, whatever path it sends
+    let Some(path) = adapter_media_file(&payload.path).await else {
+        eprintln!("Ignoring media for {} outside the adapter's media directory: {}", payload.message_id, payload.path.display());
+        return;
+    };
+
+    let stored = match tokio::fs::read(&path).await {
+        Ok(data) => media.store(account_id, &payload.message_id, &payload.chat_id, &payload.mime_type, payload.file_name, payload.timestamp, &data).await,
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = tokio::fs::remove_file(&path).await {
+        eprintln!("Failed to remove media file {}: {}", path.display(), e);
+    }
+
+    match stored {
+        Ok(record) => {
+            let _ = events.send(ProviderEvent::MediaReady((&record).into()));
+        }
+        Err(e) => eprintln!("Failed to cache media for {}: {}", payload.message_id, e),
+    }
+}
+
+/// Maps a sidecar event onto the backend-agnostic `ProviderEvent`.
+fn translate_event(event: SidecarEvent) -> Option<ProviderEvent> {
+    match event {
+        SidecarEvent::QrCode(code) => Some(ProviderEvent::Qr(code)),
+        SidecarEvent::ConnectionStatus(status) => {
+            let status = match status {
+                SidecarConnectionStatus::Connecting => ConnectionState::Connecting,
+                SidecarConnectionStatus::Connected => ConnectionState::Connected,
+                SidecarConnectionStatus::DisconnectedReconnecting => ConnectionState::Reconnecting,
+                SidecarConnectionStatus::Disconnected => ConnectionState::Disconnected,
+            };
+            Some(ProviderEvent::ConnectionStatus { status })
+        }
+        SidecarEvent::Message(msg) => Some(ProviderEvent::Message(msg)),
+        SidecarEvent::Receipt(receipt) => Some(ProviderEvent::Receipt(receipt)),
+        SidecarEvent::Revoke(revoke) => Some(ProviderEvent::MessageRevoked(revoke)),
+        SidecarEvent::Edit(edit) => Some(ProviderEvent::MessageEdited(edit)),
+        SidecarEvent::Reaction(reaction) => Some(ProviderEvent::Reaction(reaction)),
+        SidecarEvent::HistorySync(sync) => Some(ProviderEvent::HistorySync(sync)),
+        SidecarEvent::Presence(presence) => Some(ProviderEvent::Presence(presence)),
+        _ => None,
+    }
+}
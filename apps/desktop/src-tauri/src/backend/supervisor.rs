@@ -0,0 +1,140 @@
+use rand::Rng;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+/// Restart rules for a supervised sidecar process
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Exits sooner than this after start count as rapid failures
+    pub rapid_failure_window: Duration,
+    /// Consecutive rapid failures before the supervisor gives up
+    pub max_rapid_failures: u32,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            rapid_failure_window: Duration::from_secs(30),
+            max_rapid_failures: 5,
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Exponential delay before restart `attempt` (1-based), capped and with up to 25% jitter
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(factor).min(self.max_delay);
+        let jitter = rand::rng().random_range(0.0..=0.25);
+        delay.mul_f64(1.0 + jitter)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SupervisorState {
+    Starting,
+    Running,
+    BackingOff,
+    /// Stopped on request
+    Stopped,
+    /// Gave up after too many rapid failures
+    CrashLoop,
+    /// The account was logged out; auth has been cleared
+    LoggedOut,
+    /// Gave up because the sidecar is not usable by this core
+    Failed,
+}
+
+/// Snapshot of a supervisor, returned to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct SupervisorHealth {
+    pub state: SupervisorState,
+    pub restarts: u32,
+    pub consecutive_failures: u32,
+    pub last_exit_code: Option<i32>,
+    pub uptime_secs: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+pub struct HealthTracker {
+    policy: RestartPolicy,
+    state: SupervisorState,
+    restarts: u32,
+    consecutive_failures: u32,
+    last_exit_code: Option<i32>,
+    started_at: Option<Instant>,
+    last_error: Option<String>,
+}
+
+impl HealthTracker {
+    pub fn new(policy: RestartPolicy) -> Self {
+        Self {
+            policy,
+            state: SupervisorState::Starting,
+            restarts: 0,
+            consecutive_failures: 0,
+            last_exit_code: None,
+            started_at: None,
+            last_error: None,
+        }
+    }
+
+    pub fn policy(&self) -> &RestartPolicy {
+        &self.policy
+    }
+
+    pub fn process_started(&mut self) {
+        self.state = SupervisorState::Running;
+        self.started_at = Some(Instant::now());
+    }
+
+    /// Records an exit (or a failed spawn, with no start) and returns the restart delay,
+    /// or `None` once the crash-loop breaker has tripped.
+    pub fn process_exited(&mut self, exit_code: Option<i32>, error: Option<String>) -> Option<Duration> {
+        let uptime = self.started_at.take().map(|t| t.elapsed()).unwrap_or_default();
+        self.last_exit_code = exit_code;
+        if error.is_some() {
+            self.last_error = error;
+        }
+
+        // A process that stayed up for a while starts a fresh failure streak
+        if uptime >= self.policy.rapid_failure_window {
+            self.consecutive_failures = 1;
+        } else {
+            self.consecutive_failures += 1;
+        }
+
+        if self.consecutive_failures >= self.policy.max_rapid_failures {
+            self.state = SupervisorState::CrashLoop;
+            return None;
+        }
+
+        self.state = SupervisorState::BackingOff;
+        self.restarts += 1;
+        Some(self.policy.backoff(self.consecutive_failures))
+    }
+
+    pub fn finish(&mut self, state: SupervisorState, error: Option<String>) {
+        self.state = state;
+        self.started_at = None;
+        if error.is_some() {
+            self.last_error = error;
+        }
+    }
+
+    pub fn snapshot(&self) -> SupervisorHealth {
+        SupervisorHealth {
+            state: self.state,
+            restarts: self.restarts,
+            consecutive_failures: self.consecutive_failures,
+            last_exit_code: self.last_exit_code,
+            uptime_secs: self.started_at.map(|t| t.elapsed().as_secs()),
+            last_error: self.last_error.clone(),
+        }
+    }
+}
@@ -0,0 +1,13 @@
+use tauri::AppHandle;
+use crate::backend::sidecar::{AuthScheme, SidecarBackend, SidecarSpec};
+
+const SPEC: SidecarSpec = SidecarSpec {
+    name: "WhatsApp-Web.js",
+    adapter_dir: "wwebjs-adapter",
+    auth: AuthScheme::WWebJs,
+};
+
+/// A backend running the whatsapp-web.js adapter
+pub fn backend(app_handle: AppHandle, account_id: String) -> SidecarBackend {
+    SidecarBackend::new(app_handle, SPEC, account_id)
+}
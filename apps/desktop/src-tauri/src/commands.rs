@@ -1,21 +1,66 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 use tauri::{AppHandle, State};
+use tauri::ipc::Response;
 use tokio::sync::Mutex;
-use crate::backend::WhatsAppProvider;
+use crate::backend::{unix_now, WhatsAppProvider};
+use crate::backend::accounts::{validate_account_id, BackendKind};
+use crate::backend::sidecar::AuthScheme;
 use crate::backend::rust::RustBackend;
-use crate::backend::baileys::BaileysBackend;
+use crate::backend::{baileys, wwebjs};
+use crate::backend::events::{spawn_event_forwarder, PresenceState};
+use crate::backend::groups::{GroupMetadata, ParticipantAction};
+use crate::backend::outbox::{OutgoingMessage, Outbox};
+use crate::backend::privacy::PrivacyPolicy;
+use crate::backend::scheduler::Scheduler;
+use crate::backend::supervisor::SupervisorHealth;
+use crate::storage::{Account, Chat, Contact, Group, GroupParticipant, MediaCache, Message, MessagePage, MessageRange, MessageReceipt, MessageRevision, MissedPolicy, OutboxEntry, PrivacyRule, Reaction, ScheduleState, ScheduledMessage, SearchFilters, SearchHit, SqliteStorage, Storage, GLOBAL_PRIVACY_SCOPE};
+use crate::storage::ingest::{spawn_event_persister, spawn_history_ingester};
 use crate::utils::chrome::launch_chrome;
+use crate::utils::security::SecurityManager;
 use crate::SessionConfig; // Import from main
 
 pub struct WhatsAppManager {
-    pub provider: Mutex<Option<Box<dyn WhatsAppProvider>>>,
+    /// Running sessions, keyed by account id
+    pub providers: Mutex<HashMap<String, Arc<dyn WhatsAppProvider>>>,
+    /// Send queue of each running session
+    pub outboxes: Mutex<HashMap<String, Arc<Outbox>>>,
+    /// Scheduled message worker of each running session
+    pub schedulers: Mutex<HashMap<String, Arc<Scheduler>>>,
 }
 
 impl WhatsAppManager {
     pub fn new() -> Self {
         Self {
-            provider: Mutex::new(None),
+            providers: Mutex::new(HashMap::new()),
+            outboxes: Mutex::new(HashMap::new()),
+            schedulers: Mutex::new(HashMap::new()),
         }
     }
+
+    /// The account's running provider, cloned out so the map is not locked while it works
+    pub async fn provider(&self, account_id: &str) -> Result<Arc<dyn WhatsAppProvider>, String> {
+        self.providers.lock().await.get(account_id).cloned().ok_or_else(|| "No active session".to_string())
+    }
+
+    pub async fn outbox(&self, account_id: &str) -> Result<Arc<Outbox>, String> {
+        self.outboxes.lock().await.get(account_id).cloned().ok_or_else(|| "No active session".to_string())
+    }
+
+    /// Lets a running account pick up a changed schedule; others read it when they start
+    pub async fn reschedule(&self, account_id: &str) {
+        if let Some(scheduler) = self.schedulers.lock().await.get(account_id) {
+            scheduler.reschedule();
+        }
+    }
+
+    /// Stops an account's session. Its outbox and scheduler workers end with the provider
+    async fn remove_session(&self, account_id: &str) -> Option<Arc<dyn WhatsAppProvider>> {
+        self.schedulers.lock().await.remove(account_id);
+        self.outboxes.lock().await.remove(account_id);
+        self.providers.lock().await.remove(account_id)
+    }
 }
 
 // Commands
@@ -38,10 +83,14 @@ pub struct SessionConfigResponse {
 pub async fn setup_session(
     app: AppHandle,
     manager: State<'_, WhatsAppManager>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
     backend: String,
     frontend: String,
 ) -> Result<(), String> {
-    println!("Setting up session: Backend={}, Frontend={}", backend, frontend);
+    validate_account_id(&account_id)?;
+    let kind = BackendKind::parse(&backend).ok_or("Unsupported backend")?;
+    println!("Setting up session for account {}: Backend={}, Frontend={}", account_id, backend, frontend);
 
     // Handle Frontend Selection
     if frontend == "chrome" {
@@ -51,64 +100,625 @@ pub async fn setup_session(
         }
     }
 
-    // Handle Backend Selection
-    let mut provider_lock = manager.provider.lock().await;
-
-    // Disconnect existing if any
-    if let Some(p) = provider_lock.take() {
+    // Replace this account's session, if any; other accounts keep running
+    if let Some(p) = manager.remove_session(&account_id).await {
         let _ = p.disconnect().await;
     }
 
-    if backend == "baileys" {
-        println!("Baileys backend selected. Initializing Baileys Adapter...");
-        let backend = BaileysBackend::new(app.clone());
-        if let Err(e) = backend.initialize("".to_string()).await {
-             eprintln!("Failed to initialize Baileys backend: {}", e);
-             return Err(format!("Failed to initialize Baileys backend: {}", e));
-        }
-        *provider_lock = Some(Box::new(backend));
-        println!("Baileys backend initialized successfully.");
-    } else if backend == "rust" {
-        println!("Rust backend selected. Initializing Rust Adapter...");
-        let backend = RustBackend::new(app.clone());
-        if let Err(e) = backend.initialize("".to_string()).await {
-            eprintln!("Failed to initialize Rust backend: {}", e);
-            return Err(format!("Failed to initialize Rust backend: {}", e));
-        }
-        *provider_lock = Some(Box::new(backend));
-        println!("Rust backend initialized successfully.");
-    } else if backend == "wwebjs" || backend == "whatsapp-web.js" {
-        println!("WhatsApp-Web.js backend selected (Adapter to be implemented).");
-    } else {
-        return Err("Unsupported backend".to_string());
+    println!("Initializing {} backend for account {}...", kind.as_str(), account_id);
+    let provider: Arc<dyn WhatsAppProvider> = match kind {
+        BackendKind::Rust => Arc::new(RustBackend::new(app.clone(), account_id.clone())),
+        BackendKind::Baileys => Arc::new(baileys::backend(app.clone(), account_id.clone())),
+        BackendKind::Wwebjs => Arc::new(wwebjs::backend(app.clone(), account_id.clone())),
+    };
+    spawn_event_forwarder(app.clone(), account_id.clone(), provider.subscribe());
+    spawn_event_persister(app.clone(), account_id.clone(), storage.inner().clone(), provider.subscribe());
+    spawn_history_ingester(app.clone(), account_id.clone(), storage.inner().clone(), provider.subscribe());
+    let outbox = Outbox::spawn(app.clone(), account_id.clone(), storage.inner().clone(), &provider);
+    let scheduler = Scheduler::spawn(app.clone(), account_id.clone(), storage.inner().clone(), &outbox);
+    if let Err(e) = provider.initialize("".to_string()).await {
+        eprintln!("Failed to initialize {} backend for account {}: {}", kind.as_str(), account_id, e);
+        return Err(format!("Failed to initialize {} backend: {}", kind.as_str(), e));
     }
 
+    storage.save_account(Account {
+        id: account_id.clone(),
+        backend: kind.as_str().to_string(),
+        created_at: unix_now(),
+    }).await.map_err(|e| e.to_string())?;
+
+    manager.providers.lock().await.insert(account_id.clone(), provider);
+    manager.outboxes.lock().await.insert(account_id.clone(), outbox);
+    manager.schedulers.lock().await.insert(account_id.clone(), scheduler);
+    println!("Account {} initialized successfully.", account_id);
+
     Ok(())
 }
 
+#[derive(serde::Serialize)]
+pub struct AccountInfo {
+    #[serde(flatten)]
+    account: Account,
+    /// Whether a session is running for it
+    active: bool,
+}
+
+#[tauri::command]
+pub async fn get_accounts(
+    manager: State<'_, WhatsAppManager>,
+    storage: State<'_, Arc<SqliteStorage>>,
+) -> Result<Vec<AccountInfo>, String> {
+    let accounts = storage.get_accounts().await.map_err(|e| e.to_string())?;
+    let providers = manager.providers.lock().await;
+    Ok(accounts
+        .into_iter()
+        .map(|account| AccountInfo {
+            active: providers.contains_key(&account.id),
+            account,
+        })
+        .collect())
+}
+
+/// Disconnects an account and forgets its pairing. Messages still queued in its outbox or
+/// scheduled are dropped; its chats and messages stay in storage
+#[tauri::command]
+pub async fn remove_account(
+    app: AppHandle,
+    manager: State<'_, WhatsAppManager>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+) -> Result<(), String> {
+    validate_account_id(&account_id)?;
+
+    if let Some(provider) = manager.remove_session(&account_id).await {
+        let _ = provider.disconnect().await;
+    }
+
+    // The account may have been paired through any backend over time
+    AuthScheme::Baileys.clear(&storage, &account_id).await;
+    AuthScheme::WWebJs.clear(&storage, &account_id).await;
+    let session = RustBackend::session_path(&app, &account_id).map_err(|e| e.to_string())?;
+    if session.exists() {
+        std::fs::remove_file(&session).map_err(|e| e.to_string())?;
+    }
+
+    storage.remove_account(&account_id).await.map_err(|e| e.to_string())
+}
+
+/// Queues a text message in the account's outbox; it is sent as soon as the account is connected
 #[tauri::command]
 pub async fn send_message(
     manager: State<'_, WhatsAppManager>,
+    account_id: String,
     jid: String,
     content: String,
+) -> Result<OutboxEntry, String> {
+    let outbox = manager.outbox(&account_id).await?;
+    outbox.enqueue(OutgoingMessage {
+        chat_id: jid,
+        content,
+        quoted_id: None,
+        media: None,
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn send_reply(
+    manager: State<'_, WhatsAppManager>,
+    account_id: String,
+    jid: String,
+    content: String,
+    quoted_id: String,
+) -> Result<OutboxEntry, String> {
+    let outbox = manager.outbox(&account_id).await?;
+    outbox.enqueue(OutgoingMessage {
+        chat_id: jid,
+        content,
+        quoted_id: Some(quoted_id),
+        media: None,
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn send_media(
+    manager: State<'_, WhatsAppManager>,
+    account_id: String,
+    jid: String,
+    path: String,
+    mime_type: String,
+    caption: Option<String>,
+) -> Result<OutboxEntry, String> {
+    let outbox = manager.outbox(&account_id).await?;
+    outbox.enqueue(OutgoingMessage {
+        chat_id: jid,
+        content: caption.unwrap_or_default(),
+        quoted_id: None,
+        media: Some((PathBuf::from(path), mime_type)),
+    }).await.map_err(|e| e.to_string())
+}
+
+/// Messages of an account that are queued, being sent or failed
+#[tauri::command]
+pub async fn get_outbox(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+) -> Result<Vec<OutboxEntry>, String> {
+    storage.get_outbox(&account_id).await.map_err(|e| e.to_string())
+}
+
+/// Sends a failed (or backing off) message again right away
+#[tauri::command]
+pub async fn retry_outbox(
+    manager: State<'_, WhatsAppManager>,
+    account_id: String,
+    id: i64,
+) -> Result<OutboxEntry, String> {
+    let outbox = manager.outbox(&account_id).await?;
+    outbox.retry(id).await.map_err(|e| e.to_string())
+}
+
+/// Drops a queued or failed message. Messages already handed to WhatsApp cannot be cancelled
+#[tauri::command]
+pub async fn cancel_outbox(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    id: i64,
 ) -> Result<(), String> {
-    let provider_lock = manager.provider.lock().await;
+    if storage.cancel_outbox(&account_id, id).await.map_err(|e| e.to_string())? {
+        Ok(())
+    } else {
+        Err("Message is not queued for this account or is already being sent".to_string())
+    }
+}
+
+/// Schedules a text message. It is sent through the outbox once `send_at` (unix seconds)
+/// has passed; `if_missed` decides what happens if the app was closed at that time
+#[tauri::command]
+pub async fn schedule_message(
+    manager: State<'_, WhatsAppManager>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    jid: String,
+    content: String,
+    send_at: i64,
+    if_missed: Option<MissedPolicy>,
+) -> Result<ScheduledMessage, String> {
+    validate_account_id(&account_id)?;
+    if content.trim().is_empty() {
+        return Err("Cannot schedule an empty message".to_string());
+    }
+
+    let schedule = storage.save_schedule(ScheduledMessage {
+        id: 0,
+        account_id: account_id.clone(),
+        chat_id: jid,
+        content,
+        send_at,
+        if_missed: if_missed.unwrap_or_default(),
+        state: ScheduleState::Scheduled,
+        outbox_id: None,
+        created_at: unix_now(),
+    }).await.map_err(|e| e.to_string())?;
+
+    manager.reschedule(&account_id).await;
+    Ok(schedule)
+}
+
+#[tauri::command]
+pub async fn get_scheduled_messages(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+) -> Result<Vec<ScheduledMessage>, String> {
+    storage.get_schedules(&account_id).await.map_err(|e| e.to_string())
+}
 
-    if let Some(provider) = provider_lock.as_ref() {
-        provider.send_message(jid, content).await.map_err(|e| e.to_string())?;
+/// Changes a message that has not been sent yet
+#[tauri::command]
+pub async fn edit_scheduled_message(
+    manager: State<'_, WhatsAppManager>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    id: i64,
+    content: String,
+    send_at: i64,
+    if_missed: MissedPolicy,
+) -> Result<ScheduledMessage, String> {
+    if content.trim().is_empty() {
+        return Err("Cannot schedule an empty message".to_string());
+    }
+
+    let schedule = storage.update_schedule(&account_id, id, &content, send_at, if_missed).await
+        .map_err(|e| e.to_string())?
+        .ok_or("Message is not scheduled for this account, or was already sent or skipped")?;
+
+    manager.reschedule(&account_id).await;
+    Ok(schedule)
+}
+
+#[tauri::command]
+pub async fn cancel_scheduled_message(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    id: i64,
+) -> Result<(), String> {
+    if storage.cancel_schedule(&account_id, id).await.map_err(|e| e.to_string())? {
         Ok(())
     } else {
-        Err("No active session".to_string())
+        Err("Message is not scheduled for this account, or was already sent or skipped".to_string())
     }
 }
 
+/// Reacts to a message; an empty emoji removes our reaction
 #[tauri::command]
-pub async fn reset_session(
+pub async fn react(
+    manager: State<'_, WhatsAppManager>,
+    account_id: String,
+    chat_id: String,
+    message_id: String,
+    emoji: String,
+) -> Result<(), String> {
+    let provider = manager.provider(&account_id).await?;
+    provider.react(chat_id, message_id, emoji).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_reactions(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    message_id: String,
+) -> Result<Vec<Reaction>, String> {
+    storage.get_reactions(&account_id, &message_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_message_receipts(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    message_id: String,
+) -> Result<Vec<MessageReceipt>, String> {
+    storage.get_message_receipts(&account_id, &message_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn mark_read(
+    manager: State<'_, WhatsAppManager>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    chat_id: String,
+    sender_id: Option<String>,
+    message_ids: Vec<String>,
+) -> Result<(), String> {
+    // Read on this device whether or not the receipt gets through
+    storage.mark_chat_read(&account_id, &chat_id).await.map_err(|e| e.to_string())?;
+
+    let provider = manager.provider(&account_id).await?;
+    provider.send_read_receipt(chat_id, sender_id, message_ids).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_typing(
+    manager: State<'_, WhatsAppManager>,
+    account_id: String,
+    chat_id: String,
+    state: PresenceState,
+) -> Result<(), String> {
+    let provider = manager.provider(&account_id).await?;
+    provider.set_typing(chat_id, state).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn send_presence(
+    manager: State<'_, WhatsAppManager>,
+    account_id: String,
+    state: PresenceState,
+) -> Result<(), String> {
+    let provider = manager.provider(&account_id).await?;
+    provider.send_presence(state).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn subscribe_presence(
     manager: State<'_, WhatsAppManager>,
+    account_id: String,
+    jid: String,
 ) -> Result<(), String> {
-    let mut provider_lock = manager.provider.lock().await;
+    let provider = manager.provider(&account_id).await?;
+    provider.subscribe_presence(jid).await.map_err(|e| e.to_string())
+}
+
+/// Groups known from the last sync, without asking WhatsApp
+#[tauri::command]
+pub async fn get_groups(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+) -> Result<Vec<Group>, String> {
+    storage.get_groups(&account_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_group_participants(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    group_id: String,
+) -> Result<Vec<GroupParticipant>, String> {
+    storage.get_group_participants(&account_id, &group_id).await.map_err(|e| e.to_string())
+}
+
+async fn cache_group(storage: &SqliteStorage, account_id: &str, group: &GroupMetadata) -> Result<(), String> {
+    let (group, participants) = group.to_storage();
+    storage.save_group(account_id, group, participants).await.map_err(|e| e.to_string())
+}
+
+/// Fetches every group we are in and refreshes the stored copies
+#[tauri::command]
+pub async fn sync_groups(
+    manager: State<'_, WhatsAppManager>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+) -> Result<Vec<GroupMetadata>, String> {
+    let provider = manager.provider(&account_id).await?;
+
+    let groups = provider.get_groups().await.map_err(|e| e.to_string())?;
+    for group in &groups {
+        cache_group(&storage, &account_id, group).await?;
+    }
+    Ok(groups)
+}
+
+#[tauri::command]
+pub async fn get_group_metadata(
+    manager: State<'_, WhatsAppManager>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    group_id: String,
+) -> Result<GroupMetadata, String> {
+    let provider = manager.provider(&account_id).await?;
+
+    let group = provider.group_metadata(group_id).await.map_err(|e| e.to_string())?;
+    cache_group(&storage, &account_id, &group).await?;
+    Ok(group)
+}
+
+#[tauri::command]
+pub async fn create_group(
+    manager: State<'_, WhatsAppManager>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    subject: String,
+    participants: Vec<String>,
+) -> Result<GroupMetadata, String> {
+    let provider = manager.provider(&account_id).await?;
+
+    let group = provider.create_group(subject, participants).await.map_err(|e| e.to_string())?;
+    cache_group(&storage, &account_id, &group).await?;
+    Ok(group)
+}
+
+/// Applies `action` to the participants and returns the group as it is afterwards
+#[tauri::command]
+pub async fn update_group_participants(
+    manager: State<'_, WhatsAppManager>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    group_id: String,
+    participants: Vec<String>,
+    action: ParticipantAction,
+) -> Result<GroupMetadata, String> {
+    let provider = manager.provider(&account_id).await?;
+
+    provider.update_group_participants(group_id.clone(), participants, action).await.map_err(|e| e.to_string())?;
+    let group = provider.group_metadata(group_id).await.map_err(|e| e.to_string())?;
+    cache_group(&storage, &account_id, &group).await?;
+    Ok(group)
+}
+
+#[tauri::command]
+pub async fn set_group_subject(
+    manager: State<'_, WhatsAppManager>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    group_id: String,
+    subject: String,
+) -> Result<GroupMetadata, String> {
+    let provider = manager.provider(&account_id).await?;
+
+    provider.set_group_subject(group_id.clone(), subject).await.map_err(|e| e.to_string())?;
+    let group = provider.group_metadata(group_id).await.map_err(|e| e.to_string())?;
+    cache_group(&storage, &account_id, &group).await?;
+    Ok(group)
+}
+
+#[tauri::command]
+pub async fn set_group_description(
+    manager: State<'_, WhatsAppManager>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    group_id: String,
+    description: Option<String>,
+) -> Result<GroupMetadata, String> {
+    let provider = manager.provider(&account_id).await?;
+
+    provider.set_group_description(group_id.clone(), description).await.map_err(|e| e.to_string())?;
+    let group = provider.group_metadata(group_id).await.map_err(|e| e.to_string())?;
+    cache_group(&storage, &account_id, &group).await?;
+    Ok(group)
+}
+
+#[tauri::command]
+pub async fn leave_group(
+    manager: State<'_, WhatsAppManager>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    group_id: String,
+) -> Result<(), String> {
+    let provider = manager.provider(&account_id).await?;
+
+    provider.leave_group(group_id.clone()).await.map_err(|e| e.to_string())?;
+    storage.remove_group(&account_id, &group_id).await.map_err(|e| e.to_string())
+}
+
+/// Ghost mode rules: the global one (scope `*`) plus any per-chat overrides
+#[tauri::command]
+pub async fn get_privacy_rules(
+    privacy: State<'_, Arc<PrivacyPolicy>>,
+) -> Result<Vec<PrivacyRule>, String> {
+    Ok(privacy.rules())
+}
 
-    if let Some(provider) = provider_lock.take() {
+#[tauri::command]
+pub async fn set_privacy_rule(
+    manager: State<'_, WhatsAppManager>,
+    privacy: State<'_, Arc<PrivacyPolicy>>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    rule: PrivacyRule,
+) -> Result<(), String> {
+    let hide_presence = rule.scope == GLOBAL_PRIVACY_SCOPE && rule.presence == Some(false);
+    privacy.set_rule(storage.inner().as_ref(), rule).await.map_err(|e| e.to_string())?;
+
+    // Go offline right away instead of waiting for the next reconnect
+    if hide_presence {
+        let providers: Vec<_> = manager.providers.lock().await.values().cloned().collect();
+        for provider in providers {
+            let _ = provider.send_presence(PresenceState::Unavailable).await;
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn remove_privacy_rule(
+    privacy: State<'_, Arc<PrivacyPolicy>>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    scope: String,
+) -> Result<(), String> {
+    privacy.remove_rule(storage.inner().as_ref(), &scope).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_chats(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+) -> Result<Vec<Chat>, String> {
+    storage.get_chats(&account_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_contacts(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+) -> Result<Vec<Contact>, String> {
+    storage.get_contacts(&account_id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_messages(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    chat_id: String,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<Message>, String> {
+    storage.get_messages(&account_id, &chat_id, limit, offset).await.map_err(|e| e.to_string())
+}
+
+/// A page of a chat's history, oldest first, anchored on a cursor or a message
+#[tauri::command]
+pub async fn get_message_page(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    chat_id: String,
+    range: MessageRange,
+    limit: usize,
+) -> Result<MessagePage, String> {
+    storage.get_message_page(&account_id, &chat_id, &range, limit).await.map_err(|e| e.to_string())
+}
+
+/// Searches message content across all chats of the account, newest first
+#[tauri::command]
+pub async fn search_messages(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    query: String,
+    filters: Option<SearchFilters>,
+) -> Result<Vec<SearchHit>, String> {
+    storage.search_messages(&account_id, &query, &filters.unwrap_or_default()).await.map_err(|e| e.to_string())
+}
+
+/// Every revision of an edited message, oldest (the original) first
+#[tauri::command]
+pub async fn get_message_history(
+    storage: State<'_, Arc<SqliteStorage>>,
+    account_id: String,
+    message_id: String,
+) -> Result<Vec<MessageRevision>, String> {
+    storage.get_message_revisions(&account_id, &message_id).await.map_err(|e| e.to_string())
+}
+
+/// Returns the decrypted bytes of a cached media file as a raw IPC response
+#[tauri::command]
+pub async fn get_media(
+    media: State<'_, Arc<MediaCache>>,
+    account_id: String,
+    message_id: String,
+) -> Result<Response, String> {
+    match media.load(&account_id, &message_id).await.map_err(|e| e.to_string())? {
+        Some((_, data)) => Ok(Response::new(data)),
+        None => Err("Media not found".to_string()),
+    }
+}
+
+/// Changes the startup password and re-encrypts the database and media cache under the key
+/// derived from it. Returns false if `current_password` is wrong
+#[tauri::command]
+pub async fn change_password(
+    security: State<'_, Arc<SecurityManager>>,
+    storage: State<'_, Arc<SqliteStorage>>,
+    media: State<'_, Arc<MediaCache>>,
+    current_password: String,
+    new_password: String,
+) -> Result<bool, String> {
+    let old_key = security.get_master_key().ok_or("No startup password is set")?;
+    let Some(change) = security.begin_password_change(&current_password, &new_password).map_err(|e| e.to_string())? else {
+        return Ok(false);
+    };
+
+    // Until the new password is saved, every step can be undone and the old one still opens everything
+    if let Err(e) = media.stage_reencryption(&change).await {
+        media.discard_reencryption().await;
+        return Err(e.to_string());
+    }
+    if let Err(e) = storage.rekey(change.key()).await {
+        media.discard_reencryption().await;
+        return Err(e.to_string());
+    }
+    if let Err(e) = security.finish_password_change(change) {
+        if let Err(e) = storage.rekey(&old_key).await {
+            eprintln!("Failed to restore the database key: {}", e);
+        }
+        media.discard_reencryption().await;
+        return Err(e.to_string());
+    }
+
+    media.commit_reencryption().await.map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// Supervision status of an account's backend, if it runs a sidecar process
+#[tauri::command]
+pub async fn get_backend_health(
+    manager: State<'_, WhatsAppManager>,
+    account_id: String,
+) -> Result<Option<SupervisorHealth>, String> {
+    let providers = manager.providers.lock().await;
+    Ok(providers.get(&account_id).and_then(|p| p.health()))
+}
+
+#[tauri::command]
+pub async fn reset_session(
+    manager: State<'_, WhatsAppManager>,
+    account_id: String,
+) -> Result<(), String> {
+    if let Some(provider) = manager.remove_session(&account_id).await {
         provider.disconnect().await.map_err(|e| e.to_string())?;
     }
 
@@ -10,9 +10,11 @@ mod tests;
 
 use tauri::Manager;
 use backend::WhatsAppManager;
+use backend::privacy::PrivacyPolicy;
 use std::io::{self, Write};
 use std::process;
 use utils::security::SecurityManager;
+use storage::{MediaCache, SqliteStorage};
 use std::sync::Arc;
 
 fn main() {
@@ -133,8 +135,22 @@ fn main() {
         .setup(move |app| {
             let manager = WhatsAppManager::new();
 
+            // Shared encrypted storage, used by every backend
+            let data_dir = app.path().app_data_dir()?;
+            std::fs::create_dir_all(&data_dir)?;
+            let master_key = security.get_master_key();
+            let storage = Arc::new(SqliteStorage::new(
+                &data_dir.join("whaswapp.db").to_string_lossy(),
+                master_key.as_deref()
+            )?);
+            let media = Arc::new(MediaCache::new(data_dir.join("media"), security.clone(), storage.clone()));
+            let privacy = Arc::new(tauri::async_runtime::block_on(PrivacyPolicy::load(storage.as_ref()))?);
+
             // Pass the SecurityManager instance to Tauri state
             app.manage(security);
+            app.manage(storage);
+            app.manage(media);
+            app.manage(privacy);
             app.manage(manager);
 
             app.manage(SessionConfig {
@@ -146,8 +162,47 @@ fn main() {
         })
         .invoke_handler(tauri::generate_handler![
             commands::setup_session,
+            commands::get_accounts,
+            commands::remove_account,
             commands::send_message,
+            commands::send_reply,
+            commands::send_media,
+            commands::get_outbox,
+            commands::retry_outbox,
+            commands::cancel_outbox,
+            commands::schedule_message,
+            commands::get_scheduled_messages,
+            commands::edit_scheduled_message,
+            commands::cancel_scheduled_message,
+            commands::react,
+            commands::get_reactions,
+            commands::get_message_receipts,
+            commands::mark_read,
+            commands::set_typing,
+            commands::send_presence,
+            commands::subscribe_presence,
+            commands::get_groups,
+            commands::get_group_participants,
+            commands::sync_groups,
+            commands::get_group_metadata,
+            commands::create_group,
+            commands::update_group_participants,
+            commands::set_group_subject,
+            commands::set_group_description,
+            commands::leave_group,
+            commands::get_privacy_rules,
+            commands::set_privacy_rule,
+            commands::remove_privacy_rule,
+            commands::get_media,
+            commands::change_password,
+            commands::get_chats,
+            commands::get_contacts,
+            commands::get_messages,
+            commands::get_message_page,
+            commands::search_messages,
+            commands::get_message_history,
             commands::reset_session,
+            commands::get_backend_health,
             commands::get_session_config
         ])
         .run(tauri::generate_context!())
@@ -0,0 +1,110 @@
+use rusqlite::{Connection, ErrorCode};
+use std::error::Error;
+use std::fmt;
+
+/// Length of the vault key, which SQLCipher uses as its raw AES-256 key
+pub const KEY_LEN: usize = 32;
+
+#[derive(Debug)]
+pub enum KeyError {
+    /// The key is not `KEY_LEN` bytes
+    InvalidLength(usize),
+    /// The database is encrypted with a different key
+    WrongKey,
+    /// A key was given but the database is plain SQLite
+    NotEncrypted,
+    /// The database is encrypted (or not a database at all) and no key was given
+    KeyRequired,
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::InvalidLength(len) => write!(f, "Database key must be {} bytes, got {}", KEY_LEN, len),
+            KeyError::WrongKey => write!(f, "Database key is wrong"),
+            KeyError::NotEncrypted => write!(f, "Database is not encrypted"),
+            KeyError::KeyRequired => write!(f, "Database is encrypted and no key was given"),
+            KeyError::Sqlite(e) => write!(f, "Failed to open database: {}", e),
+        }
+    }
+}
+
+impl Error for KeyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            KeyError::Sqlite(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for KeyError {
+    fn from(e: rusqlite::Error) -> Self {
+        KeyError::Sqlite(e)
+    }
+}
+
+/// Opens `path` and applies `key`, failing straight away if the key does not open the file.
+///
+/// Databases keyed before the vault key was applied raw used its hex string as a
+/// passphrase; those are accepted once and rekeyed to the raw key.
+pub fn open_encrypted(path: &str, key: Option<&[u8]>) -> Result<Connection, KeyError> {
+    let conn = Connection::open(path)?;
+    let Some(key) = key else {
+        return match verify(&conn) {
+            Err(KeyError::WrongKey) => Err(KeyError::KeyRequired),
+            result => result.map(|()| conn),
+        };
+    };
+
+    apply_key(&conn, "key", key)?;
+    match verify(&conn) {
+        Ok(()) => return Ok(conn),
+        Err(KeyError::WrongKey) => {}
+        Err(e) => return Err(e),
+    }
+    drop(conn);
+
+    let legacy = Connection::open(path)?;
+    legacy.execute_batch(&format!("PRAGMA key = '{}';", hex::encode(key)))?;
+    if verify(&legacy).is_ok() {
+        rekey(&legacy, key)?;
+        return Ok(legacy);
+    }
+    drop(legacy);
+
+    // Tell a plain database apart from one under another key
+    let plain = Connection::open(path)?;
+    match verify(&plain) {
+        Ok(()) => Err(KeyError::NotEncrypted),
+        Err(e) => Err(e),
+    }
+}
+
+/// Re-encrypts the database under `key`. Every other connection to it must be closed
+/// first, and reopened with the new key afterwards
+pub fn rekey(conn: &Connection, key: &[u8]) -> Result<(), KeyError> {
+    apply_key(conn, "rekey", key)?;
+    verify(conn)
+}
+
+/// Sets `pragma` (`key` or `rekey`) to `key` as a raw key, so SQLCipher skips its
+/// passphrase derivation; the vault key already comes out of Argon2
+fn apply_key(conn: &Connection, pragma: &str, key: &[u8]) -> Result<(), KeyError> {
+    if key.len() != KEY_LEN {
+        return Err(KeyError::InvalidLength(key.len()));
+    }
+    // Hex digits need no escaping, and the pragma may answer with a row, so no `execute`
+    conn.execute_batch(&format!("PRAGMA {} = \"x'{}'\";", pragma, hex::encode(key)))?;
+    Ok(())
+}
+
+/// SQLCipher only decrypts on first access, so read the schema to find out whether the key fits
+fn verify(conn: &Connection) -> Result<(), KeyError> {
+    match conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(())) {
+        Ok(()) => Ok(()),
+        Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == ErrorCode::NotADatabase => Err(KeyError::WrongKey),
+        Err(e) => Err(e.into()),
+    }
+}
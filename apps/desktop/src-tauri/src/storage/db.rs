@@ -1,180 +1,1419 @@
-use super::{Storage, Message, Chat};
+use super::migrations::migrate;
+use super::pool::{open_connection, ConnectionPool, DEFAULT_READERS};
+use super::{Storage, Account, Message, MessageCursor, MessagePage, MessageRange, MissedPolicy, OutboxEntry, OutboxState, ScheduleState, ScheduledMessage, SearchFilters, SearchHit, DEFAULT_SEARCH_LIMIT, SNIPPET_END, SNIPPET_START, MessageReceipt, MessageRevision, MessageStatus, Chat, Contact, Group, GroupParticipant, MediaRecord, PrivacyRule, Reaction};
 use async_trait::async_trait;
 use serde_json::Value;
+use std::collections::HashSet;
 use std::error::Error;
-use std::sync::{Arc, Mutex};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
 use rusqlite::{params, Connection, OptionalExtension};
 
 pub struct SqliteStorage {
-    conn: Arc<Mutex<Connection>>,
+    pool: ConnectionPool,
 }
 
 impl SqliteStorage {
-    pub fn new(path: &str, key: Option<&str>) -> Result<Self, Box<dyn Error>> {
-        let conn = Connection::open(path)?;
+    pub fn new(path: &str, key: Option<&[u8]>) -> Result<Self, Box<dyn Error>> {
+        Self::with_readers(path, key, DEFAULT_READERS)
+    }
 
-        // Encryption
-        if let Some(k) = key {
-            conn.execute(&format!("PRAGMA key = '{}';", k), [])?;
-        }
+    /// Opens the database with `readers` read connections next to the writer
+    pub fn with_readers(path: &str, key: Option<&[u8]>, readers: usize) -> Result<Self, Box<dyn Error>> {
+        let mut conn = open_connection(path, key)?;
 
-        // Initialize Tables
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS messages (
-                id TEXT PRIMARY KEY,
-                chat_id TEXT NOT NULL,
-                content TEXT NOT NULL,
-                sender_id TEXT NOT NULL,
-                timestamp INTEGER NOT NULL,
-                from_me BOOLEAN NOT NULL
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS chats (
-                id TEXT PRIMARY KEY,
-                name TEXT NOT NULL,
-                unread_count INTEGER NOT NULL,
-                last_message_timestamp INTEGER NOT NULL
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS auth_store (
-                key TEXT PRIMARY KEY,
-                data TEXT NOT NULL
-            )",
-            [],
-        )?;
+        // Bring the schema up to date, refusing databases from a newer version
+        migrate(&mut conn)?;
 
         Ok(Self {
-            conn: Arc::new(Mutex::new(conn)),
+            pool: ConnectionPool::new(conn, path, key, readers)?,
         })
     }
+
+    /// Re-encrypts the database under a new vault key, e.g. after a password change
+    pub async fn rekey(&self, key: &[u8]) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.pool.rekey(key).await
+    }
+}
+
+/// Stored as its rank so the upsert can keep the furthest status with `MAX`
+impl ToSql for MessageStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(*self as i64))
+    }
+}
+
+impl FromSql for MessageStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_i64()? {
+            0 => Ok(MessageStatus::Sent),
+            1 => Ok(MessageStatus::Delivered),
+            2 => Ok(MessageStatus::Read),
+            3 => Ok(MessageStatus::Played),
+            other => Err(FromSqlError::OutOfRange(other)),
+        }
+    }
+}
+
+impl ToSql for OutboxState {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for OutboxState {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "pending" => Ok(OutboxState::Pending),
+            "sending" => Ok(OutboxState::Sending),
+            "sent" => Ok(OutboxState::Sent),
+            "failed" => Ok(OutboxState::Failed),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl ToSql for MissedPolicy {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for MissedPolicy {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "send_late" => Ok(MissedPolicy::SendLate),
+            "skip" => Ok(MissedPolicy::Skip),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl ToSql for ScheduleState {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for ScheduleState {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "scheduled" => Ok(ScheduleState::Scheduled),
+            "queued" => Ok(ScheduleState::Queued),
+            "skipped" => Ok(ScheduleState::Skipped),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+/// Turns what the user typed into an FTS5 query. Each word is quoted, so FTS syntax in the
+/// input is matched literally, and the last one is a prefix so results follow typing
+fn fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        return None;
+    }
+    Some(format!("{}*", terms.join(" ")))
+}
+
+fn upsert_message(conn: &Connection, account_id: &str, message: &Message) -> rusqlite::Result<()> {
+    conn.prepare_cached(
+        // Upsert rather than replace so a re-delivered message keeps its revoke flag and edits
+        "INSERT INTO messages (account_id, id, chat_id, content, sender_id, timestamp, from_me, revoked_at, revoked_by, edited_at, quoted_id, status)
+         VALUES (?12, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+         ON CONFLICT(account_id, id) DO UPDATE SET
+            chat_id = excluded.chat_id,
+            content = CASE WHEN messages.edited_at IS NULL THEN excluded.content ELSE messages.content END,
+            sender_id = excluded.sender_id,
+            timestamp = excluded.timestamp,
+            from_me = excluded.from_me,
+            revoked_at = COALESCE(messages.revoked_at, excluded.revoked_at),
+            revoked_by = COALESCE(messages.revoked_by, excluded.revoked_by),
+            edited_at = COALESCE(messages.edited_at, excluded.edited_at),
+            quoted_id = COALESCE(excluded.quoted_id, messages.quoted_id),
+            status = COALESCE(MAX(messages.status, excluded.status), messages.status, excluded.status)",
+    )?.execute(params![
+        message.id,
+        message.chat_id,
+        message.content,
+        message.sender_id,
+        message.timestamp,
+        message.from_me,
+        message.revoked_at,
+        message.revoked_by,
+        message.edited_at,
+        message.quoted_id,
+        message.status,
+        account_id
+    ])?;
+    Ok(())
+}
+
+fn replace_chat(conn: &Connection, account_id: &str, chat: &Chat) -> rusqlite::Result<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO chats (account_id, id, name, unread_count, last_message_timestamp)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?.execute(params![
+        account_id,
+        chat.id,
+        chat.name,
+        chat.unread_count,
+        chat.last_message_timestamp
+    ])?;
+    Ok(())
+}
+
+const MESSAGE_COLUMNS: &str = "id, chat_id, content, sender_id, timestamp, from_me, revoked_at, revoked_by, edited_at, quoted_id, status";
+
+fn message(row: &rusqlite::Row) -> rusqlite::Result<Message> {
+    Ok(Message {
+        id: row.get(0)?,
+        chat_id: row.get(1)?,
+        content: row.get(2)?,
+        sender_id: row.get(3)?,
+        timestamp: row.get(4)?,
+        from_me: row.get(5)?,
+        revoked_at: row.get(6)?,
+        revoked_by: row.get(7)?,
+        edited_at: row.get(8)?,
+        quoted_id: row.get(9)?,
+        status: row.get(10)?,
+    })
+}
+
+/// Up to `limit` messages of a chat strictly older (or newer) than the cursor, nearest first
+fn messages_beside(conn: &Connection, account_id: &str, chat_id: &str, cursor: &MessageCursor, older: bool, limit: usize) -> rusqlite::Result<Vec<Message>> {
+    let sql = if older {
+        "WHERE account_id = ?5 AND chat_id = ?1 AND (timestamp, id) < (?2, ?3) ORDER BY timestamp DESC, id DESC LIMIT ?4"
+    } else {
+        "WHERE account_id = ?5 AND chat_id = ?1 AND (timestamp, id) > (?2, ?3) ORDER BY timestamp ASC, id ASC LIMIT ?4"
+    };
+    let mut stmt = conn.prepare_cached(&format!("SELECT {} FROM messages {}", MESSAGE_COLUMNS, sql))?;
+    let rows = stmt.query_map(params![chat_id, cursor.timestamp, cursor.id, limit, account_id], message)?;
+    rows.collect()
+}
+
+fn has_messages_beside(conn: &Connection, account_id: &str, chat_id: &str, cursor: &MessageCursor, older: bool) -> rusqlite::Result<bool> {
+    let sql = if older {
+        "SELECT 1 FROM messages WHERE account_id = ?4 AND chat_id = ?1 AND (timestamp, id) < (?2, ?3) LIMIT 1"
+    } else {
+        "SELECT 1 FROM messages WHERE account_id = ?4 AND chat_id = ?1 AND (timestamp, id) > (?2, ?3) LIMIT 1"
+    };
+    conn.prepare_cached(sql)?.exists(params![chat_id, cursor.timestamp, cursor.id, account_id])
+}
+
+const SCHEDULE_COLUMNS: &str = "id, account_id, chat_id, content, send_at, if_missed, state, outbox_id, created_at";
+
+fn scheduled_message(row: &rusqlite::Row) -> rusqlite::Result<ScheduledMessage> {
+    Ok(ScheduledMessage {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        chat_id: row.get(2)?,
+        content: row.get(3)?,
+        send_at: row.get(4)?,
+        if_missed: row.get(5)?,
+        state: row.get(6)?,
+        outbox_id: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+fn get_schedule(conn: &Connection, id: i64) -> rusqlite::Result<Option<ScheduledMessage>> {
+    conn.query_row(
+        &format!("SELECT {} FROM scheduled_messages WHERE id = ?1", SCHEDULE_COLUMNS),
+        params![id],
+        scheduled_message,
+    ).optional()
+}
+
+fn list_schedules(conn: &Connection, sql: &str, params: impl rusqlite::Params) -> rusqlite::Result<Vec<ScheduledMessage>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM scheduled_messages {}", SCHEDULE_COLUMNS, sql))?;
+    let rows = stmt.query_map(params, scheduled_message)?;
+    rows.collect()
+}
+
+const OUTBOX_COLUMNS: &str = "id, account_id, chat_id, content, quoted_id, media_path, mime_type, state, attempts, next_attempt_at, last_error, message_id, created_at";
+
+fn outbox_entry(row: &rusqlite::Row) -> rusqlite::Result<OutboxEntry> {
+    Ok(OutboxEntry {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        chat_id: row.get(2)?,
+        content: row.get(3)?,
+        quoted_id: row.get(4)?,
+        media_path: row.get(5)?,
+        mime_type: row.get(6)?,
+        state: row.get(7)?,
+        attempts: row.get(8)?,
+        next_attempt_at: row.get(9)?,
+        last_error: row.get(10)?,
+        message_id: row.get(11)?,
+        created_at: row.get(12)?,
+    })
+}
+
+fn insert_outbox(conn: &Connection, entry: &OutboxEntry) -> rusqlite::Result<Option<OutboxEntry>> {
+    conn.execute(
+        "INSERT INTO outbox (account_id, chat_id, content, quoted_id, media_path, mime_type, state, attempts, next_attempt_at, last_error, message_id, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, ?8, NULL, NULL, ?9)",
+        params![
+            entry.account_id,
+            entry.chat_id,
+            entry.content,
+            entry.quoted_id,
+            entry.media_path,
+            entry.mime_type,
+            OutboxState::Pending,
+            entry.next_attempt_at,
+            entry.created_at
+        ],
+    )?;
+    get_outbox_entry(conn, conn.last_insert_rowid())
+}
+
+fn get_outbox_entry(conn: &Connection, id: i64) -> rusqlite::Result<Option<OutboxEntry>> {
+    conn.query_row(
+        &format!("SELECT {} FROM outbox WHERE id = ?1", OUTBOX_COLUMNS),
+        params![id],
+        outbox_entry,
+    ).optional()
+}
+
+/// `message_receipts` column holding the time a recipient reached `status`
+fn receipt_column(status: MessageStatus) -> Option<&'static str> {
+    match status {
+        MessageStatus::Sent => None,
+        MessageStatus::Delivered => Some("delivered_at"),
+        MessageStatus::Read => Some("read_at"),
+        MessageStatus::Played => Some("played_at"),
+    }
 }
 
 #[async_trait]
 impl Storage for SqliteStorage {
-    async fn save_message(&self, message: Message) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT OR REPLACE INTO messages (id, chat_id, content, sender_id, timestamp, from_me)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![
-                message.id,
-                message.chat_id,
-                message.content,
-                message.sender_id,
-                message.timestamp,
-                message.from_me
-            ],
-        ).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        Ok(())
-    }
-
-    async fn get_messages(&self, chat_id: &str, limit: usize, offset: usize) -> Result<Vec<Message>, Box<dyn Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, chat_id, content, sender_id, timestamp, from_me
-             FROM messages
-             WHERE chat_id = ?1
-             ORDER BY timestamp DESC
-             LIMIT ?2 OFFSET ?3"
-        ).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-
-        let message_iter = stmt.query_map(params![chat_id, limit, offset], |row| {
-            Ok(Message {
-                id: row.get(0)?,
-                chat_id: row.get(1)?,
-                content: row.get(2)?,
-                sender_id: row.get(3)?,
-                timestamp: row.get(4)?,
-                from_me: row.get(5)?,
-            })
-        }).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+    async fn save_message(&self, account_id: &str, message: Message) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.write(move |conn| {
+            upsert_message(conn, &account_id, &message).map_err(Into::into)
+        }).await
+    }
+
+    async fn save_messages(&self, account_id: &str, messages: Vec<Message>, chats: Vec<Chat>) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.write(move |conn| {
+            let tx = conn.transaction()?;
+            for message in &messages {
+                upsert_message(&tx, &account_id, message)?;
+            }
+            for chat in &chats {
+                replace_chat(&tx, &account_id, chat)?;
+            }
+            tx.commit().map_err(Into::into)
+        }).await
+    }
+
+    async fn get_messages(&self, account_id: &str, chat_id: &str, limit: usize, offset: usize) -> Result<Vec<Message>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let chat_id = chat_id.to_owned();
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM messages WHERE account_id = ?4 AND chat_id = ?1 ORDER BY timestamp DESC LIMIT ?2 OFFSET ?3",
+                MESSAGE_COLUMNS
+            ))?;
+            let rows = stmt.query_map(params![chat_id, limit, offset, account_id], message)?;
+            Ok(rows.collect::<rusqlite::Result<_>>()?)
+        }).await
+    }
+
+    async fn search_messages(&self, account_id: &str, query: &str, filters: &SearchFilters) -> Result<Vec<SearchHit>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let Some(query) = fts_query(query) else {
+            return Ok(Vec::new());
+        };
+
+        let filters = filters.clone();
+        self.pool.read(move |conn| {
+            // The FTS side is a subquery so MESSAGE_COLUMNS resolve against `messages` alone
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {}, hits.snippet
+                 FROM messages
+                 JOIN (SELECT rowid, snippet(messages_fts, 0, ?2, ?3, '…', 12) AS snippet
+                       FROM messages_fts WHERE messages_fts MATCH ?1) hits ON hits.rowid = messages.seq
+                 WHERE account_id = ?10
+                   AND (?4 IS NULL OR chat_id = ?4)
+                   AND (?5 IS NULL OR sender_id = ?5)
+                   AND (?6 IS NULL OR timestamp >= ?6)
+                   AND (?7 IS NULL OR timestamp < ?7)
+                   AND (?8 IS NULL OR from_me = ?8)
+                 ORDER BY timestamp DESC, id DESC
+                 LIMIT ?9",
+                MESSAGE_COLUMNS
+            ))?;
+
+            let hits = stmt.query_map(
+                params![
+                    query,
+                    SNIPPET_START.to_string(),
+                    SNIPPET_END.to_string(),
+                    filters.chat_id,
+                    filters.sender_id,
+                    filters.after,
+                    filters.before,
+                    filters.from_me,
+                    filters.limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+                    account_id
+                ],
+                |row| {
+                    Ok(SearchHit {
+                        message: message(row)?,
+                        snippet: row.get(11)?,
+                    })
+                },
+            )?;
+            Ok(hits.collect::<rusqlite::Result<_>>()?)
+        }).await
+    }
+
+    async fn save_message_edit(&self, account_id: &str, message_id: &str, content: &str, edited_at: i64) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let message_id = message_id.to_owned();
+        let content = content.to_owned();
+        self.pool.write(move |conn| {
+            let tx = conn.transaction()?;
+
+            let original: Option<(String, i64)> = tx.query_row(
+                "SELECT content, timestamp FROM messages WHERE account_id = ?2 AND id = ?1",
+                params![message_id, account_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ).optional()?;
+            let Some((original_content, original_timestamp)) = original else {
+                return Ok(false);
+            };
+
+            // The first edit also preserves the original as revision zero
+            let has_revisions = tx
+                .prepare("SELECT 1 FROM message_revisions WHERE account_id = ?2 AND message_id = ?1")
+                .and_then(|mut stmt| stmt.exists(params![message_id, account_id]))?;
+            if !has_revisions {
+                tx.execute(
+                    "INSERT INTO message_revisions (account_id, message_id, content, timestamp) VALUES (?4, ?1, ?2, ?3)",
+                    params![message_id, original_content, original_timestamp, account_id],
+                )?;
+            }
+
+            tx.execute(
+                "INSERT INTO message_revisions (account_id, message_id, content, timestamp) VALUES (?4, ?1, ?2, ?3)",
+                params![message_id, content, edited_at, account_id],
+            )?;
+
+            // Edits can arrive out of order; only a newer one becomes visible
+            tx.execute(
+                "UPDATE messages SET content = ?2, edited_at = ?3
+                 WHERE account_id = ?4 AND id = ?1 AND (edited_at IS NULL OR edited_at <= ?3)",
+                params![message_id, content, edited_at, account_id],
+            )?;
+
+            tx.commit()?;
+            Ok(true)
+        }).await
+    }
+
+    async fn get_message_revisions(&self, account_id: &str, message_id: &str) -> Result<Vec<MessageRevision>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let message_id = message_id.to_owned();
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT message_id, content, timestamp FROM message_revisions
+                 WHERE account_id = ?2 AND message_id = ?1
+                 ORDER BY timestamp ASC, id ASC"
+            )?;
+
+            let revision_iter = stmt.query_map(params![message_id, account_id], |row| {
+                Ok(MessageRevision {
+                    message_id: row.get(0)?,
+                    content: row.get(1)?,
+                    timestamp: row.get(2)?,
+                })
+            })?;
+
+            let mut revisions = Vec::new();
+            for revision in revision_iter {
+                revisions.push(revision?);
+            }
+
+            Ok(revisions)
+        }).await
+    }
+
+    async fn mark_message_revoked(&self, account_id: &str, message_id: &str, revoked_by: &str, revoked_at: i64) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let message_id = message_id.to_owned();
+        let revoked_by = revoked_by.to_owned();
+        self.pool.write(move |conn| {
+            let updated = conn.execute(
+                "UPDATE messages SET revoked_at = ?2, revoked_by = ?3 WHERE account_id = ?4 AND id = ?1",
+                params![message_id, revoked_at, revoked_by, account_id],
+            )?;
+            Ok(updated > 0)
+        }).await
+    }
+
+    async fn get_message_page(&self, account_id: &str, chat_id: &str, range: &MessageRange, limit: usize) -> Result<MessagePage, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let chat_id = chat_id.to_owned();
+        let range = range.clone();
+        self.pool.read(move |conn| {
+
+            let messages = match &range {
+                MessageRange::Latest => {
+                    let mut stmt = conn.prepare_cached(&format!(
+                        "SELECT {} FROM messages WHERE account_id = ?3 AND chat_id = ?1 ORDER BY timestamp DESC, id DESC LIMIT ?2",
+                        MESSAGE_COLUMNS
+                    ))?;
+                    let mut messages = stmt.query_map(params![chat_id, limit, account_id], message)
+                        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())?;
+                    messages.reverse();
+                    messages
+                }
+                MessageRange::Before { cursor } => {
+                    let mut messages = messages_beside(conn, &account_id, &chat_id, cursor, true, limit)?;
+                    messages.reverse();
+                    messages
+                }
+                MessageRange::After { cursor } => messages_beside(conn, &account_id, &chat_id, cursor, false, limit)?,
+                MessageRange::Around { message_id } => {
+                    let target = conn.query_row(
+                        &format!("SELECT {} FROM messages WHERE account_id = ?3 AND id = ?1 AND chat_id = ?2", MESSAGE_COLUMNS),
+                        params![message_id, chat_id, account_id],
+                        message,
+                    ).optional()?;
 
-        let mut messages = Vec::new();
-        for msg in message_iter {
-            messages.push(msg.map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?);
+                    match target {
+                        Some(target) if limit > 0 => {
+                            let cursor = MessageCursor::of(&target);
+                            let older = (limit - 1) / 2;
+                            let mut messages = messages_beside(conn, &account_id, &chat_id, &cursor, true, older)?;
+                            messages.reverse();
+                            messages.push(target);
+                            messages.extend(
+                                messages_beside(conn, &account_id, &chat_id, &cursor, false, limit - 1 - older)?,
+                            );
+                            messages
+                        }
+                        _ => Vec::new(),
+                    }
+                }
+            };
+
+            let (has_before, has_after) = match (messages.first(), messages.last()) {
+                (Some(first), Some(last)) => (
+                    has_messages_beside(conn, &account_id, &chat_id, &MessageCursor::of(first), true)?,
+                    has_messages_beside(conn, &account_id, &chat_id, &MessageCursor::of(last), false)?,
+                ),
+                _ => (false, false),
+            };
+
+            Ok(MessagePage { messages, has_before, has_after })
+        }).await
+    }
+
+    async fn get_message(&self, account_id: &str, message_id: &str) -> Result<Option<Message>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let message_id = message_id.to_owned();
+        self.pool.read(move |conn| {
+            conn.query_row(
+                &format!("SELECT {} FROM messages WHERE account_id = ?2 AND id = ?1", MESSAGE_COLUMNS),
+                params![message_id, account_id],
+                message,
+            )
+            .optional()
+            .map_err(Into::into)
+        }).await
+    }
+
+    async fn stored_message_ids(&self, account_id: &str, message_ids: &[String]) -> Result<HashSet<String>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let message_ids = message_ids.to_vec();
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare_cached("SELECT 1 FROM messages WHERE account_id = ?2 AND id = ?1")?;
+            let mut stored = HashSet::new();
+            for id in message_ids {
+                if stmt.exists(params![id, account_id])? {
+                    stored.insert(id);
+                }
+            }
+            Ok(stored)
+        }).await
+    }
+
+    async fn save_receipt(&self, account_id: &str, chat_id: &str, participant: &str, message_ids: &[String], status: MessageStatus, timestamp: i64) -> Result<Vec<(String, MessageStatus)>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        if status == MessageStatus::Sent {
+            return Ok(Vec::new());
         }
 
-        Ok(messages)
-    }
-
-    async fn save_chat(&self, chat: Chat) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT OR REPLACE INTO chats (id, name, unread_count, last_message_timestamp)
-             VALUES (?1, ?2, ?3, ?4)",
-            params![
-                chat.id,
-                chat.name,
-                chat.unread_count,
-                chat.last_message_timestamp
-            ],
-        ).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        Ok(())
-    }
-
-    async fn get_chats(&self) -> Result<Vec<Chat>, Box<dyn Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, name, unread_count, last_message_timestamp FROM chats ORDER BY last_message_timestamp DESC"
-        ).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-
-        let chat_iter = stmt.query_map([], |row| {
-            Ok(Chat {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                unread_count: row.get(2)?,
-                last_message_timestamp: row.get(3)?,
+        let chat_id = chat_id.to_owned();
+        let participant = participant.to_owned();
+        let message_ids = message_ids.to_vec();
+        self.pool.write(move |conn| {
+            let tx = conn.transaction()?;
+
+            // A receipt naming the chat itself is already aggregated by the backend
+            let per_participant = chat_id.ends_with("@g.us") && participant != chat_id;
+            let members: i64 = if per_participant {
+                tx.query_row(
+                    "SELECT COUNT(*) FROM group_participants WHERE account_id = ?2 AND group_id = ?1",
+                    params![chat_id, account_id],
+                    |row| row.get(0),
+                )?
+            } else {
+                1
+            };
+            // Everyone but us; without stored members the first receipt has to do
+            let needed = (members - 1).max(1);
+
+            let mut changed = Vec::new();
+            for message_id in message_ids {
+                let current: Option<Option<MessageStatus>> = tx.query_row(
+                    "SELECT status FROM messages WHERE account_id = ?2 AND id = ?1 AND from_me = 1",
+                    params![message_id, account_id],
+                    |row| row.get(0),
+                ).optional()?;
+                let Some(current) = current else {
+                    continue;
+                };
+
+                // Reading implies delivery, so the earlier steps are filled in as well
+                tx.execute(
+                    "INSERT INTO message_receipts (account_id, message_id, participant, delivered_at, read_at, played_at)
+                     VALUES (?6, ?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT(account_id, message_id, participant) DO UPDATE SET
+                        delivered_at = COALESCE(message_receipts.delivered_at, excluded.delivered_at),
+                        read_at = COALESCE(message_receipts.read_at, excluded.read_at),
+                        played_at = COALESCE(message_receipts.played_at, excluded.played_at)",
+                    params![
+                        message_id,
+                        participant,
+                        timestamp,
+                        (status >= MessageStatus::Read).then_some(timestamp),
+                        (status >= MessageStatus::Played).then_some(timestamp),
+                        account_id
+                    ],
+                )?;
+
+                let mut reached = None;
+                for candidate in [MessageStatus::Played, MessageStatus::Read, MessageStatus::Delivered] {
+                    let Some(column) = receipt_column(candidate).filter(|_| candidate <= status) else {
+                        continue;
+                    };
+                    if !per_participant {
+                        reached = Some(candidate);
+                        break;
+                    }
+                    let count: i64 = tx.query_row(
+                        &format!("SELECT COUNT(*) FROM message_receipts WHERE account_id = ?2 AND message_id = ?1 AND {} IS NOT NULL", column),
+                        params![message_id, account_id],
+                        |row| row.get(0),
+                    )?;
+                    if count >= needed {
+                        reached = Some(candidate);
+                        break;
+                    }
+                }
+
+                if let Some(reached) = reached.filter(|r| current.is_none_or(|c| *r > c)) {
+                    tx.execute(
+                        "UPDATE messages SET status = ?2 WHERE account_id = ?3 AND id = ?1",
+                        params![message_id, reached, account_id],
+                    )?;
+                    changed.push((message_id.clone(), reached));
+                }
+            }
+
+            tx.commit()?;
+            Ok(changed)
+        }).await
+    }
+
+    async fn get_message_receipts(&self, account_id: &str, message_id: &str) -> Result<Vec<MessageReceipt>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let message_id = message_id.to_owned();
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT message_id, participant, delivered_at, read_at, played_at FROM message_receipts
+                 WHERE account_id = ?2 AND message_id = ?1
+                 ORDER BY COALESCE(read_at, delivered_at) ASC"
+            )?;
+
+            let receipt_iter = stmt.query_map(params![message_id, account_id], |row| {
+                Ok(MessageReceipt {
+                    message_id: row.get(0)?,
+                    participant: row.get(1)?,
+                    delivered_at: row.get(2)?,
+                    read_at: row.get(3)?,
+                    played_at: row.get(4)?,
+                })
+            })?;
+
+            let mut receipts = Vec::new();
+            for receipt in receipt_iter {
+                receipts.push(receipt?);
+            }
+
+            Ok(receipts)
+        }).await
+    }
+
+    async fn save_reaction(&self, account_id: &str, reaction: Reaction) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.write(move |conn| {
+            conn.execute(
+                "INSERT INTO reactions (account_id, message_id, chat_id, sender_id, emoji, timestamp)
+                 VALUES (?6, ?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(account_id, message_id, sender_id) DO UPDATE SET
+                    emoji = excluded.emoji,
+                    timestamp = excluded.timestamp
+                 WHERE excluded.timestamp >= reactions.timestamp",
+                params![
+                    reaction.message_id,
+                    reaction.chat_id,
+                    reaction.sender_id,
+                    reaction.emoji,
+                    reaction.timestamp,
+                    account_id
+                ],
+            )?;
+            Ok(())
+        }).await
+    }
+
+    async fn remove_reaction(&self, account_id: &str, message_id: &str, sender_id: &str, timestamp: i64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let message_id = message_id.to_owned();
+        let sender_id = sender_id.to_owned();
+        self.pool.write(move |conn| {
+            conn.execute(
+                "DELETE FROM reactions WHERE account_id = ?4 AND message_id = ?1 AND sender_id = ?2 AND timestamp <= ?3",
+                params![message_id, sender_id, timestamp, account_id],
+            )?;
+            Ok(())
+        }).await
+    }
+
+    async fn get_reactions(&self, account_id: &str, message_id: &str) -> Result<Vec<Reaction>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let message_id = message_id.to_owned();
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT message_id, chat_id, sender_id, emoji, timestamp FROM reactions
+                 WHERE account_id = ?2 AND message_id = ?1
+                 ORDER BY timestamp ASC"
+            )?;
+
+            let reaction_iter = stmt.query_map(params![message_id, account_id], |row| {
+                Ok(Reaction {
+                    message_id: row.get(0)?,
+                    chat_id: row.get(1)?,
+                    sender_id: row.get(2)?,
+                    emoji: row.get(3)?,
+                    timestamp: row.get(4)?,
+                })
+            })?;
+
+            let mut reactions = Vec::new();
+            for reaction in reaction_iter {
+                reactions.push(reaction?);
+            }
+
+            Ok(reactions)
+        }).await
+    }
+
+    async fn save_history(&self, account_id: &str, chats: Vec<Chat>, contacts: Vec<Contact>, messages: Vec<Message>) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.write(move |conn| {
+            let tx = conn.transaction()?;
+
+            for contact in contacts {
+                tx.execute(
+                    "INSERT INTO contacts (account_id, jid, name, notify_name) VALUES (?4, ?1, ?2, ?3)
+                     ON CONFLICT(account_id, jid) DO UPDATE SET
+                        name = COALESCE(excluded.name, contacts.name),
+                        notify_name = COALESCE(excluded.notify_name, contacts.notify_name)",
+                    params![contact.jid, contact.name, contact.notify_name, account_id],
+                )?;
+            }
+
+            // Live delivery may have stored a message first; that copy can carry edits or a revoke
+            for message in messages {
+                tx.execute(
+                    "INSERT OR IGNORE INTO messages (account_id, id, chat_id, content, sender_id, timestamp, from_me, revoked_at, revoked_by, edited_at, quoted_id, status)
+                     VALUES (?12, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                    params![
+                        message.id,
+                        message.chat_id,
+                        message.content,
+                        message.sender_id,
+                        message.timestamp,
+                        message.from_me,
+                        message.revoked_at,
+                        message.revoked_by,
+                        message.edited_at,
+                        message.quoted_id,
+                        message.status,
+                        account_id
+                    ],
+                )?;
+            }
+
+            // The phone's unread count is authoritative at sync time
+            for chat in chats {
+                tx.execute(
+                    "INSERT INTO chats (account_id, id, name, unread_count, last_message_timestamp) VALUES (?5, ?1, ?2, ?3, ?4)
+                     ON CONFLICT(account_id, id) DO UPDATE SET
+                        name = CASE WHEN excluded.name = excluded.id THEN chats.name ELSE excluded.name END,
+                        unread_count = excluded.unread_count,
+                        last_message_timestamp = MAX(chats.last_message_timestamp, excluded.last_message_timestamp)",
+                    params![chat.id, chat.name, chat.unread_count, chat.last_message_timestamp, account_id],
+                )?;
+            }
+
+            tx.commit()?;
+            Ok(())
+        }).await
+    }
+
+    async fn get_contact(&self, account_id: &str, jid: &str) -> Result<Option<Contact>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let jid = jid.to_owned();
+        self.pool.read(move |conn| {
+            conn.query_row(
+                "SELECT jid, name, notify_name FROM contacts WHERE account_id = ?2 AND jid = ?1",
+                params![jid, account_id],
+                |row| Ok(Contact {
+                    jid: row.get(0)?,
+                    name: row.get(1)?,
+                    notify_name: row.get(2)?,
+                }),
+            )
+            .optional()
+            .map_err(Into::into)
+        }).await
+    }
+
+    async fn get_contacts(&self, account_id: &str) -> Result<Vec<Contact>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT jid, name, notify_name FROM contacts WHERE account_id = ?1 ORDER BY COALESCE(name, notify_name, jid) ASC"
+            )?;
+
+            let contact_iter = stmt.query_map(params![account_id], |row| {
+                Ok(Contact {
+                    jid: row.get(0)?,
+                    name: row.get(1)?,
+                    notify_name: row.get(2)?,
+                })
+            })?;
+
+            let mut contacts = Vec::new();
+            for contact in contact_iter {
+                contacts.push(contact?);
+            }
+
+            Ok(contacts)
+        }).await
+    }
+
+    async fn save_chat(&self, account_id: &str, chat: Chat) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.write(move |conn| {
+            replace_chat(conn, &account_id, &chat).map_err(Into::into)
+        }).await
+    }
+
+    async fn get_chat(&self, account_id: &str, chat_id: &str) -> Result<Option<Chat>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let chat_id = chat_id.to_owned();
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, name, unread_count, last_message_timestamp FROM chats WHERE account_id = ?2 AND id = ?1"
+            )?;
+
+            stmt.query_row(params![chat_id, account_id], |row| {
+                Ok(Chat {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    unread_count: row.get(2)?,
+                    last_message_timestamp: row.get(3)?,
+                })
             })
-        }).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+            .optional()
+            .map_err(Into::into)
+        }).await
+    }
 
-        let mut chats = Vec::new();
-        for chat in chat_iter {
-            chats.push(chat.map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?);
-        }
+    async fn get_chats(&self, account_id: &str) -> Result<Vec<Chat>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, name, unread_count, last_message_timestamp FROM chats WHERE account_id = ?1 ORDER BY last_message_timestamp DESC"
+            )?;
+
+            let chat_iter = stmt.query_map(params![account_id], |row| {
+                Ok(Chat {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    unread_count: row.get(2)?,
+                    last_message_timestamp: row.get(3)?,
+                })
+            })?;
 
-        Ok(chats)
+            let mut chats = Vec::new();
+            for chat in chat_iter {
+                chats.push(chat?);
+            }
+
+            Ok(chats)
+        }).await
     }
 
-    async fn save_auth_data(&self, key: &str, data: &Value) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        let json_str = serde_json::to_string(data).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+    async fn mark_chat_read(&self, account_id: &str, chat_id: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let chat_id = chat_id.to_owned();
+        self.pool.write(move |conn| {
+            conn.execute("UPDATE chats SET unread_count = 0 WHERE account_id = ?2 AND id = ?1", params![chat_id, account_id])?;
+            Ok(())
+        }).await
+    }
+
+    async fn save_group(&self, account_id: &str, group: Group, participants: Vec<GroupParticipant>) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.write(move |conn| {
+            let tx = conn.transaction()?;
 
-        conn.execute(
-            "INSERT OR REPLACE INTO auth_store (key, data) VALUES (?1, ?2)",
-            params![key, json_str],
-        ).map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        Ok(())
+            tx.execute(
+                "INSERT OR REPLACE INTO groups (account_id, id, subject, description, owner, created_at)
+                 VALUES (?6, ?1, ?2, ?3, ?4, ?5)",
+                params![group.id, group.subject, group.description, group.owner, group.created_at, account_id],
+            )?;
+
+            tx.execute("DELETE FROM group_participants WHERE account_id = ?2 AND group_id = ?1", params![group.id, account_id])?;
+            for participant in participants {
+                tx.execute(
+                    "INSERT OR REPLACE INTO group_participants (account_id, group_id, jid, role) VALUES (?4, ?1, ?2, ?3)",
+                    params![group.id, participant.jid, participant.role, account_id],
+                )?;
+            }
+
+            tx.commit()?;
+            Ok(())
+        }).await
     }
 
-    async fn get_auth_data(&self, key: &str) -> Result<Option<Value>, Box<dyn Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT data FROM auth_store WHERE key = ?1")
-            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
+    async fn get_groups(&self, account_id: &str) -> Result<Vec<Group>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, subject, description, owner, created_at FROM groups WHERE account_id = ?1 ORDER BY subject ASC"
+            )?;
 
-        let result: Option<String> = stmt.query_row(params![key], |row| row.get(0))
+            let group_iter = stmt.query_map(params![account_id], |row| {
+                Ok(Group {
+                    id: row.get(0)?,
+                    subject: row.get(1)?,
+                    description: row.get(2)?,
+                    owner: row.get(3)?,
+                    created_at: row.get(4)?,
+                })
+            })?;
+
+            let mut groups = Vec::new();
+            for group in group_iter {
+                groups.push(group?);
+            }
+
+            Ok(groups)
+        }).await
+    }
+
+    async fn get_group_participants(&self, account_id: &str, group_id: &str) -> Result<Vec<GroupParticipant>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let group_id = group_id.to_owned();
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT group_id, jid, role FROM group_participants WHERE account_id = ?2 AND group_id = ?1 ORDER BY jid ASC"
+            )?;
+
+            let participant_iter = stmt.query_map(params![group_id, account_id], |row| {
+                Ok(GroupParticipant {
+                    group_id: row.get(0)?,
+                    jid: row.get(1)?,
+                    role: row.get(2)?,
+                })
+            })?;
+
+            let mut participants = Vec::new();
+            for participant in participant_iter {
+                participants.push(participant?);
+            }
+
+            Ok(participants)
+        }).await
+    }
+
+    async fn remove_group(&self, account_id: &str, group_id: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let group_id = group_id.to_owned();
+        self.pool.write(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM group_participants WHERE account_id = ?2 AND group_id = ?1", params![group_id, account_id])?;
+            tx.execute("DELETE FROM groups WHERE account_id = ?2 AND id = ?1", params![group_id, account_id])?;
+            tx.commit()?;
+            Ok(())
+        }).await
+    }
+
+    async fn save_media(&self, account_id: &str, media: MediaRecord) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.write(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO media (account_id, message_id, chat_id, mime_type, file_name, size, path, timestamp)
+                 VALUES (?8, ?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    media.message_id,
+                    media.chat_id,
+                    media.mime_type,
+                    media.file_name,
+                    media.size,
+                    media.path,
+                    media.timestamp,
+                    account_id
+                ],
+            )?;
+            Ok(())
+        }).await
+    }
+
+    async fn get_media(&self, account_id: &str, message_id: &str) -> Result<Option<MediaRecord>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let message_id = message_id.to_owned();
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT message_id, chat_id, mime_type, file_name, size, path, timestamp FROM media WHERE account_id = ?2 AND message_id = ?1"
+            )?;
+
+            stmt.query_row(params![message_id, account_id], |row| {
+                Ok(MediaRecord {
+                    message_id: row.get(0)?,
+                    chat_id: row.get(1)?,
+                    mime_type: row.get(2)?,
+                    file_name: row.get(3)?,
+                    size: row.get(4)?,
+                    path: row.get(5)?,
+                    timestamp: row.get(6)?,
+                })
+            })
             .optional()
-            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-
-        match result {
-            Some(json_str) => {
-                let val: Value = serde_json::from_str(&json_str)
-                    .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-                Ok(Some(val))
-            },
-            None => Ok(None),
-        }
+            .map_err(Into::into)
+        }).await
+    }
+
+    async fn save_privacy_rule(&self, rule: PrivacyRule) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.pool.write(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO privacy_rules (scope, read_receipts, chat_state, presence)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![rule.scope, rule.read_receipts, rule.chat_state, rule.presence],
+            )?;
+            Ok(())
+        }).await
+    }
+
+    async fn get_privacy_rules(&self) -> Result<Vec<PrivacyRule>, Box<dyn Error + Send + Sync>> {
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT scope, read_receipts, chat_state, presence FROM privacy_rules"
+            )?;
+
+            let rule_iter = stmt.query_map([], |row| {
+                Ok(PrivacyRule {
+                    scope: row.get(0)?,
+                    read_receipts: row.get(1)?,
+                    chat_state: row.get(2)?,
+                    presence: row.get(3)?,
+                })
+            })?;
+
+            let mut rules = Vec::new();
+            for rule in rule_iter {
+                rules.push(rule?);
+            }
+
+            Ok(rules)
+        }).await
+    }
+
+    async fn remove_privacy_rule(&self, scope: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let scope = scope.to_owned();
+        self.pool.write(move |conn| {
+            conn.execute("DELETE FROM privacy_rules WHERE scope = ?1", params![scope])?;
+            Ok(())
+        }).await
+    }
+
+    async fn enqueue_outbox(&self, entry: OutboxEntry) -> Result<OutboxEntry, Box<dyn Error + Send + Sync>> {
+        self.pool.write(move |conn| {
+            insert_outbox(conn, &entry)?
+                .ok_or_else(|| "Queued outbox entry disappeared".into())
+        }).await
+    }
+
+    async fn get_outbox(&self, account_id: &str) -> Result<Vec<OutboxEntry>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM outbox WHERE account_id = ?1 AND state != ?2 ORDER BY id ASC",
+                OUTBOX_COLUMNS
+            ))?;
+
+            let entry_iter = stmt.query_map(params![account_id, OutboxState::Sent], outbox_entry)?;
+
+            let mut entries = Vec::new();
+            for entry in entry_iter {
+                entries.push(entry?);
+            }
+
+            Ok(entries)
+        }).await
+    }
+
+    async fn claim_outbox(&self, account_id: &str, now: i64) -> Result<Option<OutboxEntry>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.write(move |conn| {
+            let tx = conn.transaction()?;
+
+            let id: Option<i64> = tx.query_row(
+                "SELECT o.id FROM outbox o
+                 WHERE o.account_id = ?1 AND o.state = 'pending' AND o.next_attempt_at <= ?2
+                   AND NOT EXISTS (
+                       SELECT 1 FROM outbox p
+                       WHERE p.account_id = o.account_id AND p.chat_id = o.chat_id AND p.id < o.id
+                         AND p.state IN ('pending', 'sending')
+                   )
+                 ORDER BY o.id ASC
+                 LIMIT 1",
+                params![account_id, now],
+                |row| row.get(0),
+            ).optional()?;
+            let Some(id) = id else {
+                return Ok(None);
+            };
+
+            tx.execute(
+                "UPDATE outbox SET state = ?2 WHERE id = ?1",
+                params![id, OutboxState::Sending],
+            )?;
+            let entry = get_outbox_entry(&tx, id)?;
+
+            tx.commit()?;
+            Ok(entry)
+        }).await
+    }
+
+    async fn next_outbox_attempt(&self, account_id: &str) -> Result<Option<i64>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.read(move |conn| {
+            conn.query_row(
+                "SELECT MIN(next_attempt_at) FROM outbox WHERE account_id = ?1 AND state = ?2",
+                params![account_id, OutboxState::Pending],
+                |row| row.get(0),
+            ).map_err(Into::into)
+        }).await
+    }
+
+    async fn finish_outbox(&self, id: i64, message_id: &str) -> Result<Option<OutboxEntry>, Box<dyn Error + Send + Sync>> {
+        let message_id = message_id.to_owned();
+        self.pool.write(move |conn| {
+            conn.execute(
+                "UPDATE outbox SET state = ?2, message_id = ?3, attempts = attempts + 1, last_error = NULL WHERE id = ?1",
+                params![id, OutboxState::Sent, message_id],
+            )?;
+            get_outbox_entry(conn, id).map_err(Into::into)
+        }).await
+    }
+
+    async fn fail_outbox(&self, id: i64, error: &str, retry_at: Option<i64>) -> Result<Option<OutboxEntry>, Box<dyn Error + Send + Sync>> {
+        let error = error.to_owned();
+        self.pool.write(move |conn| {
+            let state = if retry_at.is_some() { OutboxState::Pending } else { OutboxState::Failed };
+            conn.execute(
+                "UPDATE outbox SET state = ?2, attempts = attempts + 1, last_error = ?3,
+                    next_attempt_at = COALESCE(?4, next_attempt_at)
+                 WHERE id = ?1",
+                params![id, state, error, retry_at],
+            )?;
+            get_outbox_entry(conn, id).map_err(Into::into)
+        }).await
+    }
+
+    async fn retry_outbox(&self, account_id: &str, id: i64, now: i64) -> Result<Option<OutboxEntry>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.write(move |conn| {
+            let updated = conn.execute(
+                "UPDATE outbox SET state = ?2, attempts = 0, next_attempt_at = ?3
+                 WHERE id = ?1 AND account_id = ?4 AND state IN ('pending', 'failed')",
+                params![id, OutboxState::Pending, now, account_id],
+            )?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            get_outbox_entry(conn, id).map_err(Into::into)
+        }).await
+    }
+
+    async fn cancel_outbox(&self, account_id: &str, id: i64) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.write(move |conn| {
+            let deleted = conn.execute(
+                "DELETE FROM outbox WHERE id = ?1 AND account_id = ?2 AND state IN ('pending', 'failed')",
+                params![id, account_id],
+            )?;
+            Ok(deleted > 0)
+        }).await
+    }
+
+    async fn requeue_outbox(&self, account_id: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.write(move |conn| {
+            conn.execute(
+                "UPDATE outbox SET state = ?2 WHERE account_id = ?1 AND state = ?3",
+                params![account_id, OutboxState::Pending, OutboxState::Sending],
+            )?;
+            Ok(())
+        }).await
+    }
+
+    async fn save_schedule(&self, schedule: ScheduledMessage) -> Result<ScheduledMessage, Box<dyn Error + Send + Sync>> {
+        self.pool.write(move |conn| {
+            conn.execute(
+                "INSERT INTO scheduled_messages (account_id, chat_id, content, send_at, if_missed, state, outbox_id, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, NULL, ?7)",
+                params![
+                    schedule.account_id,
+                    schedule.chat_id,
+                    schedule.content,
+                    schedule.send_at,
+                    schedule.if_missed,
+                    ScheduleState::Scheduled,
+                    schedule.created_at
+                ],
+            )?;
+
+            get_schedule(conn, conn.last_insert_rowid())?
+                .ok_or_else(|| "Scheduled message disappeared".into())
+        }).await
+    }
+
+    async fn get_schedules(&self, account_id: &str) -> Result<Vec<ScheduledMessage>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.read(move |conn| {
+            list_schedules(conn, "WHERE account_id = ?1 ORDER BY send_at ASC, id ASC", params![account_id])
+                .map_err(Into::into)
+        }).await
+    }
+
+    async fn update_schedule(&self, account_id: &str, id: i64, content: &str, send_at: i64, if_missed: MissedPolicy) -> Result<Option<ScheduledMessage>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        let content = content.to_owned();
+        self.pool.write(move |conn| {
+            let updated = conn.execute(
+                "UPDATE scheduled_messages SET content = ?2, send_at = ?3, if_missed = ?4
+                 WHERE id = ?1 AND account_id = ?6 AND state = ?5",
+                params![id, content, send_at, if_missed, ScheduleState::Scheduled, account_id],
+            )?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            get_schedule(conn, id).map_err(Into::into)
+        }).await
+    }
+
+    async fn cancel_schedule(&self, account_id: &str, id: i64) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.write(move |conn| {
+            let deleted = conn.execute(
+                "DELETE FROM scheduled_messages WHERE id = ?1 AND account_id = ?3 AND state = ?2",
+                params![id, ScheduleState::Scheduled, account_id],
+            )?;
+            Ok(deleted > 0)
+        }).await
+    }
+
+    async fn due_schedules(&self, account_id: &str, now: i64) -> Result<Vec<ScheduledMessage>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.read(move |conn| {
+            list_schedules(
+                conn,
+                "WHERE account_id = ?1 AND state = ?2 AND send_at <= ?3 ORDER BY send_at ASC, id ASC",
+                params![account_id, ScheduleState::Scheduled, now],
+            ).map_err(Into::into)
+        }).await
+    }
+
+    async fn next_schedule_at(&self, account_id: &str) -> Result<Option<i64>, Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.read(move |conn| {
+            conn.query_row(
+                "SELECT MIN(send_at) FROM scheduled_messages WHERE account_id = ?1 AND state = ?2",
+                params![account_id, ScheduleState::Scheduled],
+                |row| row.get(0),
+            ).map_err(Into::into)
+        }).await
+    }
+
+    async fn queue_schedule(&self, id: i64, now: i64) -> Result<Option<(ScheduledMessage, OutboxEntry)>, Box<dyn Error + Send + Sync>> {
+        self.pool.write(move |conn| {
+            let tx = conn.transaction()?;
+
+            let schedule = get_schedule(&tx, id)?;
+            let Some(schedule) = schedule.filter(|s| s.state == ScheduleState::Scheduled && s.send_at <= now) else {
+                return Ok(None);
+            };
+
+            let entry = insert_outbox(&tx, &OutboxEntry {
+                id: 0,
+                account_id: schedule.account_id.clone(),
+                chat_id: schedule.chat_id.clone(),
+                content: schedule.content.clone(),
+                quoted_id: None,
+                media_path: None,
+                mime_type: None,
+                state: OutboxState::Pending,
+                attempts: 0,
+                next_attempt_at: now,
+                last_error: None,
+                message_id: None,
+                created_at: now,
+            })?
+                .ok_or("Queued outbox entry disappeared")?;
+
+            tx.execute(
+                "UPDATE scheduled_messages SET state = ?2, outbox_id = ?3 WHERE id = ?1",
+                params![id, ScheduleState::Queued, entry.id],
+            )?;
+
+            tx.commit()?;
+            Ok(Some((
+                ScheduledMessage { state: ScheduleState::Queued, outbox_id: Some(entry.id), ..schedule },
+                entry,
+            )))
+        }).await
+    }
+
+    async fn skip_schedule(&self, id: i64, now: i64) -> Result<Option<ScheduledMessage>, Box<dyn Error + Send + Sync>> {
+        self.pool.write(move |conn| {
+            let updated = conn.execute(
+                "UPDATE scheduled_messages SET state = ?2 WHERE id = ?1 AND state = ?3 AND send_at <= ?4",
+                params![id, ScheduleState::Skipped, ScheduleState::Scheduled, now],
+            )?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            get_schedule(conn, id).map_err(Into::into)
+        }).await
+    }
+
+    async fn save_account(&self, account: Account) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.pool.write(move |conn| {
+            conn.execute(
+                "INSERT INTO accounts (id, backend, created_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(id) DO UPDATE SET backend = excluded.backend",
+                params![account.id, account.backend, account.created_at],
+            )?;
+            Ok(())
+        }).await
+    }
+
+    async fn get_accounts(&self) -> Result<Vec<Account>, Box<dyn Error + Send + Sync>> {
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, backend, created_at FROM accounts ORDER BY created_at ASC"
+            )?;
+
+            let account_iter = stmt.query_map([], |row| {
+                Ok(Account {
+                    id: row.get(0)?,
+                    backend: row.get(1)?,
+                    created_at: row.get(2)?,
+                })
+            })?;
+
+            let mut accounts = Vec::new();
+            for account in account_iter {
+                accounts.push(account?);
+            }
+
+            Ok(accounts)
+        }).await
+    }
+
+    async fn remove_account(&self, account_id: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let account_id = account_id.to_owned();
+        self.pool.write(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM accounts WHERE id = ?1", params![account_id])?;
+            // Whatever has not gone out yet must not be sent if the id is set up again
+            tx.execute(
+                "DELETE FROM outbox WHERE account_id = ?1 AND state != ?2",
+                params![account_id, OutboxState::Sent],
+            )?;
+            tx.execute(
+                "DELETE FROM scheduled_messages WHERE account_id = ?1 AND state = ?2",
+                params![account_id, ScheduleState::Scheduled],
+            )?;
+            tx.commit()?;
+            Ok(())
+        }).await
+    }
+
+    async fn save_auth_data(&self, key: &str, data: &Value) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let key = key.to_owned();
+        let json_str = serde_json::to_string(data)?;
+        self.pool.write(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO auth_store (key, data) VALUES (?1, ?2)",
+                params![key, json_str],
+            )?;
+            Ok(())
+        }).await
+    }
+
+    async fn get_auth_data(&self, key: &str) -> Result<Option<Value>, Box<dyn Error + Send + Sync>> {
+        let key = key.to_owned();
+        self.pool.read(move |conn| {
+            let mut stmt = conn.prepare("SELECT data FROM auth_store WHERE key = ?1")?;
+
+            let result: Option<String> = stmt.query_row(params![key], |row| row.get(0))
+                .optional()?;
+
+            match result {
+                Some(json_str) => {
+                    let val: Value = serde_json::from_str(&json_str)?;
+                    Ok(Some(val))
+                },
+                None => Ok(None),
+            }
+        }).await
     }
 
     async fn remove_auth_data(&self, key: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM auth_store WHERE key = ?1", params![key])
-            .map_err(|e| Box::new(e) as Box<dyn Error + Send + Sync>)?;
-        Ok(())
+        let key = key.to_owned();
+        self.pool.write(move |conn| {
+            conn.execute("DELETE FROM auth_store WHERE key = ?1", params![key])?;
+            Ok(())
+        }).await
     }
 }
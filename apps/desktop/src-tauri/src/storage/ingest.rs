@@ -0,0 +1,285 @@
+use super::{Chat, Contact, Message, MessageStatus, Reaction, SqliteStorage, Storage};
+use crate::backend::events::{
+    EditEvent, HistorySyncEvent, HistorySyncProgress, MessageEvent, MessageStatusEvent, ProviderEvent,
+    ReactionEvent, ReceiptEvent, RevokeEvent, emit_event,
+};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+use tauri::AppHandle;
+use tokio::sync::broadcast;
+
+/// Most events the persister takes off the channel at once
+const MAX_BATCH: usize = 256;
+
+/// Writes the events of a provider into storage so history survives restarts, and
+/// emits the events storage derives from them (e.g. status changes) to the frontend.
+/// Events that queued up meanwhile are handled together, so a burst of messages costs
+/// one transaction. The task ends once the provider drops its sender.
+pub fn spawn_event_persister(app_handle: AppHandle, account_id: String, storage: Arc<SqliteStorage>, mut rx: broadcast::Receiver<ProviderEvent>) {
+    tokio::spawn(async move {
+        loop {
+            let mut batch = match rx.recv().await {
+                Ok(event) => vec![event],
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    eprintln!("Event persister lagged, skipped {} events", skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            while batch.len() < MAX_BATCH {
+                match rx.try_recv() {
+                    Ok(event) => batch.push(event),
+                    Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                        eprintln!("Event persister lagged, skipped {} events", skipped);
+                    }
+                    // Empty, or closed: the next `recv` ends the loop
+                    Err(_) => break,
+                }
+            }
+
+            for event in persist_events(storage.as_ref(), &account_id, &batch).await {
+                emit_event(&app_handle, &account_id, &event);
+            }
+        }
+    });
+}
+
+/// Writes history sync batches into storage and reports each one to the frontend as a
+/// `HistorySyncProgress` event. Kept apart from the event persister so a large batch
+/// does not hold up live messages.
+pub fn spawn_history_ingester(app_handle: AppHandle, account_id: String, storage: Arc<SqliteStorage>, mut rx: broadcast::Receiver<ProviderEvent>) {
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(ProviderEvent::HistorySync(sync)) => {
+                    match ingest_history(storage.as_ref(), &account_id, &sync).await {
+                        Ok(progress) => {
+                            emit_event(&app_handle, &account_id, &ProviderEvent::HistorySyncProgress(progress));
+                        }
+                        Err(e) => eprintln!("Failed to ingest history sync: {}", e),
+                    }
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    eprintln!("History ingester lagged, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+pub async fn ingest_history(storage: &dyn Storage, account_id: &str, sync: &HistorySyncEvent) -> Result<HistorySyncProgress, Box<dyn Error + Send + Sync>> {
+    let contacts: Vec<Contact> = sync.contacts
+        .iter()
+        .map(|c| Contact {
+            jid: c.jid.clone(),
+            name: c.name.clone().filter(|n| !n.is_empty()),
+            notify_name: c.notify_name.clone().filter(|n| !n.is_empty()),
+        })
+        .collect();
+    let contact_names: HashMap<&str, &str> = contacts
+        .iter()
+        .filter_map(|c| c.name.as_deref().or(c.notify_name.as_deref()).map(|n| (c.jid.as_str(), n)))
+        .collect();
+
+    let mut latest: HashMap<&str, i64> = HashMap::new();
+    for msg in &sync.messages {
+        let ts = latest.entry(msg.chat_id.as_str()).or_default();
+        *ts = (*ts).max(msg.timestamp);
+    }
+
+    let mut chats = Vec::with_capacity(sync.chats.len());
+    for chat in &sync.chats {
+        let mut name = chat.name.clone()
+            .filter(|n| !n.is_empty())
+            .or_else(|| contact_names.get(chat.id.as_str()).map(|n| n.to_string()));
+        if name.is_none() {
+            name = storage.get_contact(account_id, &chat.id).await?
+                .and_then(|c| c.name.or(c.notify_name));
+        }
+
+        chats.push(Chat {
+            id: chat.id.clone(),
+            name: name.unwrap_or_else(|| chat.id.clone()),
+            unread_count: chat.unread_count,
+            last_message_timestamp: chat.last_message_timestamp
+                .or_else(|| latest.get(chat.id.as_str()).copied())
+                .unwrap_or_default(),
+        });
+    }
+
+    let progress = HistorySyncProgress {
+        chats: chats.len(),
+        contacts: contacts.len(),
+        messages: sync.messages.len(),
+        progress: sync.progress,
+        is_latest: sync.is_latest,
+    };
+    storage.save_history(account_id, chats, contacts, sync.messages.iter().map(stored_message).collect()).await?;
+    Ok(progress)
+}
+
+/// Persists events in order, writing runs of consecutive messages as one batch. Failures
+/// are logged and skipped. Returns the events derived from what was stored
+pub async fn persist_events(storage: &dyn Storage, account_id: &str, events: &[ProviderEvent]) -> Vec<ProviderEvent> {
+    let mut derived = Vec::new();
+    let mut messages: Vec<&MessageEvent> = Vec::new();
+
+    for event in events {
+        if let ProviderEvent::Message(msg) = event {
+            messages.push(msg);
+            continue;
+        }
+        if !messages.is_empty() {
+            if let Err(e) = persist_messages(storage, account_id, &messages).await {
+                eprintln!("Failed to persist {} messages: {}", messages.len(), e);
+            }
+            messages.clear();
+        }
+        match persist_event(storage, account_id, event).await {
+            Ok(events) => derived.extend(events),
+            Err(e) => eprintln!("Failed to persist event: {}", e),
+        }
+    }
+    if !messages.is_empty() {
+        if let Err(e) = persist_messages(storage, account_id, &messages).await {
+            eprintln!("Failed to persist {} messages: {}", messages.len(), e);
+        }
+    }
+
+    derived
+}
+
+/// Returns the events derived from what was stored, for the frontend
+pub async fn persist_event(storage: &dyn Storage, account_id: &str, event: &ProviderEvent) -> Result<Vec<ProviderEvent>, Box<dyn Error + Send + Sync>> {
+    match event {
+        ProviderEvent::Message(msg) => persist_messages(storage, account_id, &[msg]).await?,
+        ProviderEvent::Receipt(receipt) => return persist_receipt(storage, account_id, receipt).await,
+        ProviderEvent::MessageRevoked(revoke) => persist_revoke(storage, account_id, revoke).await?,
+        ProviderEvent::MessageEdited(edit) => persist_edit(storage, account_id, edit).await?,
+        ProviderEvent::Reaction(reaction) => persist_reaction(storage, account_id, reaction).await?,
+        _ => {}
+    }
+    Ok(Vec::new())
+}
+
+async fn persist_receipt(storage: &dyn Storage, account_id: &str, receipt: &ReceiptEvent) -> Result<Vec<ProviderEvent>, Box<dyn Error + Send + Sync>> {
+    let changed = storage.save_receipt(
+        account_id,
+        &receipt.chat_id,
+        &receipt.sender_id,
+        &receipt.message_ids,
+        receipt.kind.into(),
+        receipt.timestamp,
+    ).await?;
+
+    Ok(changed
+        .into_iter()
+        .map(|(message_id, status)| ProviderEvent::MessageStatusChanged(MessageStatusEvent {
+            chat_id: receipt.chat_id.clone(),
+            message_id,
+            status,
+        }))
+        .collect())
+}
+
+/// Anti-delete: the stored message keeps its content and is only flagged
+async fn persist_revoke(storage: &dyn Storage, account_id: &str, revoke: &RevokeEvent) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if !storage.mark_message_revoked(account_id, &revoke.message_id, &revoke.revoked_by, revoke.timestamp).await? {
+        eprintln!("Revoke for unknown message {} in {}", revoke.message_id, revoke.chat_id);
+    }
+    Ok(())
+}
+
+async fn persist_edit(storage: &dyn Storage, account_id: &str, edit: &EditEvent) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if !storage.save_message_edit(account_id, &edit.message_id, &edit.content.preview(), edit.timestamp).await? {
+        eprintln!("Edit for unknown message {} in {}", edit.message_id, edit.chat_id);
+    }
+    Ok(())
+}
+
+async fn persist_reaction(storage: &dyn Storage, account_id: &str, reaction: &ReactionEvent) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match &reaction.emoji {
+        Some(emoji) => storage.save_reaction(account_id, Reaction {
+            message_id: reaction.message_id.clone(),
+            chat_id: reaction.chat_id.clone(),
+            sender_id: reaction.sender_id.clone(),
+            emoji: emoji.clone(),
+            timestamp: reaction.timestamp,
+        }).await,
+        None => storage.remove_reaction(account_id, &reaction.message_id, &reaction.sender_id, reaction.timestamp).await,
+    }
+}
+
+fn stored_message(msg: &MessageEvent) -> Message {
+    Message {
+        id: msg.id.clone(),
+        chat_id: msg.chat_id.clone(),
+        content: msg.content.preview(),
+        sender_id: msg.sender_id.clone(),
+        timestamp: msg.timestamp,
+        from_me: msg.from_me,
+        revoked_at: None,
+        revoked_by: None,
+        edited_at: None,
+        quoted_id: msg.quoted.as_ref().map(|q| q.id.clone()),
+        status: msg.from_me.then_some(MessageStatus::Sent),
+    }
+}
+
+/// Stores messages together with the chats they update, in one write
+async fn persist_messages(storage: &dyn Storage, account_id: &str, messages: &[&MessageEvent]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let ids: Vec<String> = messages.iter().map(|msg| msg.id.clone()).collect();
+    // Grows with the batch too, so a message delivered twice in one burst counts once
+    let mut seen = storage.stored_message_ids(account_id, &ids).await?;
+
+    let mut chats: HashMap<String, Chat> = HashMap::new();
+    for msg in messages {
+        let existing = match chats.remove(&msg.chat_id) {
+            Some(chat) => Some(chat),
+            None => storage.get_chat(account_id, &msg.chat_id).await?,
+        };
+        let is_new = seen.insert(msg.id.clone());
+        chats.insert(msg.chat_id.clone(), updated_chat(existing, msg, is_new));
+    }
+
+    storage.save_messages(
+        account_id,
+        messages.iter().map(|msg| stored_message(msg)).collect(),
+        chats.into_values().collect(),
+    ).await
+}
+
+/// `is_new` is false for a message that was already stored, which must not count as unread again
+fn updated_chat(existing: Option<Chat>, msg: &MessageEvent, is_new: bool) -> Chat {
+    let is_group = msg.chat_id.ends_with("@g.us");
+
+    // Push names only identify 1:1 chats; group names come from group metadata
+    let name = match (&existing, &msg.sender_name) {
+        (_, Some(sender)) if !msg.from_me && !is_group => sender.clone(),
+        (Some(chat), _) => chat.name.clone(),
+        (None, _) => msg.chat_id.clone(),
+    };
+
+    // Replying from this device means the chat has been seen
+    let unread_count = match &existing {
+        _ if msg.from_me => 0,
+        Some(chat) if !is_new => chat.unread_count,
+        Some(chat) => chat.unread_count + 1,
+        None => 1,
+    };
+
+    let last_message_timestamp = existing
+        .as_ref()
+        .map(|c| c.last_message_timestamp.max(msg.timestamp))
+        .unwrap_or(msg.timestamp);
+
+    Chat {
+        id: msg.chat_id.clone(),
+        name,
+        unread_count,
+        last_message_timestamp,
+    }
+}
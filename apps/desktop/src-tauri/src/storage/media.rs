@@ -0,0 +1,113 @@
+use super::{MediaRecord, SqliteStorage, Storage};
+use crate::backend::accounts::scoped_file;
+use crate::utils::security::{PasswordChange, SecurityManager};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Extension of the copies written while the vault password changes
+const STAGED_EXTENSION: &str = "rekey";
+
+/// Local cache for received media.
+/// Files are re-encrypted at rest with the vault key and indexed in `SqliteStorage`.
+pub struct MediaCache {
+    dir: PathBuf,
+    security: Arc<SecurityManager>,
+    storage: Arc<SqliteStorage>,
+}
+
+impl MediaCache {
+    pub fn new(dir: PathBuf, security: Arc<SecurityManager>, storage: Arc<SqliteStorage>) -> Self {
+        Self { dir, security, storage }
+    }
+
+    /// Encrypts and stores decrypted media bytes for a message, then indexes them.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn store(
+        &self,
+        account_id: &str,
+        message_id: &str,
+        chat_id: &str,
+        mime_type: &str,
+        file_name: Option<String>,
+        timestamp: i64,
+        data: &[u8],
+    ) -> anyhow::Result<MediaRecord> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+
+        // Message ids are not guaranteed to be filesystem-safe
+        let path = self.dir.join(scoped_file(account_id, &hex::encode(message_id), "bin"));
+        let encrypted = self.security.encrypt_data(data)?;
+        tokio::fs::write(&path, encrypted).await?;
+
+        let record = MediaRecord {
+            message_id: message_id.to_string(),
+            chat_id: chat_id.to_string(),
+            mime_type: mime_type.to_string(),
+            file_name,
+            size: data.len() as i64,
+            path: path.to_string_lossy().to_string(),
+            timestamp,
+        };
+        self.storage.save_media(account_id, record.clone()).await
+            .map_err(|e| anyhow::anyhow!("Storage error: {}", e))?;
+
+        Ok(record)
+    }
+
+    /// Returns the index entry and decrypted bytes for a message, if cached.
+    pub async fn load(&self, account_id: &str, message_id: &str) -> anyhow::Result<Option<(MediaRecord, Vec<u8>)>> {
+        let record = match self.storage.get_media(account_id, message_id).await
+            .map_err(|e| anyhow::anyhow!("Storage error: {}", e))? {
+            Some(r) => r,
+            None => return Ok(None),
+        };
+
+        let encrypted = tokio::fs::read(&record.path).await?;
+        let data = self.security.decrypt_data(&encrypted)?;
+        Ok(Some((record, data)))
+    }
+
+    /// Writes a copy of every cached file under the key of a pending password change,
+    /// next to the original. Nothing is replaced until `commit_reencryption`
+    pub async fn stage_reencryption(&self, change: &PasswordChange) -> anyhow::Result<()> {
+        for path in self.cached_files("bin").await? {
+            let data = tokio::fs::read(&path).await?;
+            let staged = self.security.reencrypt_data(&data, change)?;
+            tokio::fs::write(path.with_extension(STAGED_EXTENSION), staged).await?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the cached files with their staged copies
+    pub async fn commit_reencryption(&self) -> anyhow::Result<()> {
+        for path in self.cached_files(STAGED_EXTENSION).await? {
+            tokio::fs::rename(&path, path.with_extension("bin")).await?;
+        }
+        Ok(())
+    }
+
+    /// Drops the staged copies of a password change that did not go through
+    pub async fn discard_reencryption(&self) {
+        for path in self.cached_files(STAGED_EXTENSION).await.unwrap_or_default() {
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                eprintln!("Failed to remove staged media file {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    async fn cached_files(&self, extension: &str) -> anyhow::Result<Vec<PathBuf>> {
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut files = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == extension) {
+                files.push(path);
+            }
+        }
+        Ok(files)
+    }
+}
@@ -0,0 +1,469 @@
+use rusqlite::{params, Connection};
+use std::error::Error;
+use std::fmt;
+
+/// One step of the schema. Steps run in order, each in its own transaction, and
+/// `PRAGMA user_version` records the last step a database has applied.
+///
+/// Databases created before versioning report version 0 but may already contain any
+/// part of the schema, so the early steps use `IF NOT EXISTS` and `add_column_if_missing`.
+/// New steps only run on databases that have never seen them and can be plain DDL.
+/// Never edit or reorder a released step; append a new one instead.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub up: fn(&Connection) -> rusqlite::Result<()>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "messages, chats and auth store", up: initial_schema },
+    Migration { version: 2, description: "revoked, edited, quoted messages and reactions", up: message_history },
+    Migration { version: 3, description: "media cache, contacts and groups", up: media_contacts_groups },
+    Migration { version: 4, description: "privacy rules", up: privacy_rules },
+    Migration { version: 5, description: "delivery status and receipts", up: receipts },
+    Migration { version: 6, description: "accounts, outbox and scheduled messages", up: accounts_outbox_schedule },
+    Migration { version: 7, description: "full-text index of message content", up: message_search },
+    Migration { version: 8, description: "index for paging through a chat", up: message_paging },
+    Migration { version: 9, description: "stable key for the message search index", up: message_search_key },
+    Migration { version: 10, description: "account scope for messages, chats, contacts and groups", up: account_scope },
+];
+
+/// Schema version this build writes
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The database was written by a newer WhaSwapp; opening it could lose data
+    TooNew { found: u32, supported: u32 },
+    /// A step failed and was rolled back; the database stays at the previous version
+    Step { version: u32, description: &'static str, source: rusqlite::Error },
+    Sqlite(rusqlite::Error),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::TooNew { found, supported } => write!(
+                f,
+                "Database schema version {} is newer than the supported version {}; update WhaSwapp to open it",
+                found, supported
+            ),
+            MigrationError::Step { version, description, source } => {
+                write!(f, "Migration {} ({}) failed: {}", version, description, source)
+            }
+            MigrationError::Sqlite(e) => write!(f, "Failed to migrate database: {}", e),
+        }
+    }
+}
+
+impl Error for MigrationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MigrationError::TooNew { .. } => None,
+            MigrationError::Step { source, .. } => Some(source),
+            MigrationError::Sqlite(e) => Some(e),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(e)
+    }
+}
+
+pub fn schema_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Brings the database up to `latest_version`, returning the version it started at
+pub fn migrate(conn: &mut Connection) -> Result<u32, MigrationError> {
+    run_migrations(conn, MIGRATIONS)
+}
+
+/// Applies the steps of `migrations` newer than the database, which must be sorted by version
+pub fn run_migrations(conn: &mut Connection, migrations: &[Migration]) -> Result<u32, MigrationError> {
+    let found = schema_version(conn)?;
+    let supported = migrations.last().map_or(0, |m| m.version);
+    if found > supported {
+        return Err(MigrationError::TooNew { found, supported });
+    }
+
+    for migration in migrations.iter().filter(|m| m.version > found) {
+        let tx = conn.transaction()?;
+        (migration.up)(&tx).map_err(|source| MigrationError::Step {
+            version: migration.version,
+            description: migration.description,
+            source,
+        })?;
+        // `user_version` lives in the database header, so it commits or rolls back with the step
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+
+    Ok(found)
+}
+
+/// Adds a column that a database from before versioning may already have
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> rusqlite::Result<()> {
+    let exists = conn
+        .prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table))?
+        .exists(params![column])?;
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(())
+}
+
+fn initial_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS messages (
+            id TEXT PRIMARY KEY,
+            chat_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            sender_id TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            from_me BOOLEAN NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS chats (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            unread_count INTEGER NOT NULL,
+            last_message_timestamp INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS auth_store (
+            key TEXT PRIMARY KEY,
+            data TEXT NOT NULL
+        );",
+    )
+}
+
+fn message_history(conn: &Connection) -> rusqlite::Result<()> {
+    add_column_if_missing(conn, "messages", "revoked_at", "INTEGER")?;
+    add_column_if_missing(conn, "messages", "revoked_by", "TEXT")?;
+    add_column_if_missing(conn, "messages", "edited_at", "INTEGER")?;
+    add_column_if_missing(conn, "messages", "quoted_id", "TEXT")?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS message_revisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            message_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            timestamp INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_message_revisions_message ON message_revisions (message_id);
+        CREATE TABLE IF NOT EXISTS reactions (
+            message_id TEXT NOT NULL,
+            chat_id TEXT NOT NULL,
+            sender_id TEXT NOT NULL,
+            emoji TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            PRIMARY KEY (message_id, sender_id)
+        );",
+    )
+}
+
+fn media_contacts_groups(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS media (
+            message_id TEXT PRIMARY KEY,
+            chat_id TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            file_name TEXT,
+            size INTEGER NOT NULL,
+            path TEXT NOT NULL,
+            timestamp INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS contacts (
+            jid TEXT PRIMARY KEY,
+            name TEXT,
+            notify_name TEXT
+        );
+        CREATE TABLE IF NOT EXISTS groups (
+            id TEXT PRIMARY KEY,
+            subject TEXT NOT NULL,
+            description TEXT,
+            owner TEXT,
+            created_at INTEGER
+        );
+        CREATE TABLE IF NOT EXISTS group_participants (
+            group_id TEXT NOT NULL,
+            jid TEXT NOT NULL,
+            role TEXT NOT NULL,
+            PRIMARY KEY (group_id, jid)
+        );",
+    )
+}
+
+fn privacy_rules(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS privacy_rules (
+            scope TEXT PRIMARY KEY,
+            read_receipts BOOLEAN,
+            chat_state BOOLEAN,
+            presence BOOLEAN
+        );",
+    )
+}
+
+fn receipts(conn: &Connection) -> rusqlite::Result<()> {
+    add_column_if_missing(conn, "messages", "status", "INTEGER")?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS message_receipts (
+            message_id TEXT NOT NULL,
+            participant TEXT NOT NULL,
+            delivered_at INTEGER,
+            read_at INTEGER,
+            played_at INTEGER,
+            PRIMARY KEY (message_id, participant)
+        );",
+    )
+}
+
+fn accounts_outbox_schedule(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS accounts (
+            id TEXT PRIMARY KEY,
+            backend TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS outbox (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id TEXT NOT NULL,
+            chat_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            quoted_id TEXT,
+            media_path TEXT,
+            mime_type TEXT,
+            state TEXT NOT NULL,
+            attempts INTEGER NOT NULL,
+            next_attempt_at INTEGER NOT NULL,
+            last_error TEXT,
+            message_id TEXT,
+            created_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_outbox_account_state ON outbox (account_id, state);
+        CREATE TABLE IF NOT EXISTS scheduled_messages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            account_id TEXT NOT NULL,
+            chat_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            send_at INTEGER NOT NULL,
+            if_missed TEXT NOT NULL,
+            state TEXT NOT NULL,
+            outbox_id INTEGER,
+            created_at INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_scheduled_messages_due ON scheduled_messages (account_id, state, send_at);",
+    )
+}
+
+/// External-content FTS5 index: `messages` holds the text, the index only its tokens,
+/// keyed by the message rowid. Triggers keep it in sync; edits replace the indexed text.
+/// `VACUUM` may renumber rowids of `messages`, so it must be followed by a `'rebuild'`
+fn message_search(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE messages_fts USING fts5(
+            content,
+            content = 'messages',
+            content_rowid = 'rowid',
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+        CREATE TRIGGER messages_fts_insert AFTER INSERT ON messages BEGIN
+            INSERT INTO messages_fts (rowid, content) VALUES (new.rowid, new.content);
+        END;
+        CREATE TRIGGER messages_fts_delete AFTER DELETE ON messages BEGIN
+            INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+        END;
+        CREATE TRIGGER messages_fts_update AFTER UPDATE OF content ON messages BEGIN
+            INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+            INSERT INTO messages_fts (rowid, content) VALUES (new.rowid, new.content);
+        END;
+        INSERT INTO messages_fts (messages_fts) VALUES ('rebuild');",
+    )
+}
+
+/// Serves keyset pages: `WHERE chat_id = ? AND (timestamp, id) < (?, ?) ORDER BY timestamp, id`
+fn message_paging(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch("CREATE INDEX idx_messages_chat_timestamp ON messages (chat_id, timestamp, id);")
+}
+
+/// The index of v7 is keyed on the implicit rowid of `messages`, which `VACUUM` may renumber
+/// because the table's primary key is text. Rebuilds `messages` around `seq`, an INTEGER
+/// PRIMARY KEY and so a rowid alias that never changes, and keys the index on that instead
+fn message_search_key(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "DROP TABLE messages_fts;
+        CREATE TABLE messages_new (
+            seq INTEGER PRIMARY KEY,
+            id TEXT NOT NULL UNIQUE,
+            chat_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            sender_id TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            from_me BOOLEAN NOT NULL,
+            revoked_at INTEGER,
+            revoked_by TEXT,
+            edited_at INTEGER,
+            quoted_id TEXT,
+            status INTEGER
+        );
+        INSERT INTO messages_new (id, chat_id, content, sender_id, timestamp, from_me, revoked_at, revoked_by, edited_at, quoted_id, status)
+            SELECT id, chat_id, content, sender_id, timestamp, from_me, revoked_at, revoked_by, edited_at, quoted_id, status
+            FROM messages ORDER BY rowid;
+        DROP TABLE messages;
+        ALTER TABLE messages_new RENAME TO messages;
+        CREATE INDEX idx_messages_chat_timestamp ON messages (chat_id, timestamp, id);
+        CREATE VIRTUAL TABLE messages_fts USING fts5(
+            content,
+            content = 'messages',
+            content_rowid = 'seq',
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+        CREATE TRIGGER messages_fts_insert AFTER INSERT ON messages BEGIN
+            INSERT INTO messages_fts (rowid, content) VALUES (new.seq, new.content);
+        END;
+        CREATE TRIGGER messages_fts_delete AFTER DELETE ON messages BEGIN
+            INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.seq, old.content);
+        END;
+        CREATE TRIGGER messages_fts_update AFTER UPDATE OF content ON messages BEGIN
+            INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.seq, old.content);
+            INSERT INTO messages_fts (rowid, content) VALUES (new.seq, new.content);
+        END;
+        INSERT INTO messages_fts (messages_fts) VALUES ('rebuild');",
+    )
+}
+
+/// Adds `account_id` to the keys of everything a session stores, so two accounts in the same
+/// chat or group no longer overwrite each other's rows. Rows stored so far cannot be told
+/// apart and go to the default account. `messages` keeps its `seq` values, so the search
+/// index stays valid; its triggers are dropped with the old table and created again
+fn account_scope(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE messages_new (
+            seq INTEGER PRIMARY KEY,
+            account_id TEXT NOT NULL,
+            id TEXT NOT NULL,
+            chat_id TEXT NOT NULL,
+            content TEXT NOT NULL,
+            sender_id TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            from_me BOOLEAN NOT NULL,
+            revoked_at INTEGER,
+            revoked_by TEXT,
+            edited_at INTEGER,
+            quoted_id TEXT,
+            status INTEGER,
+            UNIQUE (account_id, id)
+        );
+        INSERT INTO messages_new (seq, account_id, id, chat_id, content, sender_id, timestamp, from_me, revoked_at, revoked_by, edited_at, quoted_id, status)
+            SELECT seq, 'default', id, chat_id, content, sender_id, timestamp, from_me, revoked_at, revoked_by, edited_at, quoted_id, status
+            FROM messages;
+        DROP TABLE messages;
+        ALTER TABLE messages_new RENAME TO messages;
+        CREATE INDEX idx_messages_chat_timestamp ON messages (account_id, chat_id, timestamp, id);
+        CREATE TRIGGER messages_fts_insert AFTER INSERT ON messages BEGIN
+            INSERT INTO messages_fts (rowid, content) VALUES (new.seq, new.content);
+        END;
+        CREATE TRIGGER messages_fts_delete AFTER DELETE ON messages BEGIN
+            INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.seq, old.content);
+        END;
+        CREATE TRIGGER messages_fts_update AFTER UPDATE OF content ON messages BEGIN
+            INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.seq, old.content);
+            INSERT INTO messages_fts (rowid, content) VALUES (new.seq, new.content);
+        END;
+
+        ALTER TABLE message_revisions ADD COLUMN account_id TEXT NOT NULL DEFAULT 'default';
+        DROP INDEX idx_message_revisions_message;
+        CREATE INDEX idx_message_revisions_message ON message_revisions (account_id, message_id);
+
+        CREATE TABLE chats_new (
+            account_id TEXT NOT NULL,
+            id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            unread_count INTEGER NOT NULL,
+            last_message_timestamp INTEGER NOT NULL,
+            PRIMARY KEY (account_id, id)
+        );
+        INSERT INTO chats_new SELECT 'default', id, name, unread_count, last_message_timestamp FROM chats;
+        DROP TABLE chats;
+        ALTER TABLE chats_new RENAME TO chats;
+
+        CREATE TABLE contacts_new (
+            account_id TEXT NOT NULL,
+            jid TEXT NOT NULL,
+            name TEXT,
+            notify_name TEXT,
+            PRIMARY KEY (account_id, jid)
+        );
+        INSERT INTO contacts_new SELECT 'default', jid, name, notify_name FROM contacts;
+        DROP TABLE contacts;
+        ALTER TABLE contacts_new RENAME TO contacts;
+
+        CREATE TABLE groups_new (
+            account_id TEXT NOT NULL,
+            id TEXT NOT NULL,
+            subject TEXT NOT NULL,
+            description TEXT,
+            owner TEXT,
+            created_at INTEGER,
+            PRIMARY KEY (account_id, id)
+        );
+        INSERT INTO groups_new SELECT 'default', id, subject, description, owner, created_at FROM groups;
+        DROP TABLE groups;
+        ALTER TABLE groups_new RENAME TO groups;
+
+        CREATE TABLE group_participants_new (
+            account_id TEXT NOT NULL,
+            group_id TEXT NOT NULL,
+            jid TEXT NOT NULL,
+            role TEXT NOT NULL,
+            PRIMARY KEY (account_id, group_id, jid)
+        );
+        INSERT INTO group_participants_new SELECT 'default', group_id, jid, role FROM group_participants;
+        DROP TABLE group_participants;
+        ALTER TABLE group_participants_new RENAME TO group_participants;
+
+        CREATE TABLE reactions_new (
+            account_id TEXT NOT NULL,
+            message_id TEXT NOT NULL,
+            chat_id TEXT NOT NULL,
+            sender_id TEXT NOT NULL,
+            emoji TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            PRIMARY KEY (account_id, message_id, sender_id)
+        );
+        INSERT INTO reactions_new SELECT 'default', message_id, chat_id, sender_id, emoji, timestamp FROM reactions;
+        DROP TABLE reactions;
+        ALTER TABLE reactions_new RENAME TO reactions;
+
+        CREATE TABLE message_receipts_new (
+            account_id TEXT NOT NULL,
+            message_id TEXT NOT NULL,
+            participant TEXT NOT NULL,
+            delivered_at INTEGER,
+            read_at INTEGER,
+            played_at INTEGER,
+            PRIMARY KEY (account_id, message_id, participant)
+        );
+        INSERT INTO message_receipts_new SELECT 'default', message_id, participant, delivered_at, read_at, played_at FROM message_receipts;
+        DROP TABLE message_receipts;
+        ALTER TABLE message_receipts_new RENAME TO message_receipts;
+
+        CREATE TABLE media_new (
+            account_id TEXT NOT NULL,
+            message_id TEXT NOT NULL,
+            chat_id TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            file_name TEXT,
+            size INTEGER NOT NULL,
+            path TEXT NOT NULL,
+            timestamp INTEGER NOT NULL,
+            PRIMARY KEY (account_id, message_id)
+        );
+        INSERT INTO media_new SELECT 'default', message_id, chat_id, mime_type, file_name, size, path, timestamp FROM media;
+        DROP TABLE media;
+        ALTER TABLE media_new RENAME TO media;",
+    )
+}
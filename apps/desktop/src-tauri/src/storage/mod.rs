@@ -1,9 +1,9 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::error::Error;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[allow(dead_code)]
 pub struct Message {
     pub id: String,
     pub chat_id: String,
@@ -11,10 +11,88 @@ pub struct Message {
     pub sender_id: String,
     pub timestamp: i64,
     pub from_me: bool,
+    /// Set when the message was deleted for everyone; the content is kept
+    pub revoked_at: Option<i64>,
+    pub revoked_by: Option<String>,
+    /// Time of the latest edit; earlier contents are kept as `MessageRevision`s
+    pub edited_at: Option<i64>,
+    /// Id of the message this one replies to
+    pub quoted_id: Option<String>,
+    /// Delivery progress; only tracked for our own messages
+    pub status: Option<MessageStatus>,
+}
+
+/// Position of a message in its chat's history. Messages are ordered by timestamp, with
+/// the id breaking ties, so a cursor stays valid while new messages arrive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageCursor {
+    pub timestamp: i64,
+    pub id: String,
+}
+
+impl MessageCursor {
+    pub fn of(message: &Message) -> Self {
+        Self {
+            timestamp: message.timestamp,
+            id: message.id.clone(),
+        }
+    }
+}
+
+/// Which part of a chat's history to load
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MessageRange {
+    /// The most recent messages
+    Latest,
+    /// Messages older than the cursor, e.g. when scrolling up
+    Before { cursor: MessageCursor },
+    /// Messages newer than the cursor, e.g. when scrolling back down after a jump
+    After { cursor: MessageCursor },
+    /// A message with the messages on both sides of it, e.g. to jump to a search result
+    Around { message_id: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessagePage {
+    /// Oldest first
+    pub messages: Vec<Message>,
+    /// Whether older messages exist before the first one
+    pub has_before: bool,
+    /// Whether newer messages exist after the last one
+    pub has_after: bool,
+}
+
+/// Delivery progress of a message we sent. It only ever moves forward
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageStatus {
+    Sent,
+    Delivered,
+    Read,
+    /// A voice note or video was played
+    Played,
+}
+
+/// Receipts of one recipient for one of our messages, for the "message info" view
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageReceipt {
+    pub message_id: String,
+    pub participant: String,
+    pub delivered_at: Option<i64>,
+    pub read_at: Option<i64>,
+    pub played_at: Option<i64>,
+}
+
+/// One version of an edited message. The first revision is the original content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageRevision {
+    pub message_id: String,
+    pub content: String,
+    pub timestamp: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[allow(dead_code)]
 pub struct Chat {
     pub id: String,
     pub name: String,
@@ -22,25 +100,331 @@ pub struct Chat {
     pub last_message_timestamp: i64,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Contact {
+    pub jid: String,
+    /// Name from the phone's address book
+    pub name: Option<String>,
+    /// Name the contact chose for themselves
+    pub notify_name: Option<String>,
+}
+
+/// A group we are (or were) a member of
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Group {
+    pub id: String,
+    pub subject: String,
+    pub description: Option<String>,
+    pub owner: Option<String>,
+    pub created_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupParticipant {
+    pub group_id: String,
+    pub jid: String,
+    /// `member`, `admin` or `super_admin`
+    pub role: String,
+}
+
+/// A participant's current reaction to a message; each has at most one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reaction {
+    pub message_id: String,
+    pub chat_id: String,
+    pub sender_id: String,
+    pub emoji: String,
+    pub timestamp: i64,
+}
+
+/// Index entry for a media file held encrypted in the `MediaCache`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaRecord {
+    pub message_id: String,
+    pub chat_id: String,
+    pub mime_type: String,
+    pub file_name: Option<String>,
+    pub size: i64,
+    /// Path of the encrypted file inside the media cache
+    pub path: String,
+    pub timestamp: i64,
+}
+
+/// Marks the start of a matched term in `SearchHit::snippet`
+pub const SNIPPET_START: char = '\u{2}';
+/// Marks the end of a matched term in `SearchHit::snippet`
+pub const SNIPPET_END: char = '\u{3}';
+
+/// Narrows a message search; unset fields match everything
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchFilters {
+    pub chat_id: Option<String>,
+    pub sender_id: Option<String>,
+    /// Unix time, inclusive
+    pub after: Option<i64>,
+    /// Unix time, exclusive
+    pub before: Option<i64>,
+    pub from_me: Option<bool>,
+    /// Defaults to `DEFAULT_SEARCH_LIMIT`
+    pub limit: Option<usize>,
+}
+
+pub const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub message: Message,
+    /// Excerpt around the matches, each wrapped in `SNIPPET_START` and `SNIPPET_END`.
+    /// The content is not escaped, so render it as text
+    pub snippet: String,
+}
+
+/// An outgoing message waiting in the outbox until its account can deliver it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    /// Assigned by storage when queued
+    pub id: i64,
+    pub account_id: String,
+    pub chat_id: String,
+    /// Text, or the caption of a media message
+    pub content: String,
+    pub quoted_id: Option<String>,
+    /// Local file to send as media instead of text
+    pub media_path: Option<String>,
+    pub mime_type: Option<String>,
+    pub state: OutboxState,
+    pub attempts: u32,
+    /// Unix time before which the entry is not retried
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+    /// Id WhatsApp assigned once sent
+    pub message_id: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutboxState {
+    Pending,
+    /// Handed to the provider, waiting for the result
+    Sending,
+    Sent,
+    /// Out of retries; only sent again on request
+    Failed,
+}
+
+impl OutboxState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutboxState::Pending => "pending",
+            OutboxState::Sending => "sending",
+            OutboxState::Sent => "sent",
+            OutboxState::Failed => "failed",
+        }
+    }
+}
+
+/// A message composed ahead of time and sent at `send_at`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledMessage {
+    /// Assigned by storage when scheduled
+    pub id: i64,
+    pub account_id: String,
+    pub chat_id: String,
+    pub content: String,
+    /// Unix time to send at
+    pub send_at: i64,
+    /// What to do if the app was not running at `send_at`
+    pub if_missed: MissedPolicy,
+    pub state: ScheduleState,
+    /// Outbox entry the message was handed to once due
+    pub outbox_id: Option<i64>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedPolicy {
+    /// Send as soon as the app is running again
+    #[default]
+    SendLate,
+    /// Drop the message and mark it `Skipped`
+    Skip,
+}
+
+impl MissedPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MissedPolicy::SendLate => "send_late",
+            MissedPolicy::Skip => "skip",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleState {
+    /// Waiting for `send_at`; the only state in which it can be edited or cancelled
+    Scheduled,
+    /// Moved to the outbox, which takes care of delivery
+    Queued,
+    /// Missed with `MissedPolicy::Skip`
+    Skipped,
+}
+
+impl ScheduleState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduleState::Scheduled => "scheduled",
+            ScheduleState::Queued => "queued",
+            ScheduleState::Skipped => "skipped",
+        }
+    }
+}
+
+/// A WhatsApp number connected to this instance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    pub id: String,
+    /// `rust`, `baileys` or `wwebjs`
+    pub backend: String,
+    pub created_at: i64,
+}
+
+/// Ghost mode rule for one scope: `GLOBAL_PRIVACY_SCOPE` or a chat jid.
+/// `None` fields inherit from the global rule (and default to allowed).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrivacyRule {
+    pub scope: String,
+    pub read_receipts: Option<bool>,
+    pub chat_state: Option<bool>,
+    /// Only meaningful on the global rule; online status is not per chat
+    pub presence: Option<bool>,
+}
+
+pub const GLOBAL_PRIVACY_SCOPE: &str = "*";
+
+/// Everything a session stores is kept per account: methods taking an `account_id` only see
+/// and change that account's rows, even where two accounts share a chat or group
 #[async_trait]
 pub trait Storage: Send + Sync {
-    #[allow(dead_code)]
-    async fn save_message(&self, message: Message) -> Result<(), Box<dyn Error + Send + Sync>>;
-    #[allow(dead_code)]
-    async fn get_messages(&self, chat_id: &str, limit: usize, offset: usize) -> Result<Vec<Message>, Box<dyn Error + Send + Sync>>;
+    async fn save_message(&self, account_id: &str, message: Message) -> Result<(), Box<dyn Error + Send + Sync>>;
+    /// Writes a burst of live messages and the chats they update in a single transaction
+    async fn save_messages(&self, account_id: &str, messages: Vec<Message>, chats: Vec<Chat>) -> Result<(), Box<dyn Error + Send + Sync>>;
+    /// Newest first, skipping `offset` messages. Slows down with the offset; prefer `get_message_page`
+    async fn get_messages(&self, account_id: &str, chat_id: &str, limit: usize, offset: usize) -> Result<Vec<Message>, Box<dyn Error + Send + Sync>>;
+    /// Keyset-paginated history of a chat. Unlike `get_messages` its cost does not grow with
+    /// how far back the page is. An unknown message in `Around` yields an empty page
+    async fn get_message_page(&self, account_id: &str, chat_id: &str, range: &MessageRange, limit: usize) -> Result<MessagePage, Box<dyn Error + Send + Sync>>;
+    async fn get_message(&self, account_id: &str, message_id: &str) -> Result<Option<Message>, Box<dyn Error + Send + Sync>>;
+    /// Which of the given messages are already stored, e.g. to tell re-deliveries apart
+    async fn stored_message_ids(&self, account_id: &str, message_ids: &[String]) -> Result<HashSet<String>, Box<dyn Error + Send + Sync>>;
+    /// Full-text search over message content, newest first. Every word of `query` has to
+    /// match, the last one as a prefix; FTS operators in it are treated as plain text
+    async fn search_messages(&self, account_id: &str, query: &str, filters: &SearchFilters) -> Result<Vec<SearchHit>, Box<dyn Error + Send + Sync>>;
+    /// Records a new revision and makes it the visible content. Returns false if the message is not stored
+    async fn save_message_edit(&self, account_id: &str, message_id: &str, content: &str, edited_at: i64) -> Result<bool, Box<dyn Error + Send + Sync>>;
+    /// Every revision of a message, oldest first; empty if it was never edited
+    async fn get_message_revisions(&self, account_id: &str, message_id: &str) -> Result<Vec<MessageRevision>, Box<dyn Error + Send + Sync>>;
+    /// Flags a stored message as deleted for everyone. Returns false if it is not stored
+    async fn mark_message_revoked(&self, account_id: &str, message_id: &str, revoked_by: &str, revoked_at: i64) -> Result<bool, Box<dyn Error + Send + Sync>>;
+
+    /// Records a recipient's receipt for our messages and advances their status. In groups a
+    /// message only counts as delivered/read once every other participant got there.
+    /// Returns the messages whose status changed, with their new status
+    async fn save_receipt(&self, account_id: &str, chat_id: &str, participant: &str, message_ids: &[String], status: MessageStatus, timestamp: i64) -> Result<Vec<(String, MessageStatus)>, Box<dyn Error + Send + Sync>>;
+    async fn get_message_receipts(&self, account_id: &str, message_id: &str) -> Result<Vec<MessageReceipt>, Box<dyn Error + Send + Sync>>;
+
+    /// Sets a participant's reaction, unless a newer one is already stored
+    async fn save_reaction(&self, account_id: &str, reaction: Reaction) -> Result<(), Box<dyn Error + Send + Sync>>;
+    /// Removes a participant's reaction, unless it was set after `timestamp`
+    async fn remove_reaction(&self, account_id: &str, message_id: &str, sender_id: &str, timestamp: i64) -> Result<(), Box<dyn Error + Send + Sync>>;
+    async fn get_reactions(&self, account_id: &str, message_id: &str) -> Result<Vec<Reaction>, Box<dyn Error + Send + Sync>>;
+
+    /// Writes one history sync batch in a single transaction. Messages already stored are kept
+    /// as they are, and a chat only gets a new name if the batch knows a real one
+    async fn save_history(&self, account_id: &str, chats: Vec<Chat>, contacts: Vec<Contact>, messages: Vec<Message>) -> Result<(), Box<dyn Error + Send + Sync>>;
+    async fn get_contact(&self, account_id: &str, jid: &str) -> Result<Option<Contact>, Box<dyn Error + Send + Sync>>;
+    async fn get_contacts(&self, account_id: &str) -> Result<Vec<Contact>, Box<dyn Error + Send + Sync>>;
+
+    async fn save_chat(&self, account_id: &str, chat: Chat) -> Result<(), Box<dyn Error + Send + Sync>>;
+    async fn get_chat(&self, account_id: &str, chat_id: &str) -> Result<Option<Chat>, Box<dyn Error + Send + Sync>>;
+    async fn get_chats(&self, account_id: &str) -> Result<Vec<Chat>, Box<dyn Error + Send + Sync>>;
+    /// Resets a chat's unread count once the user has read it
+    async fn mark_chat_read(&self, account_id: &str, chat_id: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Stores a group and replaces its participant list
+    async fn save_group(&self, account_id: &str, group: Group, participants: Vec<GroupParticipant>) -> Result<(), Box<dyn Error + Send + Sync>>;
+    async fn get_groups(&self, account_id: &str) -> Result<Vec<Group>, Box<dyn Error + Send + Sync>>;
+    async fn get_group_participants(&self, account_id: &str, group_id: &str) -> Result<Vec<GroupParticipant>, Box<dyn Error + Send + Sync>>;
+    /// Forgets a group and its participants, e.g. after leaving it
+    async fn remove_group(&self, account_id: &str, group_id: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    async fn save_media(&self, account_id: &str, media: MediaRecord) -> Result<(), Box<dyn Error + Send + Sync>>;
+    async fn get_media(&self, account_id: &str, message_id: &str) -> Result<Option<MediaRecord>, Box<dyn Error + Send + Sync>>;
+
+    async fn save_privacy_rule(&self, rule: PrivacyRule) -> Result<(), Box<dyn Error + Send + Sync>>;
+    async fn get_privacy_rules(&self) -> Result<Vec<PrivacyRule>, Box<dyn Error + Send + Sync>>;
+    async fn remove_privacy_rule(&self, scope: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Queues an outgoing message as `Pending`, returning it with its id
+    async fn enqueue_outbox(&self, entry: OutboxEntry) -> Result<OutboxEntry, Box<dyn Error + Send + Sync>>;
+    /// An account's messages that are not sent yet, oldest first
+    async fn get_outbox(&self, account_id: &str) -> Result<Vec<OutboxEntry>, Box<dyn Error + Send + Sync>>;
+    /// Marks the oldest due `Pending` entry as `Sending` and returns it. Entries of a chat are
+    /// claimed in order, so a message never overtakes an earlier one that is still queued
+    async fn claim_outbox(&self, account_id: &str, now: i64) -> Result<Option<OutboxEntry>, Box<dyn Error + Send + Sync>>;
+    /// Earliest time a `Pending` entry of the account becomes due
+    async fn next_outbox_attempt(&self, account_id: &str) -> Result<Option<i64>, Box<dyn Error + Send + Sync>>;
+    async fn finish_outbox(&self, id: i64, message_id: &str) -> Result<Option<OutboxEntry>, Box<dyn Error + Send + Sync>>;
+    /// Records a failed attempt: back to `Pending` until `retry_at`, or `Failed` without one
+    async fn fail_outbox(&self, id: i64, error: &str, retry_at: Option<i64>) -> Result<Option<OutboxEntry>, Box<dyn Error + Send + Sync>>;
+    /// Makes a `Failed` or `Pending` entry due now, with a fresh set of attempts
+    async fn retry_outbox(&self, account_id: &str, id: i64, now: i64) -> Result<Option<OutboxEntry>, Box<dyn Error + Send + Sync>>;
+    /// Drops an entry of the account that is not being sent. Returns false if there was none to drop
+    async fn cancel_outbox(&self, account_id: &str, id: i64) -> Result<bool, Box<dyn Error + Send + Sync>>;
+    /// Puts entries left `Sending` by a crash back to `Pending`
+    async fn requeue_outbox(&self, account_id: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Stores a new `Scheduled` message, returning it with its id
+    async fn save_schedule(&self, schedule: ScheduledMessage) -> Result<ScheduledMessage, Box<dyn Error + Send + Sync>>;
+    /// An account's scheduled messages, including queued and skipped ones, by send time
+    async fn get_schedules(&self, account_id: &str) -> Result<Vec<ScheduledMessage>, Box<dyn Error + Send + Sync>>;
+    /// Changes a message of the account that is still `Scheduled`. Returns `None` if there is
+    /// no such message, e.g. because it already went out
+    async fn update_schedule(&self, account_id: &str, id: i64, content: &str, send_at: i64, if_missed: MissedPolicy) -> Result<Option<ScheduledMessage>, Box<dyn Error + Send + Sync>>;
+    /// Deletes a message of the account that is still `Scheduled`. Returns false if there was none to delete
+    async fn cancel_schedule(&self, account_id: &str, id: i64) -> Result<bool, Box<dyn Error + Send + Sync>>;
+    /// `Scheduled` messages of the account whose send time has come, oldest first
+    async fn due_schedules(&self, account_id: &str, now: i64) -> Result<Vec<ScheduledMessage>, Box<dyn Error + Send + Sync>>;
+    /// Earliest send time among the account's `Scheduled` messages
+    async fn next_schedule_at(&self, account_id: &str) -> Result<Option<i64>, Box<dyn Error + Send + Sync>>;
+    /// Moves a due message into the outbox in one transaction, so it is neither lost nor sent
+    /// twice. Returns `None` if it was edited, cancelled or already queued meanwhile
+    async fn queue_schedule(&self, id: i64, now: i64) -> Result<Option<(ScheduledMessage, OutboxEntry)>, Box<dyn Error + Send + Sync>>;
+    /// Marks a due message `Skipped`. Returns `None` if it is no longer `Scheduled`
+    async fn skip_schedule(&self, id: i64, now: i64) -> Result<Option<ScheduledMessage>, Box<dyn Error + Send + Sync>>;
 
-    #[allow(dead_code)]
-    async fn save_chat(&self, chat: Chat) -> Result<(), Box<dyn Error + Send + Sync>>;
-    #[allow(dead_code)]
-    async fn get_chats(&self) -> Result<Vec<Chat>, Box<dyn Error + Send + Sync>>;
+    /// Registers an account or changes its backend
+    async fn save_account(&self, account: Account) -> Result<(), Box<dyn Error + Send + Sync>>;
+    async fn get_accounts(&self) -> Result<Vec<Account>, Box<dyn Error + Send + Sync>>;
+    /// Forgets an account along with the messages it has queued or scheduled but not sent
+    async fn remove_account(&self, account_id: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
 
     // Auth Data Management (for Baileys/Rust adapters)
+    // Keys of accounts other than the default one are prefixed with the account id
     // Key is usually "auth_info" or specific keys like "creds"
     async fn save_auth_data(&self, key: &str, data: &serde_json::Value) -> Result<(), Box<dyn Error + Send + Sync>>;
     async fn get_auth_data(&self, key: &str) -> Result<Option<serde_json::Value>, Box<dyn Error + Send + Sync>>;
-    #[allow(dead_code)]
     async fn remove_auth_data(&self, key: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
 }
 
+pub mod cipher;
 pub mod db;
+pub mod ingest;
+pub mod media;
+pub mod migrations;
+pub mod pool;
 pub use db::SqliteStorage;
+pub use media::MediaCache;
@@ -0,0 +1,135 @@
+use super::cipher::{self, KeyError};
+use rusqlite::Connection;
+use std::error::Error;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Read connections opened by default. Readers only contend with each other for the
+/// `Vec`, so a handful is enough for the UI, the persisters and the workers
+pub const DEFAULT_READERS: usize = 4;
+
+/// How long a statement waits for a lock held by another connection before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connections to one database file: a single writer and a few read-only readers.
+/// The database runs in WAL mode, so readers see the last committed state and are never
+/// blocked by the writer. Queries run on tokio's blocking threads, never on a worker.
+pub struct ConnectionPool {
+    path: String,
+    key: Arc<Mutex<Option<Vec<u8>>>>,
+    writer: Arc<Mutex<Connection>>,
+    readers: Arc<Mutex<Vec<Connection>>>,
+    /// Number of readers; without any, reads go through the writer
+    reader_slots: usize,
+    /// One permit per reader, so a reader is free whenever a permit is held
+    available: Arc<Semaphore>,
+}
+
+impl ConnectionPool {
+    /// Wraps an open, migrated writer and opens `readers` read connections next to it.
+    /// In-memory databases cannot be shared, so they get no readers and read through the writer
+    pub fn new(writer: Connection, path: &str, key: Option<&[u8]>, readers: usize) -> Result<Self, Box<dyn Error>> {
+        writer.busy_timeout(BUSY_TIMEOUT)?;
+        let readers = if path == ":memory:" { 0 } else { readers };
+        if readers > 0 {
+            writer.pragma_update(None, "journal_mode", "WAL")?;
+            // Safe in WAL mode: a crash can only lose the last commits, not corrupt the file
+            writer.pragma_update(None, "synchronous", "NORMAL")?;
+        }
+
+        let connections = (0..readers)
+            .map(|_| open_reader(path, key))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            path: path.to_string(),
+            key: Arc::new(Mutex::new(key.map(<[u8]>::to_vec))),
+            writer: Arc::new(Mutex::new(writer)),
+            readers: Arc::new(Mutex::new(connections)),
+            reader_slots: readers,
+            available: Arc::new(Semaphore::new(readers)),
+        })
+    }
+
+    /// Runs `f` on a read connection, waiting for one to be free
+    pub async fn read<T, F>(&self, f: F) -> Result<T, Box<dyn Error + Send + Sync>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, Box<dyn Error + Send + Sync>> + Send + 'static,
+    {
+        if self.reader_slots == 0 {
+            return self.write(move |conn| f(conn)).await;
+        }
+
+        let permit = self.available.clone().acquire_owned().await?;
+        let readers = self.readers.clone();
+        let path = self.path.clone();
+        let key = self.key.clone();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            // A reader is only missing if a query panicked while holding it
+            let reader = readers.lock().unwrap_or_else(PoisonError::into_inner).pop();
+            let conn = match reader {
+                Some(conn) => conn,
+                None => open_reader(&path, key.lock().unwrap_or_else(PoisonError::into_inner).as_deref())?,
+            };
+            let result = f(&conn);
+            readers.lock().unwrap_or_else(PoisonError::into_inner).push(conn);
+            result
+        }).await?
+    }
+
+    /// Runs `f` on the writer. Writes are serialised, each waiting for the previous one
+    pub async fn write<T, F>(&self, f: F) -> Result<T, Box<dyn Error + Send + Sync>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, Box<dyn Error + Send + Sync>> + Send + 'static,
+    {
+        let writer = self.writer.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = writer.lock().unwrap_or_else(PoisonError::into_inner);
+            f(&mut conn)
+        }).await?
+    }
+
+    /// Re-encrypts the database under `key`. Waits for every reader to be returned and
+    /// closes them, since SQLCipher cannot rekey while other connections are open
+    pub async fn rekey(&self, key: &[u8]) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let _all = self.available.acquire_many(self.reader_slots as u32).await?;
+        let writer = self.writer.clone();
+        let readers = self.readers.clone();
+        let current = self.key.clone();
+        let path = self.path.clone();
+        let key = key.to_vec();
+        tokio::task::spawn_blocking(move || {
+            let conn = writer.lock().unwrap_or_else(PoisonError::into_inner);
+            let mut current = current.lock().unwrap_or_else(PoisonError::into_inner);
+            if current.is_none() {
+                return Err(Box::new(KeyError::NotEncrypted) as Box<dyn Error + Send + Sync>);
+            }
+
+            let mut readers = readers.lock().unwrap_or_else(PoisonError::into_inner);
+            let count = readers.len();
+            readers.clear();
+            cipher::rekey(&conn, &key)?;
+            *current = Some(key);
+            for _ in 0..count {
+                readers.push(open_reader(&path, current.as_deref())?);
+            }
+            Ok(())
+        }).await?
+    }
+}
+
+/// Opens a connection with the database key applied and checked
+pub fn open_connection(path: &str, key: Option<&[u8]>) -> Result<Connection, KeyError> {
+    cipher::open_encrypted(path, key)
+}
+
+fn open_reader(path: &str, key: Option<&[u8]>) -> Result<Connection, KeyError> {
+    let conn = open_connection(path, key)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.pragma_update(None, "query_only", true)?;
+    Ok(conn)
+}
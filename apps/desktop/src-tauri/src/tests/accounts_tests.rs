@@ -0,0 +1,25 @@
+use crate::backend::accounts::{scoped_file, scoped_key, validate_account_id, BackendKind, DEFAULT_ACCOUNT};
+
+#[test]
+fn test_default_account_keeps_legacy_names() {
+    assert_eq!(scoped_key(DEFAULT_ACCOUNT, "baileys_creds"), "baileys_creds");
+    assert_eq!(scoped_file(DEFAULT_ACCOUNT, "session", "db"), "session.db");
+
+    assert_eq!(scoped_key("work", "baileys_creds"), "work/baileys_creds");
+    assert_eq!(scoped_file("work", "session", "db"), "session-work.db");
+}
+
+#[test]
+fn test_account_ids_are_path_safe() {
+    assert!(validate_account_id("sales-2").is_ok());
+    assert!(validate_account_id("").is_err());
+    assert!(validate_account_id("../session").is_err());
+    assert!(validate_account_id("a/b").is_err());
+}
+
+#[test]
+fn test_backend_names() {
+    assert_eq!(BackendKind::parse("whatsapp-web.js"), Some(BackendKind::Wwebjs));
+    assert_eq!(BackendKind::parse("baileys").map(|k| k.as_str()), Some("baileys"));
+    assert_eq!(BackendKind::parse("telegram"), None);
+}
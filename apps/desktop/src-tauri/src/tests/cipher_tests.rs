@@ -0,0 +1,114 @@
+use crate::storage::cipher::{KeyError, KEY_LEN};
+use crate::storage::pool::DEFAULT_READERS;
+use crate::storage::{Message, SqliteStorage, Storage};
+use rusqlite::Connection;
+use std::error::Error;
+use tempfile::NamedTempFile;
+
+const KEY: [u8; KEY_LEN] = [7; KEY_LEN];
+const OTHER_KEY: [u8; KEY_LEN] = [9; KEY_LEN];
+
+fn message(id: &str, content: &str) -> Message {
+    Message {
+        id: id.to_string(),
+        chat_id: "chat1".to_string(),
+        content: content.to_string(),
+        sender_id: "alice".to_string(),
+        timestamp: 100,
+        from_me: false,
+        revoked_at: None,
+        revoked_by: None,
+        edited_at: None,
+        quoted_id: None,
+        status: None,
+    }
+}
+
+fn key_error(result: Result<SqliteStorage, Box<dyn Error>>) -> KeyError {
+    match result {
+        Ok(_) => panic!("database opened"),
+        Err(e) => *e.downcast::<KeyError>().expect("not a key error"),
+    }
+}
+
+#[tokio::test]
+async fn test_database_is_unreadable_without_key() {
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+    let storage = SqliteStorage::new(path, Some(&KEY)).unwrap();
+    storage.save_message("default", message("m1", "a very secret sentence")).await.unwrap();
+    drop(storage);
+
+    let bytes = std::fs::read(path).unwrap();
+    assert!(!bytes.starts_with(b"SQLite format 3"));
+    assert!(!bytes.windows(6).any(|w| w == b"secret"));
+
+    // Plain SQLite cannot make sense of the file
+    let plain = Connection::open(path).unwrap();
+    assert!(plain.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(())).is_err());
+
+    assert!(matches!(key_error(SqliteStorage::new(path, None)), KeyError::KeyRequired));
+    assert!(matches!(key_error(SqliteStorage::new(path, Some(&OTHER_KEY))), KeyError::WrongKey));
+    assert!(matches!(key_error(SqliteStorage::new(path, Some(&KEY[..16]))), KeyError::InvalidLength(16)));
+
+    let storage = SqliteStorage::new(path, Some(&KEY)).unwrap();
+    assert_eq!(storage.get_messages("default", "chat1", 10, 0).await.unwrap()[0].content, "a very secret sentence");
+}
+
+#[test]
+fn test_key_on_plain_database_is_refused() {
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+    drop(SqliteStorage::new(path, None).unwrap());
+
+    assert!(matches!(key_error(SqliteStorage::new(path, Some(&KEY))), KeyError::NotEncrypted));
+}
+
+#[tokio::test]
+async fn test_passphrase_keyed_database_is_rekeyed() {
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+    let passphrase = format!("PRAGMA key = '{}';", hex::encode(KEY));
+    {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(&passphrase).unwrap();
+        conn.execute_batch("CREATE TABLE legacy (id INTEGER); INSERT INTO legacy VALUES (1);").unwrap();
+    }
+
+    let storage = SqliteStorage::new(path, Some(&KEY)).unwrap();
+    storage.save_message("default", message("m1", "hello")).await.unwrap();
+    drop(storage);
+
+    // The hex passphrase no longer opens it, the raw key does
+    let conn = Connection::open(path).unwrap();
+    conn.execute_batch(&passphrase).unwrap();
+    assert!(conn.query_row("SELECT count(*) FROM legacy", [], |_| Ok(())).is_err());
+
+    let storage = SqliteStorage::new(path, Some(&KEY)).unwrap();
+    assert_eq!(storage.get_messages("default", "chat1", 10, 0).await.unwrap().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_rekey_replaces_key_for_all_connections() {
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+    let storage = SqliteStorage::with_readers(path, Some(&KEY), DEFAULT_READERS).unwrap();
+    storage.save_message("default", message("m1", "before")).await.unwrap();
+
+    storage.rekey(&OTHER_KEY).await.unwrap();
+
+    // Reopened readers and the writer both use the new key
+    storage.save_message("default", message("m2", "after")).await.unwrap();
+    assert_eq!(storage.get_messages("default", "chat1", 10, 0).await.unwrap().len(), 2);
+    drop(storage);
+
+    assert!(matches!(key_error(SqliteStorage::new(path, Some(&KEY))), KeyError::WrongKey));
+    let storage = SqliteStorage::new(path, Some(&OTHER_KEY)).unwrap();
+    assert_eq!(storage.get_messages("default", "chat1", 10, 0).await.unwrap().len(), 2);
+
+    // A plain database cannot be rekeyed in place
+    let plain = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(plain.path().to_str().unwrap(), None).unwrap();
+    let err = storage.rekey(&KEY).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<KeyError>(), Some(KeyError::NotEncrypted)));
+}
@@ -19,11 +19,741 @@ async fn test_db_message_storage() {
         sender_id: "me".to_string(),
         timestamp: 100,
         from_me: true,
+        revoked_at: None,
+        revoked_by: None,
+        edited_at: None,
+        quoted_id: None,
+        status: None,
     };
 
-    storage.save_message(msg.clone()).await.unwrap();
+    storage.save_message("default", msg.clone()).await.unwrap();
 
-    let messages = storage.get_messages("chat1", 10, 0).await.unwrap();
+    let messages = storage.get_messages("default", "chat1", 10, 0).await.unwrap();
     assert_eq!(messages.len(), 1);
     assert_eq!(messages[0].content, "Hello World");
 }
+
+#[tokio::test]
+async fn test_persist_message_events_upserts_chat() {
+    use crate::backend::events::{MessageContent, MessageEvent, ProviderEvent};
+    use crate::storage::ingest::{persist_event, persist_events};
+
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    let incoming = |id: &str, timestamp: i64, from_me: bool| ProviderEvent::Message(MessageEvent {
+        id: id.to_string(),
+        chat_id: "123@s.whatsapp.net".to_string(),
+        sender_id: if from_me { "me".to_string() } else { "123@s.whatsapp.net".to_string() },
+        sender_name: Some("Alice".to_string()),
+        timestamp,
+        from_me,
+        content: MessageContent::Text { text: format!("msg {}", id) },
+        quoted: None,
+    });
+
+    persist_event(&storage, "default", &incoming("a", 10, false)).await.unwrap();
+    persist_event(&storage, "default", &incoming("b", 20, false)).await.unwrap();
+    // Re-delivered, in its own event and within a burst
+    persist_event(&storage, "default", &incoming("b", 20, false)).await.unwrap();
+    persist_events(&storage, "default", &[incoming("a", 10, false), incoming("a", 10, false)]).await;
+
+    let chat = storage.get_chat("default", "123@s.whatsapp.net").await.unwrap().unwrap();
+    assert_eq!(chat.name, "Alice");
+    assert_eq!(chat.unread_count, 2);
+    assert_eq!(chat.last_message_timestamp, 20);
+
+    storage.mark_chat_read("default", "123@s.whatsapp.net").await.unwrap();
+    assert_eq!(storage.get_chat("default", "123@s.whatsapp.net").await.unwrap().unwrap().unread_count, 0);
+    persist_event(&storage, "default", &incoming("d", 25, false)).await.unwrap();
+    assert_eq!(storage.get_chat("default", "123@s.whatsapp.net").await.unwrap().unwrap().unread_count, 1);
+
+    persist_event(&storage, "default", &incoming("c", 30, true)).await.unwrap();
+    let chats = storage.get_chats("default").await.unwrap();
+    assert_eq!(chats.len(), 1);
+    assert_eq!(chats[0].unread_count, 0);
+
+    let messages = storage.get_messages("default", "123@s.whatsapp.net", 10, 0).await.unwrap();
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[0].content, "msg c");
+}
+
+#[tokio::test]
+async fn test_accounts_sharing_a_chat_stay_apart() {
+    use crate::backend::events::{MessageContent, MessageEvent, ProviderEvent};
+    use crate::storage::ingest::persist_event;
+    use crate::storage::SearchFilters;
+
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    // Both accounts are in the same group and receive the same message
+    let incoming = ProviderEvent::Message(MessageEvent {
+        id: "m1".to_string(),
+        chat_id: "team@g.us".to_string(),
+        sender_id: "alice@s.whatsapp.net".to_string(),
+        sender_name: Some("Alice".to_string()),
+        timestamp: 10,
+        from_me: false,
+        content: MessageContent::Text { text: "standup moved".to_string() },
+        quoted: None,
+    });
+    persist_event(&storage, "default", &incoming).await.unwrap();
+    persist_event(&storage, "work", &incoming).await.unwrap();
+
+    assert_eq!(storage.get_chat("default", "team@g.us").await.unwrap().unwrap().unread_count, 1);
+    assert_eq!(storage.get_chat("work", "team@g.us").await.unwrap().unwrap().unread_count, 1);
+
+    storage.mark_chat_read("work", "team@g.us").await.unwrap();
+    storage.mark_message_revoked("work", "m1", "alice@s.whatsapp.net", 20).await.unwrap();
+    assert_eq!(storage.get_chat("default", "team@g.us").await.unwrap().unwrap().unread_count, 1);
+    assert!(storage.get_message("default", "m1").await.unwrap().unwrap().revoked_at.is_none());
+    assert_eq!(storage.get_message("work", "m1").await.unwrap().unwrap().revoked_at, Some(20));
+
+    assert_eq!(storage.search_messages("default", "standup", &SearchFilters::default()).await.unwrap().len(), 1);
+    assert!(storage.get_chats("personal").await.unwrap().is_empty());
+    assert!(storage.search_messages("personal", "standup", &SearchFilters::default()).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_revoke_keeps_content() {
+    use crate::backend::events::{MessageContent, MessageEvent, ProviderEvent, RevokeEvent};
+    use crate::storage::ingest::persist_event;
+
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    let message = ProviderEvent::Message(MessageEvent {
+        id: "a".to_string(),
+        chat_id: "123@s.whatsapp.net".to_string(),
+        sender_id: "123@s.whatsapp.net".to_string(),
+        sender_name: None,
+        timestamp: 10,
+        from_me: false,
+        content: MessageContent::Text { text: "secret".to_string() },
+        quoted: None,
+    });
+    persist_event(&storage, "default", &message).await.unwrap();
+    persist_event(&storage, "default", &ProviderEvent::MessageRevoked(RevokeEvent {
+        chat_id: "123@s.whatsapp.net".to_string(),
+        message_id: "a".to_string(),
+        revoked_by: "123@s.whatsapp.net".to_string(),
+        timestamp: 20,
+    })).await.unwrap();
+
+    // A re-delivery of the original must not clear the flag
+    persist_event(&storage, "default", &message).await.unwrap();
+
+    let messages = storage.get_messages("default", "123@s.whatsapp.net", 10, 0).await.unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].content, "secret");
+    assert_eq!(messages[0].revoked_at, Some(20));
+    assert_eq!(messages[0].revoked_by.as_deref(), Some("123@s.whatsapp.net"));
+
+    assert!(!storage.mark_message_revoked("default", "missing", "x", 1).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_old_messages_table_gains_revoke_columns() {
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+    {
+        let conn = rusqlite::Connection::open(path).unwrap();
+        conn.execute(
+            "CREATE TABLE messages (id TEXT PRIMARY KEY, chat_id TEXT NOT NULL, content TEXT NOT NULL,
+             sender_id TEXT NOT NULL, timestamp INTEGER NOT NULL, from_me BOOLEAN NOT NULL)",
+            [],
+        ).unwrap();
+        conn.execute("INSERT INTO messages VALUES ('old', 'chat1', 'hi', 'me', 1, 1)", []).unwrap();
+    }
+
+    let storage = SqliteStorage::new(path, None).unwrap();
+    assert!(storage.mark_message_revoked("default", "old", "me", 2).await.unwrap());
+    let messages = storage.get_messages("default", "chat1", 10, 0).await.unwrap();
+    assert_eq!(messages[0].revoked_at, Some(2));
+}
+
+#[tokio::test]
+async fn test_edits_keep_revision_history() {
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    storage.save_message("default", Message {
+        id: "a".to_string(),
+        chat_id: "chat1".to_string(),
+        content: "helo".to_string(),
+        sender_id: "me".to_string(),
+        timestamp: 10,
+        from_me: true,
+        revoked_at: None,
+        revoked_by: None,
+        edited_at: None,
+        quoted_id: None,
+        status: None,
+    }).await.unwrap();
+
+    assert!(storage.get_message_revisions("default", "a").await.unwrap().is_empty());
+
+    assert!(storage.save_message_edit("default", "a", "hello!", 30).await.unwrap());
+    // An older edit delivered late is recorded but does not win
+    assert!(storage.save_message_edit("default", "a", "hello", 20).await.unwrap());
+    assert!(!storage.save_message_edit("default", "missing", "x", 1).await.unwrap());
+
+    let messages = storage.get_messages("default", "chat1", 10, 0).await.unwrap();
+    assert_eq!(messages[0].content, "hello!");
+    assert_eq!(messages[0].edited_at, Some(30));
+
+    let history: Vec<(String, i64)> = storage.get_message_revisions("default", "a").await.unwrap()
+        .into_iter()
+        .map(|r| (r.content, r.timestamp))
+        .collect();
+    assert_eq!(history, vec![
+        ("helo".to_string(), 10),
+        ("hello".to_string(), 20),
+        ("hello!".to_string(), 30),
+    ]);
+}
+
+#[tokio::test]
+async fn test_reactions_aggregate_per_sender() {
+    use crate::backend::events::{ProviderEvent, ReactionEvent};
+    use crate::storage::ingest::persist_event;
+
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    let reaction = |sender: &str, emoji: Option<&str>, timestamp: i64| ProviderEvent::Reaction(ReactionEvent {
+        chat_id: "group@g.us".to_string(),
+        message_id: "a".to_string(),
+        sender_id: sender.to_string(),
+        emoji: emoji.map(str::to_string),
+        timestamp,
+    });
+
+    persist_event(&storage, "default", &reaction("alice", Some("👍"), 10)).await.unwrap();
+    persist_event(&storage, "default", &reaction("bob", Some("😂"), 11)).await.unwrap();
+    // A later reaction replaces the sender's previous one, a stale one does not
+    persist_event(&storage, "default", &reaction("alice", Some("❤️"), 12)).await.unwrap();
+    persist_event(&storage, "default", &reaction("alice", Some("😮"), 5)).await.unwrap();
+
+    let current: Vec<(String, String)> = storage.get_reactions("default", "a").await.unwrap()
+        .into_iter()
+        .map(|r| (r.sender_id, r.emoji))
+        .collect();
+    assert_eq!(current, vec![
+        ("bob".to_string(), "😂".to_string()),
+        ("alice".to_string(), "❤️".to_string()),
+    ]);
+
+    persist_event(&storage, "default", &reaction("bob", None, 13)).await.unwrap();
+    let remaining = storage.get_reactions("default", "a").await.unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].sender_id, "alice");
+}
+
+#[tokio::test]
+async fn test_reply_keeps_quoted_relation() {
+    use crate::backend::events::{MessageContent, MessageEvent, ProviderEvent, QuotedMessage};
+    use crate::storage::ingest::persist_event;
+
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    let message = |id: &str, quoted: Option<QuotedMessage>| ProviderEvent::Message(MessageEvent {
+        id: id.to_string(),
+        chat_id: "123@s.whatsapp.net".to_string(),
+        sender_id: "123@s.whatsapp.net".to_string(),
+        sender_name: None,
+        timestamp: 10,
+        from_me: false,
+        content: MessageContent::Text { text: "sure".to_string() },
+        quoted,
+    });
+
+    persist_event(&storage, "default", &message("reply", Some(QuotedMessage {
+        id: "original".to_string(),
+        sender_id: Some("me".to_string()),
+        snippet: "lunch?".to_string(),
+    }))).await.unwrap();
+    // A re-delivery without the context must not drop the relation
+    persist_event(&storage, "default", &message("reply", None)).await.unwrap();
+
+    let stored = storage.get_message("default", "reply").await.unwrap().unwrap();
+    assert_eq!(stored.quoted_id.as_deref(), Some("original"));
+}
+
+#[tokio::test]
+async fn test_group_save_replaces_participants() {
+    use crate::backend::groups::{GroupMember, GroupMetadata, ParticipantRole};
+
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    let member = |jid: &str, role| GroupMember { jid: jid.to_string(), role };
+    let mut group = GroupMetadata {
+        id: "team@g.us".to_string(),
+        subject: "Team".to_string(),
+        description: None,
+        owner: Some("alice@s.whatsapp.net".to_string()),
+        created_at: Some(100),
+        participants: vec![
+            member("alice@s.whatsapp.net", ParticipantRole::SuperAdmin),
+            member("bob@s.whatsapp.net", ParticipantRole::Member),
+        ],
+    };
+    let (row, participants) = group.to_storage();
+    storage.save_group("default", row, participants).await.unwrap();
+
+    group.subject = "Core team".to_string();
+    group.participants = vec![
+        member("alice@s.whatsapp.net", ParticipantRole::SuperAdmin),
+        member("carol@s.whatsapp.net", ParticipantRole::Admin),
+    ];
+    let (row, participants) = group.to_storage();
+    storage.save_group("default", row, participants).await.unwrap();
+
+    let groups = storage.get_groups("default").await.unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].subject, "Core team");
+
+    let roles: Vec<(String, String)> = storage.get_group_participants("default", "team@g.us").await.unwrap()
+        .into_iter()
+        .map(|p| (p.jid, p.role))
+        .collect();
+    assert_eq!(roles, vec![
+        ("alice@s.whatsapp.net".to_string(), "super_admin".to_string()),
+        ("carol@s.whatsapp.net".to_string(), "admin".to_string()),
+    ]);
+
+    storage.remove_group("default", "team@g.us").await.unwrap();
+    assert!(storage.get_groups("default").await.unwrap().is_empty());
+    assert!(storage.get_group_participants("default", "team@g.us").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_history_sync_populates_chats_and_contacts() {
+    use crate::backend::events::{ContactInfo, HistoryChat, HistorySyncEvent, MessageContent, MessageEvent, ProviderEvent};
+    use crate::storage::ingest::{ingest_history, persist_event};
+
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    let message = |id: &str, text: &str, timestamp: i64| MessageEvent {
+        id: id.to_string(),
+        chat_id: "alice@s.whatsapp.net".to_string(),
+        sender_id: "alice@s.whatsapp.net".to_string(),
+        sender_name: None,
+        timestamp,
+        from_me: false,
+        content: MessageContent::Text { text: text.to_string() },
+        quoted: None,
+    };
+
+    // Delivered live before the history batch that also contains it
+    persist_event(&storage, "default", &ProviderEvent::Message(message("b", "live", 20))).await.unwrap();
+
+    let progress = ingest_history(&storage, "default", &HistorySyncEvent {
+        chats: vec![HistoryChat {
+            id: "alice@s.whatsapp.net".to_string(),
+            name: None,
+            unread_count: 3,
+            last_message_timestamp: None,
+        }],
+        contacts: vec![ContactInfo {
+            jid: "alice@s.whatsapp.net".to_string(),
+            name: Some("Alice".to_string()),
+            notify_name: None,
+        }],
+        messages: vec![message("a", "old", 10), message("b", "from history", 20)],
+        progress: Some(100),
+        is_latest: true,
+    }).await.unwrap();
+    assert_eq!(progress.messages, 2);
+
+    let chat = storage.get_chat("default", "alice@s.whatsapp.net").await.unwrap().unwrap();
+    assert_eq!(chat.name, "Alice");
+    assert_eq!(chat.unread_count, 3);
+    assert_eq!(chat.last_message_timestamp, 20);
+
+    let contents: Vec<String> = storage.get_messages("default", "alice@s.whatsapp.net", 10, 0).await.unwrap()
+        .into_iter()
+        .map(|m| m.content)
+        .collect();
+    assert_eq!(contents, vec!["live".to_string(), "old".to_string()]);
+
+    assert_eq!(storage.get_contacts("default").await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_group_receipts_aggregate_status() {
+    use crate::backend::events::{MessageContent, MessageEvent, ProviderEvent, ReceiptEvent, ReceiptKind};
+    use crate::storage::ingest::persist_event;
+    use crate::storage::{Group, GroupParticipant, MessageStatus};
+
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    let members = ["me@s.whatsapp.net", "alice@s.whatsapp.net", "bob@s.whatsapp.net"];
+    storage.save_group("default", 
+        Group {
+            id: "team@g.us".to_string(),
+            subject: "Team".to_string(),
+            description: None,
+            owner: None,
+            created_at: None,
+        },
+        members.iter().map(|jid| GroupParticipant {
+            group_id: "team@g.us".to_string(),
+            jid: jid.to_string(),
+            role: "member".to_string(),
+        }).collect(),
+    ).await.unwrap();
+
+    persist_event(&storage, "default", &ProviderEvent::Message(MessageEvent {
+        id: "m1".to_string(),
+        chat_id: "team@g.us".to_string(),
+        sender_id: "me@s.whatsapp.net".to_string(),
+        sender_name: None,
+        timestamp: 10,
+        from_me: true,
+        content: MessageContent::Text { text: "hi all".to_string() },
+        quoted: None,
+    })).await.unwrap();
+    assert_eq!(storage.get_message("default", "m1").await.unwrap().unwrap().status, Some(MessageStatus::Sent));
+
+    let receipt = |sender: &str, kind: ReceiptKind, timestamp: i64| ProviderEvent::Receipt(ReceiptEvent {
+        chat_id: "team@g.us".to_string(),
+        sender_id: sender.to_string(),
+        message_ids: vec!["m1".to_string()],
+        kind,
+        timestamp,
+    });
+    let statuses = |events: Vec<ProviderEvent>| -> Vec<MessageStatus> {
+        events.into_iter().filter_map(|e| match e {
+            ProviderEvent::MessageStatusChanged(change) => Some(change.status),
+            _ => None,
+        }).collect()
+    };
+
+    // Only once every other member has it does the message count as delivered
+    assert!(statuses(persist_event(&storage, "default", &receipt("alice@s.whatsapp.net", ReceiptKind::Delivered, 11)).await.unwrap()).is_empty());
+    assert_eq!(statuses(persist_event(&storage, "default", &receipt("bob@s.whatsapp.net", ReceiptKind::Delivered, 12)).await.unwrap()), vec![MessageStatus::Delivered]);
+    assert!(statuses(persist_event(&storage, "default", &receipt("alice@s.whatsapp.net", ReceiptKind::Read, 13)).await.unwrap()).is_empty());
+    assert_eq!(statuses(persist_event(&storage, "default", &receipt("bob@s.whatsapp.net", ReceiptKind::Read, 14)).await.unwrap()), vec![MessageStatus::Read]);
+
+    // A late delivery receipt never moves the status back
+    assert!(statuses(persist_event(&storage, "default", &receipt("bob@s.whatsapp.net", ReceiptKind::Delivered, 15)).await.unwrap()).is_empty());
+    assert_eq!(storage.get_message("default", "m1").await.unwrap().unwrap().status, Some(MessageStatus::Read));
+
+    let receipts = storage.get_message_receipts("default", "m1").await.unwrap();
+    assert_eq!(receipts.len(), 2);
+    let bob = receipts.iter().find(|r| r.participant == "bob@s.whatsapp.net").unwrap();
+    assert_eq!((bob.delivered_at, bob.read_at, bob.played_at), (Some(12), Some(14), None));
+}
+
+#[tokio::test]
+async fn test_outbox_claims_in_order_and_retries() {
+    use crate::storage::{OutboxEntry, OutboxState};
+
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    let queue = |chat_id: &str, content: &str| OutboxEntry {
+        id: 0,
+        account_id: "default".to_string(),
+        chat_id: chat_id.to_string(),
+        content: content.to_string(),
+        quoted_id: None,
+        media_path: None,
+        mime_type: None,
+        state: OutboxState::Pending,
+        attempts: 0,
+        next_attempt_at: 100,
+        last_error: None,
+        message_id: None,
+        created_at: 100,
+    };
+    let first = storage.enqueue_outbox(queue("chat1", "first")).await.unwrap();
+    let second = storage.enqueue_outbox(queue("chat1", "second")).await.unwrap();
+    let other = storage.enqueue_outbox(queue("chat2", "other")).await.unwrap();
+
+    // The first send fails and backs off; the chat's next message waits behind it
+    let claimed = storage.claim_outbox("default", 100).await.unwrap().unwrap();
+    assert_eq!(claimed.id, first.id);
+    assert_eq!(claimed.state, OutboxState::Sending);
+    let failed = storage.fail_outbox(first.id, "offline", Some(200)).await.unwrap().unwrap();
+    assert_eq!(failed.state, OutboxState::Pending);
+    assert_eq!(failed.attempts, 1);
+
+    let claimed = storage.claim_outbox("default", 100).await.unwrap().unwrap();
+    assert_eq!(claimed.id, other.id);
+    assert!(storage.claim_outbox("default", 100).await.unwrap().is_none());
+    assert_eq!(storage.next_outbox_attempt("default").await.unwrap(), Some(100));
+
+    // A crash mid-send puts the entry back in the queue
+    storage.requeue_outbox("default").await.unwrap();
+    let claimed = storage.claim_outbox("default", 100).await.unwrap().unwrap();
+    assert_eq!(claimed.id, other.id);
+    let sent = storage.finish_outbox(other.id, "MSG1").await.unwrap().unwrap();
+    assert_eq!(sent.state, OutboxState::Sent);
+    assert_eq!(sent.message_id.as_deref(), Some("MSG1"));
+
+    let claimed = storage.claim_outbox("default", 200).await.unwrap().unwrap();
+    assert_eq!(claimed.id, first.id);
+    assert!(!storage.cancel_outbox("default", first.id).await.unwrap());
+    storage.fail_outbox(first.id, "rejected", None).await.unwrap();
+
+    // Other accounts cannot touch the entry
+    assert!(storage.retry_outbox("work", first.id, 200).await.unwrap().is_none());
+    assert!(!storage.cancel_outbox("work", first.id).await.unwrap());
+
+    // A failed message no longer holds up the chat
+    assert!(storage.cancel_outbox("default", first.id).await.unwrap());
+    let claimed = storage.claim_outbox("default", 200).await.unwrap().unwrap();
+    assert_eq!(claimed.id, second.id);
+
+    let pending = storage.get_outbox("default").await.unwrap();
+    assert_eq!(pending.iter().map(|e| e.id).collect::<Vec<_>>(), vec![second.id]);
+}
+
+#[tokio::test]
+async fn test_scheduled_message_moves_to_outbox_once() {
+    use crate::storage::{MissedPolicy, OutboxState, ScheduleState, ScheduledMessage};
+
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    let schedule = |send_at: i64, if_missed: MissedPolicy| ScheduledMessage {
+        id: 0,
+        account_id: "default".to_string(),
+        chat_id: "chat1".to_string(),
+        content: "Happy birthday".to_string(),
+        send_at,
+        if_missed,
+        state: ScheduleState::Scheduled,
+        outbox_id: None,
+        created_at: 0,
+    };
+    let later = storage.save_schedule(schedule(500, MissedPolicy::SendLate)).await.unwrap();
+    let missed = storage.save_schedule(schedule(100, MissedPolicy::Skip)).await.unwrap();
+    let cancelled = storage.save_schedule(schedule(300, MissedPolicy::SendLate)).await.unwrap();
+
+    assert_eq!(storage.next_schedule_at("default").await.unwrap(), Some(100));
+    // Other accounts cannot touch it
+    assert!(storage.update_schedule("work", cancelled.id, "Hijacked", 300, MissedPolicy::Skip).await.unwrap().is_none());
+    assert!(!storage.cancel_schedule("work", cancelled.id).await.unwrap());
+    assert!(storage.cancel_schedule("default", cancelled.id).await.unwrap());
+
+    let due = storage.due_schedules("default", 1000).await.unwrap();
+    assert_eq!(due.iter().map(|s| s.id).collect::<Vec<_>>(), vec![missed.id, later.id]);
+
+    let skipped = storage.skip_schedule(missed.id, 1000).await.unwrap().unwrap();
+    assert_eq!(skipped.state, ScheduleState::Skipped);
+    assert!(storage.queue_schedule(missed.id, 1000).await.unwrap().is_none());
+
+    // Not due yet, then queued exactly once
+    assert!(storage.queue_schedule(later.id, 400).await.unwrap().is_none());
+    let (queued, entry) = storage.queue_schedule(later.id, 1000).await.unwrap().unwrap();
+    assert_eq!(queued.state, ScheduleState::Queued);
+    assert_eq!(queued.outbox_id, Some(entry.id));
+    assert_eq!(entry.state, OutboxState::Pending);
+    assert_eq!(entry.content, "Happy birthday");
+    assert!(storage.queue_schedule(later.id, 1000).await.unwrap().is_none());
+
+    // Gone out, so it can no longer be changed
+    assert!(storage.update_schedule("default", later.id, "Too late", 2000, MissedPolicy::Skip).await.unwrap().is_none());
+    assert!(!storage.cancel_schedule("default", later.id).await.unwrap());
+    assert_eq!(storage.get_outbox("default").await.unwrap().len(), 1);
+    assert_eq!(storage.next_schedule_at("default").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_removing_account_drops_unsent_messages() {
+    use crate::storage::{Account, MissedPolicy, OutboxEntry, OutboxState, ScheduleState, ScheduledMessage};
+
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    for account_id in ["default", "work"] {
+        storage.save_account(Account { id: account_id.to_string(), backend: "rust".to_string(), created_at: 0 }).await.unwrap();
+        storage.enqueue_outbox(OutboxEntry {
+            id: 0,
+            account_id: account_id.to_string(),
+            chat_id: "chat1".to_string(),
+            content: "On my way".to_string(),
+            quoted_id: None,
+            media_path: None,
+            mime_type: None,
+            state: OutboxState::Pending,
+            attempts: 0,
+            next_attempt_at: 100,
+            last_error: None,
+            message_id: None,
+            created_at: 100,
+        }).await.unwrap();
+        storage.save_schedule(ScheduledMessage {
+            id: 0,
+            account_id: account_id.to_string(),
+            chat_id: "chat1".to_string(),
+            content: "Happy birthday".to_string(),
+            send_at: 500,
+            if_missed: MissedPolicy::SendLate,
+            state: ScheduleState::Scheduled,
+            outbox_id: None,
+            created_at: 0,
+        }).await.unwrap();
+    }
+
+    storage.remove_account("work").await.unwrap();
+
+    // Set up again under the same id, nothing of the old account goes out
+    assert!(storage.get_outbox("work").await.unwrap().is_empty());
+    assert!(storage.claim_outbox("work", 1000).await.unwrap().is_none());
+    assert!(storage.due_schedules("work", 1000).await.unwrap().is_empty());
+
+    assert_eq!(storage.get_outbox("default").await.unwrap().len(), 1);
+    assert_eq!(storage.due_schedules("default", 1000).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_search_messages_with_filters_and_edits() {
+    use crate::storage::{SearchFilters, SNIPPET_END, SNIPPET_START};
+
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    let message = |id: &str, chat_id: &str, content: &str, timestamp: i64, from_me: bool| Message {
+        id: id.to_string(),
+        chat_id: chat_id.to_string(),
+        content: content.to_string(),
+        sender_id: if from_me { "me" } else { "alice" }.to_string(),
+        timestamp,
+        from_me,
+        revoked_at: None,
+        revoked_by: None,
+        edited_at: None,
+        quoted_id: None,
+        status: None,
+    };
+    storage.save_message("default", message("m1", "chat1", "Dinner at the café tonight?", 100, false)).await.unwrap();
+    storage.save_message("default", message("m2", "chat1", "Sure, dinner sounds good", 200, true)).await.unwrap();
+    storage.save_message("default", message("m3", "chat2", "Lunch tomorrow", 300, false)).await.unwrap();
+
+    // Newest first, prefix match on the last word, accents folded
+    let hits = storage.search_messages("default", "dinn", &SearchFilters::default()).await.unwrap();
+    assert_eq!(hits.iter().map(|h| h.message.id.as_str()).collect::<Vec<_>>(), vec!["m2", "m1"]);
+    assert!(hits[1].snippet.contains(&format!("{}Dinner{}", SNIPPET_START, SNIPPET_END)));
+    assert_eq!(storage.search_messages("default", "cafe", &SearchFilters::default()).await.unwrap().len(), 1);
+
+    let filters = SearchFilters { from_me: Some(false), ..Default::default() };
+    let hits = storage.search_messages("default", "dinner", &filters).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].message.id, "m1");
+
+    let filters = SearchFilters { chat_id: Some("chat1".to_string()), after: Some(150), before: Some(250), ..Default::default() };
+    assert_eq!(storage.search_messages("default", "dinner", &filters).await.unwrap().len(), 1);
+
+    // FTS syntax is taken literally rather than failing the query
+    assert!(storage.search_messages("default", "\"dinner OR", &SearchFilters::default()).await.unwrap().is_empty());
+    assert!(storage.search_messages("default", "   ", &SearchFilters::default()).await.unwrap().is_empty());
+
+    // Edits replace the indexed text
+    storage.save_message_edit("default", "m3", "Brunch tomorrow", 400).await.unwrap();
+    assert!(storage.search_messages("default", "lunch", &SearchFilters::default()).await.unwrap().is_empty());
+    assert_eq!(storage.search_messages("default", "brunch", &SearchFilters::default()).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_search_index_survives_vacuum() {
+    use crate::storage::SearchFilters;
+
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+    let storage = SqliteStorage::new(path, None).unwrap();
+    for (i, content) in ["first note", "second note", "third note"].iter().enumerate() {
+        storage.save_message("default", Message {
+            id: format!("m{}", i),
+            chat_id: "chat1".to_string(),
+            content: content.to_string(),
+            sender_id: "alice".to_string(),
+            timestamp: i as i64,
+            from_me: false,
+            revoked_at: None,
+            revoked_by: None,
+            edited_at: None,
+            quoted_id: None,
+            status: None,
+        }).await.unwrap();
+    }
+    drop(storage);
+
+    // Leave a gap in the keys, then compact the file
+    let conn = rusqlite::Connection::open(path).unwrap();
+    conn.execute("DELETE FROM messages WHERE id = 'm0'", []).unwrap();
+    conn.execute_batch("VACUUM").unwrap();
+    // VACUUM only promises to keep the values of an INTEGER PRIMARY KEY, so the index must be keyed on one
+    let key: String = conn
+        .query_row("SELECT name FROM pragma_table_info('messages') WHERE pk = 1 AND type = 'INTEGER'", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(key, "seq");
+    drop(conn);
+
+    let storage = SqliteStorage::new(path, None).unwrap();
+    let hits = storage.search_messages("default", "third", &SearchFilters::default()).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].message.content, "third note");
+    assert!(storage.search_messages("default", "first", &SearchFilters::default()).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_message_pages_follow_cursors() {
+    use crate::storage::{MessageCursor, MessageRange};
+
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    // m0..m9, with m4 and m5 sharing a timestamp so the id has to break the tie
+    let messages = (0..10)
+        .map(|i| Message {
+            id: format!("m{}", i),
+            chat_id: "chat1".to_string(),
+            content: format!("message {}", i),
+            sender_id: "alice".to_string(),
+            timestamp: if i == 5 { 40 } else { i * 10 },
+            from_me: false,
+            revoked_at: None,
+            revoked_by: None,
+            edited_at: None,
+            quoted_id: None,
+            status: None,
+        })
+        .collect();
+    storage.save_history("default", Vec::new(), Vec::new(), messages).await.unwrap();
+    let ids = |page: &crate::storage::MessagePage| page.messages.iter().map(|m| m.id.clone()).collect::<Vec<_>>();
+
+    let latest = storage.get_message_page("default", "chat1", &MessageRange::Latest, 4).await.unwrap();
+    assert_eq!(ids(&latest), vec!["m6", "m7", "m8", "m9"]);
+    assert!(latest.has_before && !latest.has_after);
+
+    let cursor = MessageCursor::of(&latest.messages[0]);
+    let older = storage.get_message_page("default", "chat1", &MessageRange::Before { cursor }, 4).await.unwrap();
+    assert_eq!(ids(&older), vec!["m2", "m3", "m4", "m5"]);
+    assert!(older.has_before && older.has_after);
+
+    let cursor = MessageCursor::of(&older.messages[0]);
+    let oldest = storage.get_message_page("default", "chat1", &MessageRange::Before { cursor }, 4).await.unwrap();
+    assert_eq!(ids(&oldest), vec!["m0", "m1"]);
+    assert!(!oldest.has_before);
+
+    let cursor = MessageCursor { timestamp: 40, id: "m4".to_string() };
+    let newer = storage.get_message_page("default", "chat1", &MessageRange::After { cursor }, 2).await.unwrap();
+    assert_eq!(ids(&newer), vec!["m5", "m6"]);
+    assert!(newer.has_before && newer.has_after);
+
+    let around = storage.get_message_page("default", "chat1", &MessageRange::Around { message_id: "m1".to_string() }, 5).await.unwrap();
+    assert_eq!(ids(&around), vec!["m0", "m1", "m2", "m3"]);
+    assert!(!around.has_before && around.has_after);
+
+    let missing = storage.get_message_page("default", "chat2", &MessageRange::Around { message_id: "m1".to_string() }, 5).await.unwrap();
+    assert!(missing.messages.is_empty());
+}
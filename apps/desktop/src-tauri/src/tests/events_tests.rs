@@ -0,0 +1,85 @@
+use crate::backend::events::{ConnectionState, MessageContent, ProviderEvent};
+
+#[test]
+fn test_provider_event_wire_format() {
+    let event = ProviderEvent::ConnectionStatus { status: ConnectionState::LoggedOut };
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["type"], "connection_status");
+    assert_eq!(json["payload"]["status"], "logged_out");
+}
+
+#[test]
+fn test_account_event_tags_provider_event() {
+    use crate::backend::events::AccountEvent;
+
+    let event = ProviderEvent::Qr("qr-data".to_string());
+    let json = serde_json::to_value(AccountEvent { account_id: "work", event: &event }).unwrap();
+    assert_eq!(json["account_id"], "work");
+    assert_eq!(json["type"], "qr");
+    assert_eq!(json["payload"], "qr-data");
+}
+
+#[test]
+fn test_message_event_from_adapter_payload() {
+    // Shape emitted by the sidecar adapters
+    let json = serde_json::json!({
+        "type": "message",
+        "payload": {
+            "id": "ABC",
+            "chat_id": "123@s.whatsapp.net",
+            "sender_id": "123@s.whatsapp.net",
+            "sender_name": "Alice",
+            "timestamp": 1700000000,
+            "from_me": false,
+            "content": { "kind": "image", "caption": "Look", "mime_type": "image/jpeg" }
+        }
+    });
+
+    match serde_json::from_value::<ProviderEvent>(json).unwrap() {
+        ProviderEvent::Message(msg) => {
+            assert_eq!(msg.id, "ABC");
+            assert!(matches!(msg.content, MessageContent::Image { .. }));
+            assert_eq!(msg.content.preview(), "Look");
+        }
+        other => panic!("Unexpected event: {:?}", other),
+    }
+}
+
+#[test]
+fn test_revoke_is_emitted_as_message_revoked() {
+    use crate::backend::events::RevokeEvent;
+
+    let event = ProviderEvent::MessageRevoked(RevokeEvent {
+        chat_id: "123@s.whatsapp.net".to_string(),
+        message_id: "ABC".to_string(),
+        revoked_by: "123@s.whatsapp.net".to_string(),
+        timestamp: 1700000000,
+    });
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["type"], "message_revoked");
+    assert_eq!(json["payload"]["message_id"], "ABC");
+}
+
+#[test]
+fn test_typing_presence_from_adapter_payload() {
+    use crate::backend::events::PresenceState;
+
+    let json = serde_json::json!({
+        "type": "presence",
+        "payload": {
+            "jid": "123@s.whatsapp.net",
+            "chat_id": "group@g.us",
+            "state": "recording",
+            "last_seen": null
+        }
+    });
+
+    match serde_json::from_value::<ProviderEvent>(json).unwrap() {
+        ProviderEvent::Presence(presence) => {
+            assert_eq!(presence.state, PresenceState::Recording);
+            assert_eq!(presence.chat_id.as_deref(), Some("group@g.us"));
+            assert_eq!(presence.last_seen, None);
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+}
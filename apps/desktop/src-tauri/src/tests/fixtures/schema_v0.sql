@@ -0,0 +1,24 @@
+-- Database as written by the first release, before schema versioning (user_version 0)
+CREATE TABLE messages (
+    id TEXT PRIMARY KEY,
+    chat_id TEXT NOT NULL,
+    content TEXT NOT NULL,
+    sender_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    from_me BOOLEAN NOT NULL
+);
+CREATE TABLE chats (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    unread_count INTEGER NOT NULL,
+    last_message_timestamp INTEGER NOT NULL
+);
+CREATE TABLE auth_store (
+    key TEXT PRIMARY KEY,
+    data TEXT NOT NULL
+);
+
+INSERT INTO messages VALUES ('m1', 'chat1', 'Hello from v0', 'alice', 100, 0);
+INSERT INTO messages VALUES ('m2', 'chat1', 'Reply from v0', 'me', 200, 1);
+INSERT INTO chats VALUES ('chat1', 'Alice', 1, 200);
+INSERT INTO auth_store VALUES ('creds', '{"me":"123"}');
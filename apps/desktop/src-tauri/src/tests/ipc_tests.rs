@@ -0,0 +1,111 @@
+use crate::backend::ipc::{AuthData, AuthUpdate, Command, Hello, IpcCommand, IpcEvent, QuotedTarget, SidecarEvent, PROTOCOL_VERSION, REQUIRED_COMMANDS};
+
+fn hello(protocol_version: u32, commands: &[&str]) -> Hello {
+    Hello {
+        protocol_version,
+        adapter: "baileys".to_string(),
+        adapter_version: "1.0.0".to_string(),
+        commands: commands.iter().map(|c| c.to_string()).collect(),
+    }
+}
+
+#[test]
+fn test_command_wire_format() {
+    let cmd = IpcCommand {
+        id: 7,
+        command: Command::SendMessage { jid: "123@s.whatsapp.net".to_string(), content: "hi".to_string(), quoted: None },
+    };
+    let json = serde_json::to_value(&cmd).unwrap();
+    assert_eq!(json["id"], 7);
+    assert_eq!(json["type"], "send_message");
+    assert_eq!(json["payload"]["content"], "hi");
+    assert!(json["payload"].get("quoted").is_none());
+}
+
+#[test]
+fn test_reply_wire_format() {
+    let cmd = IpcCommand {
+        id: 8,
+        command: Command::SendMessage {
+            jid: "group@g.us".to_string(),
+            content: "agreed".to_string(),
+            quoted: Some(QuotedTarget {
+                message_id: "ABC".to_string(),
+                from_me: false,
+                sender_id: "123@s.whatsapp.net".to_string(),
+                content: "lunch?".to_string(),
+            }),
+        },
+    };
+    let json = serde_json::to_value(&cmd).unwrap();
+    assert_eq!(json["payload"]["quoted"]["message_id"], "ABC");
+    assert_eq!(json["payload"]["quoted"]["sender_id"], "123@s.whatsapp.net");
+}
+
+#[test]
+fn test_reply_carries_command_id() {
+    let line = r#"{"type":"ack","payload":{"status":"sent","message_id":"ABC"},"id":7}"#;
+    let event: IpcEvent = serde_json::from_str(line).unwrap();
+    assert_eq!(event.id, Some(7));
+    assert!(matches!(event.event, SidecarEvent::Ack(_)));
+}
+
+#[test]
+fn test_unknown_event_is_rejected() {
+    assert!(serde_json::from_str::<IpcEvent>(r#"{"type":"bogus","payload":{}}"#).is_err());
+}
+
+#[test]
+fn test_handshake_compatibility() {
+    assert!(hello(PROTOCOL_VERSION, REQUIRED_COMMANDS).check_compatibility().is_ok());
+
+    let err = hello(PROTOCOL_VERSION + 1, REQUIRED_COMMANDS).check_compatibility().unwrap_err();
+    assert!(err.contains("protocol"));
+
+    let err = hello(PROTOCOL_VERSION, &["hello", "init"]).check_compatibility().unwrap_err();
+    assert!(err.contains("send_message"));
+}
+
+#[test]
+fn test_wwebjs_session_auth_update() {
+    let line = r#"{"type":"auth_update","payload":{"type":"session","data":{"name":"RemoteAuth","zip":"UEsDBA=="}}}"#;
+    let event: IpcEvent = serde_json::from_str(line).unwrap();
+    assert!(matches!(event.event, SidecarEvent::AuthUpdate(AuthUpdate::Session(_))));
+
+    let line = r#"{"type":"auth_update","payload":{"type":"session_removed"}}"#;
+    let event: IpcEvent = serde_json::from_str(line).unwrap();
+    assert!(matches!(event.event, SidecarEvent::AuthUpdate(AuthUpdate::SessionRemoved)));
+}
+
+#[test]
+fn test_init_only_sends_present_auth_fields() {
+    let cmd = Command::Init {
+        auth_data: AuthData { session: Some(serde_json::json!({"zip": "UEsDBA=="})), ..Default::default() },
+        mark_online: false,
+    };
+    let json = serde_json::to_value(&cmd).unwrap();
+    let auth = json["payload"]["auth_data"].as_object().unwrap();
+    assert!(auth.contains_key("session"));
+    assert!(!auth.contains_key("creds"));
+    // Always sent, so a ghost-mode `false` is never mistaken for a missing field
+    assert_eq!(json["payload"]["mark_online"], serde_json::json!(false));
+}
+
+#[test]
+fn test_group_list_reply() {
+    use crate::backend::groups::ParticipantRole;
+    use crate::backend::ipc::GroupListAck;
+
+    let payload = serde_json::json!({
+        "groups": [{
+            "id": "team@g.us",
+            "subject": "Team",
+            "description": null,
+            "owner": null,
+            "created_at": 100,
+            "participants": [{ "jid": "alice@s.whatsapp.net", "role": "super_admin" }],
+        }],
+    });
+    let ack: GroupListAck = serde_json::from_value(payload).unwrap();
+    assert_eq!(ack.groups[0].participants[0].role, ParticipantRole::SuperAdmin);
+}
@@ -0,0 +1,80 @@
+use crate::backend::sidecar::adapter_media_file;
+use crate::storage::{MediaCache, SqliteStorage};
+use crate::utils::security::SecurityManager;
+use std::sync::Arc;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_media_cache_roundtrip_is_encrypted_at_rest() {
+    let dir = tempdir().unwrap();
+    let security = Arc::new(SecurityManager::new(dir.path().to_path_buf()));
+    security.init("media_password").unwrap();
+
+    let db_path = dir.path().join("media.db");
+    let storage = Arc::new(SqliteStorage::new(db_path.to_str().unwrap(), None).unwrap());
+    let cache = MediaCache::new(dir.path().join("media"), security, storage);
+
+    let data = b"fake jpeg bytes".to_vec();
+    let record = cache.store("default", "MSG1", "chat1", "image/jpeg", None, 100, &data).await.unwrap();
+    assert_eq!(record.size, data.len() as i64);
+
+    // The cached file must not contain the plaintext
+    let on_disk = std::fs::read(&record.path).unwrap();
+    assert!(!on_disk.windows(data.len()).any(|w| w == data.as_slice()));
+
+    let (loaded, bytes) = cache.load("default", "MSG1").await.unwrap().unwrap();
+    assert_eq!(loaded.mime_type, "image/jpeg");
+    assert_eq!(bytes, data);
+
+    assert!(cache.load("default", "missing").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_media_cache_follows_password_change() {
+    let dir = tempdir().unwrap();
+    let security = Arc::new(SecurityManager::new(dir.path().to_path_buf()));
+    security.init("old_password").unwrap();
+
+    let db_path = dir.path().join("media.db");
+    let storage = Arc::new(SqliteStorage::new(db_path.to_str().unwrap(), None).unwrap());
+    let cache = MediaCache::new(dir.path().join("media"), security.clone(), storage);
+    cache.store("default", "MSG1", "chat1", "image/jpeg", None, 100, b"fake jpeg bytes").await.unwrap();
+
+    // A discarded change leaves the cache as it was
+    let change = security.begin_password_change("old_password", "new_password").unwrap().unwrap();
+    cache.stage_reencryption(&change).await.unwrap();
+    cache.discard_reencryption().await;
+    assert_eq!(std::fs::read_dir(dir.path().join("media")).unwrap().count(), 1);
+    assert_eq!(cache.load("default", "MSG1").await.unwrap().unwrap().1, b"fake jpeg bytes");
+
+    cache.stage_reencryption(&change).await.unwrap();
+    security.finish_password_change(change).unwrap();
+    cache.commit_reencryption().await.unwrap();
+    assert_eq!(std::fs::read_dir(dir.path().join("media")).unwrap().count(), 1);
+    assert_eq!(cache.load("default", "MSG1").await.unwrap().unwrap().1, b"fake jpeg bytes");
+}
+
+#[tokio::test]
+async fn test_sidecar_media_must_stay_in_its_directory() {
+    let media_dir = tempfile::Builder::new().prefix("whaswapp-media-").tempdir().unwrap();
+    let other_dir = tempdir().unwrap();
+    let media_file = media_dir.path().join("a.bin");
+    let other_file = other_dir.path().join("secret");
+    std::fs::write(&media_file, b"media").unwrap();
+    std::fs::write(&other_file, b"secret").unwrap();
+
+    assert_eq!(adapter_media_file(&media_file).await, Some(std::fs::canonicalize(&media_file).unwrap()));
+
+    // Leaving the directory through `..` or a symlink
+    let escaped = media_dir.path().join("..").join(other_dir.path().file_name().unwrap()).join("secret");
+    assert!(adapter_media_file(&escaped).await.is_none());
+    #[cfg(unix)]
+    {
+        let link = media_dir.path().join("b.bin");
+        std::os::unix::fs::symlink(&other_file, &link).unwrap();
+        assert!(adapter_media_file(&link).await.is_none());
+    }
+
+    assert!(adapter_media_file(&other_file).await.is_none());
+    assert!(std::fs::exists(&other_file).unwrap());
+}
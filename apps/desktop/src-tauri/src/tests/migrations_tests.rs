@@ -0,0 +1,93 @@
+use crate::storage::migrations::{latest_version, run_migrations, schema_version, Migration, MigrationError, MIGRATIONS};
+use crate::storage::{MessageStatus, SqliteStorage, Storage};
+use rusqlite::Connection;
+use tempfile::NamedTempFile;
+
+const SCHEMA_V0: &str = include_str!("fixtures/schema_v0.sql");
+
+#[test]
+fn test_migration_versions_are_sequential() {
+    for (i, migration) in MIGRATIONS.iter().enumerate() {
+        assert_eq!(migration.version, i as u32 + 1, "{}", migration.description);
+    }
+}
+
+#[tokio::test]
+async fn test_upgrade_from_version_zero_keeps_data() {
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+    Connection::open(path).unwrap().execute_batch(SCHEMA_V0).unwrap();
+
+    let storage = SqliteStorage::new(path, None).unwrap();
+
+    let messages = storage.get_messages("default", "chat1", 10, 0).await.unwrap();
+    assert_eq!(messages.len(), 2);
+    assert!(messages.iter().all(|m| m.status.is_none() && m.quoted_id.is_none()));
+    assert_eq!(storage.get_chats("default").await.unwrap()[0].name, "Alice");
+    assert_eq!(storage.get_auth_data("creds").await.unwrap().unwrap()["me"], "123");
+
+    // Columns and tables added since v0 are usable
+    storage.save_receipt("default", "chat1", "chat1", &["m2".to_string()], MessageStatus::Read, 300).await.unwrap();
+    let messages = storage.get_messages("default", "chat1", 10, 0).await.unwrap();
+    assert_eq!(messages.iter().find(|m| m.id == "m2").unwrap().status, Some(MessageStatus::Read));
+    assert!(storage.get_outbox("default").await.unwrap().is_empty());
+
+    drop(storage);
+    let conn = Connection::open(path).unwrap();
+    assert_eq!(schema_version(&conn).unwrap(), latest_version());
+}
+
+#[test]
+fn test_migrating_twice_is_a_no_op() {
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+    SqliteStorage::new(path, None).unwrap();
+    SqliteStorage::new(path, None).unwrap();
+
+    let conn = Connection::open(path).unwrap();
+    assert_eq!(schema_version(&conn).unwrap(), latest_version());
+}
+
+#[test]
+fn test_refuses_newer_database() {
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+    let newer = latest_version() + 1;
+    Connection::open(path).unwrap().pragma_update(None, "user_version", newer).unwrap();
+
+    let err = SqliteStorage::new(path, None).err().unwrap();
+    match err.downcast_ref::<MigrationError>() {
+        Some(MigrationError::TooNew { found, supported }) => {
+            assert_eq!(*found, newer);
+            assert_eq!(*supported, latest_version());
+        }
+        other => panic!("expected TooNew, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_failed_step_rolls_back() {
+    fn create_notes(conn: &Connection) -> rusqlite::Result<()> {
+        conn.execute_batch("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT NOT NULL);")
+    }
+    fn broken(conn: &Connection) -> rusqlite::Result<()> {
+        conn.execute_batch("ALTER TABLE notes ADD COLUMN pinned BOOLEAN; INSERT INTO missing_table VALUES (1);")
+    }
+    let migrations = [
+        Migration { version: 1, description: "notes", up: create_notes },
+        Migration { version: 2, description: "broken", up: broken },
+    ];
+
+    let mut conn = Connection::open_in_memory().unwrap();
+    let err = run_migrations(&mut conn, &migrations).unwrap_err();
+    assert!(matches!(err, MigrationError::Step { version: 2, .. }));
+
+    // Step 1 stays applied, nothing of step 2 does
+    assert_eq!(schema_version(&conn).unwrap(), 1);
+    let pinned = conn
+        .prepare("SELECT 1 FROM pragma_table_info('notes') WHERE name = 'pinned'")
+        .unwrap()
+        .exists([])
+        .unwrap();
+    assert!(!pinned);
+}
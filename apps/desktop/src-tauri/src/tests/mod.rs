@@ -1,2 +1,12 @@
 mod security_tests;
 mod db_tests;
+mod events_tests;
+mod media_tests;
+mod ipc_tests;
+mod supervisor_tests;
+mod privacy_tests;
+mod accounts_tests;
+mod migrations_tests;
+mod cipher_tests;
+mod pagination_bench;
+mod storage_bench;
@@ -0,0 +1,78 @@
+//! Paging through a 1M-message chat with OFFSET versus keyset cursors.
+//! Slow to set up, so ignored by default. Run with
+//! `cargo test --release pagination_bench -- --ignored --nocapture`
+
+use crate::storage::{Message, MessageCursor, MessageRange, SqliteStorage, Storage};
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+
+const MESSAGES: i64 = 1_000_000;
+const BATCH: i64 = 10_000;
+const PAGE: usize = 50;
+const RUNS: u32 = 20;
+
+async fn average<F, Fut>(mut run: F) -> Duration
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        run().await;
+    }
+    start.elapsed() / RUNS
+}
+
+#[tokio::test]
+#[ignore]
+async fn bench_offset_vs_keyset_pages() {
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    let start = Instant::now();
+    for batch in 0..MESSAGES / BATCH {
+        let messages = (batch * BATCH..(batch + 1) * BATCH)
+            .map(|i| Message {
+                id: format!("msg{:07}", i),
+                // Most messages in one chat, the rest spread out so the index has to filter
+                chat_id: if i % 10 == 0 { format!("chat{}", i % 1000) } else { "big".to_string() },
+                content: format!("message number {}", i),
+                sender_id: "alice".to_string(),
+                timestamp: i / 2,
+                from_me: i % 3 == 0,
+                revoked_at: None,
+                revoked_by: None,
+                edited_at: None,
+                quoted_id: None,
+                status: None,
+            })
+            .collect();
+        storage.save_history("default", Vec::new(), Vec::new(), messages).await.unwrap();
+    }
+    println!("inserted {} messages in {:?}", MESSAGES, start.elapsed());
+    let storage = &storage;
+
+    for depth in [0usize, 100_000, 800_000] {
+        let offset = average(|| async move {
+            let page = storage.get_messages("default", "big", PAGE, depth).await.unwrap();
+            assert_eq!(page.len(), PAGE);
+        }).await;
+
+        // The cursor a client holds after scrolling `depth` messages back
+        let anchor = storage.get_messages("default", "big", 1, depth).await.unwrap().remove(0);
+        let range = &MessageRange::Before { cursor: MessageCursor::of(&anchor) };
+        let keyset = average(|| async move {
+            let page = storage.get_message_page("default", "big", range, PAGE).await.unwrap();
+            assert_eq!(page.messages.len(), PAGE);
+        }).await;
+
+        let anchor_id = anchor.id.as_str();
+        let around = &MessageRange::Around { message_id: anchor.id.clone() };
+        let jump = average(|| async move {
+            let page = storage.get_message_page("default", "big", around, PAGE).await.unwrap();
+            assert!(page.messages.iter().any(|m| m.id == anchor_id));
+        }).await;
+
+        println!("depth {:>7}: offset {:>10?}  keyset {:>10?}  around {:>10?}", depth, offset, keyset, jump);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::backend::privacy::{PrivacyAction, PrivacyPolicy};
+use crate::storage::{PrivacyRule, SqliteStorage, GLOBAL_PRIVACY_SCOPE};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tempfile::NamedTempFile;
+
+fn rule(scope: &str, read_receipts: Option<bool>, chat_state: Option<bool>, presence: Option<bool>) -> PrivacyRule {
+    PrivacyRule { scope: scope.to_string(), read_receipts, chat_state, presence }
+}
+
+#[test]
+fn test_everything_allowed_without_rules() {
+    let policy = PrivacyPolicy::default();
+    assert!(policy.allows(PrivacyAction::ReadReceipt, Some("123@s.whatsapp.net")));
+    assert!(policy.allows(PrivacyAction::ChatState, None));
+    assert!(policy.allows(PrivacyAction::Presence, None));
+}
+
+#[test]
+fn test_chat_rule_overrides_global() {
+    let policy = PrivacyPolicy::from_rules(vec![
+        rule(GLOBAL_PRIVACY_SCOPE, Some(false), Some(false), Some(false)),
+        rule("friend@s.whatsapp.net", Some(true), None, Some(true)),
+    ]);
+
+    assert!(policy.allows(PrivacyAction::ReadReceipt, Some("friend@s.whatsapp.net")));
+    assert!(!policy.allows(PrivacyAction::ReadReceipt, Some("other@s.whatsapp.net")));
+    // Unset fields inherit from the global rule
+    assert!(!policy.allows(PrivacyAction::ChatState, Some("friend@s.whatsapp.net")));
+    // Online status is global only
+    assert!(!policy.allows(PrivacyAction::Presence, Some("friend@s.whatsapp.net")));
+}
+
+#[tokio::test]
+async fn test_suppressed_stanza_is_never_sent() {
+    let policy = PrivacyPolicy::from_rules(vec![
+        rule(GLOBAL_PRIVACY_SCOPE, Some(false), Some(false), Some(false)),
+    ]);
+    let sent = AtomicUsize::new(0);
+
+    for action in [PrivacyAction::ReadReceipt, PrivacyAction::ChatState, PrivacyAction::Presence] {
+        let delivered = policy.gate(action, Some("123@s.whatsapp.net"), async {
+            sent.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }).await.unwrap();
+        assert!(!delivered);
+    }
+    assert_eq!(sent.load(Ordering::SeqCst), 0);
+
+    let delivered = PrivacyPolicy::default().gate(PrivacyAction::ReadReceipt, None, async {
+        sent.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }).await.unwrap();
+    assert!(delivered);
+    assert_eq!(sent.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_rules_are_persisted() {
+    let file = NamedTempFile::new().unwrap();
+    let storage = SqliteStorage::new(file.path().to_str().unwrap(), None).unwrap();
+
+    let policy = PrivacyPolicy::load(&storage).await.unwrap();
+    policy.set_rule(&storage, rule(GLOBAL_PRIVACY_SCOPE, Some(false), None, None)).await.unwrap();
+    policy.set_rule(&storage, rule("chat@g.us", None, Some(false), None)).await.unwrap();
+    policy.remove_rule(&storage, "chat@g.us").await.unwrap();
+
+    let reloaded = PrivacyPolicy::load(&storage).await.unwrap();
+    assert_eq!(reloaded.rules(), vec![rule(GLOBAL_PRIVACY_SCOPE, Some(false), None, None)]);
+    assert!(!reloaded.allows(PrivacyAction::ReadReceipt, Some("chat@g.us")));
+    assert!(reloaded.allows(PrivacyAction::ChatState, Some("chat@g.us")));
+}
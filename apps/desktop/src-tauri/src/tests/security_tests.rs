@@ -25,3 +25,29 @@ fn test_security_init_and_unlock() {
     let failed = security2.unlock("wrong").unwrap();
     assert!(!failed); // Should return false, not panic
 }
+
+#[test]
+fn test_password_change_moves_data_to_new_key() {
+    let dir = tempdir().unwrap();
+    let security = SecurityManager::new(dir.path().to_path_buf());
+    security.init("old_password").unwrap();
+    let old_key = security.get_master_key().unwrap();
+    let sealed = security.encrypt_data(b"secret").unwrap();
+
+    assert!(security.begin_password_change("wrong", "new_password").unwrap().is_none());
+
+    let change = security.begin_password_change("old_password", "new_password").unwrap().unwrap();
+    assert_ne!(change.key(), old_key.as_slice());
+    let resealed = security.reencrypt_data(&sealed, &change).unwrap();
+    // Nothing switches before the change is finished
+    assert_eq!(security.get_master_key().unwrap(), old_key);
+    assert!(security.decrypt_data(&resealed).is_err());
+
+    security.finish_password_change(change).unwrap();
+    assert_eq!(security.decrypt_data(&resealed).unwrap(), b"secret");
+
+    let reopened = SecurityManager::new(dir.path().to_path_buf());
+    assert!(!reopened.unlock("old_password").unwrap());
+    assert!(reopened.unlock("new_password").unwrap());
+    assert_eq!(reopened.get_master_key(), security.get_master_key());
+}
@@ -0,0 +1,107 @@
+//! UI reads while a history sync is being written, with the reader pool versus every
+//! query going through the one connection. Ignored by default. Run with
+//! `cargo test --release storage_bench -- --ignored --nocapture`
+
+use crate::storage::pool::DEFAULT_READERS;
+use crate::storage::{Chat, Message, MessageRange, SqliteStorage, Storage};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tempfile::NamedTempFile;
+
+const BATCHES: i64 = 50;
+const BATCH: i64 = 2_000;
+const READERS: usize = 4;
+const PAGE: usize = 50;
+
+struct Report {
+    written: Duration,
+    reads: usize,
+    p50: Duration,
+    p99: Duration,
+}
+
+async fn run(readers: usize) -> Report {
+    let file = NamedTempFile::new().unwrap();
+    let storage = Arc::new(SqliteStorage::with_readers(file.path().to_str().unwrap(), None, readers).unwrap());
+    let syncing = Arc::new(AtomicBool::new(true));
+
+    let ui = (0..READERS)
+        .map(|n| {
+            let storage = storage.clone();
+            let syncing = syncing.clone();
+            tokio::spawn(async move {
+                let chat_id = format!("chat{}", n);
+                let mut latencies = Vec::new();
+                while syncing.load(Ordering::Relaxed) {
+                    let start = Instant::now();
+                    storage.get_message_page("default", &chat_id, &MessageRange::Latest, PAGE).await.unwrap();
+                    storage.get_chats("default").await.unwrap();
+                    latencies.push(start.elapsed());
+                }
+                latencies
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let start = Instant::now();
+    for batch in 0..BATCHES {
+        let messages = (batch * BATCH..(batch + 1) * BATCH)
+            .map(|i| Message {
+                id: format!("msg{:07}", i),
+                chat_id: format!("chat{}", i % 100),
+                content: format!("history message number {}", i),
+                sender_id: "alice".to_string(),
+                timestamp: i,
+                from_me: i % 3 == 0,
+                revoked_at: None,
+                revoked_by: None,
+                edited_at: None,
+                quoted_id: None,
+                status: None,
+            })
+            .collect();
+        let chats = (0..100)
+            .map(|c| Chat {
+                id: format!("chat{}", c),
+                name: format!("Chat {}", c),
+                unread_count: 0,
+                last_message_timestamp: (batch + 1) * BATCH,
+            })
+            .collect();
+        storage.save_history("default", chats, Vec::new(), messages).await.unwrap();
+    }
+    let written = start.elapsed();
+    syncing.store(false, Ordering::Relaxed);
+
+    let mut latencies = Vec::new();
+    for reader in ui {
+        latencies.extend(reader.await.unwrap());
+    }
+    latencies.sort();
+    let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+
+    Report {
+        written,
+        reads: latencies.len(),
+        p50: percentile(50),
+        p99: percentile(99),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[ignore]
+async fn bench_reads_during_history_sync() {
+    for (label, readers) in [("single connection", 0), ("reader pool", DEFAULT_READERS)] {
+        let report = run(readers).await;
+        let seconds = report.written.as_secs_f64();
+        println!(
+            "{:<17}: {:>8.0} msgs/s written  {:>7.0} reads/s  read p50 {:>10?}  p99 {:>10?}",
+            label,
+            (BATCHES * BATCH) as f64 / seconds,
+            report.reads as f64 / seconds,
+            report.p50,
+            report.p99,
+        );
+    }
+}
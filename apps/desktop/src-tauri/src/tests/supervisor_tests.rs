@@ -0,0 +1,55 @@
+use crate::backend::supervisor::{HealthTracker, RestartPolicy, SupervisorState};
+use std::time::Duration;
+
+#[test]
+fn test_backoff_is_exponential_capped_and_jittered() {
+    let policy = RestartPolicy::default();
+
+    for attempt in 1..=10 {
+        let expected = policy.base_delay.saturating_mul(2u32.pow(attempt - 1)).min(policy.max_delay);
+        let delay = policy.backoff(attempt);
+        assert!(delay >= expected, "attempt {}: {:?} < {:?}", attempt, delay, expected);
+        assert!(delay <= expected.mul_f64(1.25), "attempt {}: {:?} too large", attempt, delay);
+    }
+}
+
+#[test]
+fn test_crash_loop_breaker_trips_after_rapid_failures() {
+    let policy = RestartPolicy {
+        base_delay: Duration::from_millis(1),
+        max_rapid_failures: 3,
+        ..RestartPolicy::default()
+    };
+    let mut health = HealthTracker::new(policy);
+
+    health.process_started();
+    assert!(health.process_exited(Some(1), None).is_some());
+    health.process_started();
+    assert!(health.process_exited(Some(1), None).is_some());
+    health.process_started();
+    assert!(health.process_exited(Some(1), Some("boom".to_string())).is_none());
+
+    let snapshot = health.snapshot();
+    assert_eq!(snapshot.state, SupervisorState::CrashLoop);
+    assert_eq!(snapshot.restarts, 2);
+    assert_eq!(snapshot.last_exit_code, Some(1));
+    assert_eq!(snapshot.last_error.as_deref(), Some("boom"));
+}
+
+#[test]
+fn test_long_uptime_resets_failure_streak() {
+    let policy = RestartPolicy {
+        base_delay: Duration::from_millis(1),
+        rapid_failure_window: Duration::ZERO,
+        max_rapid_failures: 2,
+        ..RestartPolicy::default()
+    };
+    let mut health = HealthTracker::new(policy);
+
+    // With a zero window every exit counts as "after a healthy run"
+    for _ in 0..5 {
+        health.process_started();
+        assert!(health.process_exited(Some(0), None).is_some());
+    }
+    assert_eq!(health.snapshot().consecutive_failures, 1);
+}
@@ -48,16 +48,7 @@ impl SecurityManager {
     }
 
     pub fn init(&self, password: &str) -> anyhow::Result<()> {
-        let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-        let password_hash = argon2.hash_password(password.as_bytes(), &salt)
-            .map_err(|e| anyhow::anyhow!("Hashing failed: {}", e))?
-            .to_string();
-
-        let config = SecurityConfig {
-            password_hash,
-            salt: salt.as_str().to_string(),
-        };
+        let config = hash_password(password)?;
 
         let json = serde_json::to_string_pretty(&config)?;
         fs::write(self.config_path(), json)?;
@@ -69,14 +60,8 @@ impl SecurityManager {
     }
 
     pub fn unlock(&self, password: &str) -> anyhow::Result<bool> {
-        let config_data = fs::read_to_string(self.config_path())?;
-        let config: SecurityConfig = serde_json::from_str(&config_data)?;
-
-        let parsed_hash = PasswordHash::new(&config.password_hash)
-            .map_err(|e| anyhow::anyhow!("Invalid hash format: {}", e))?;
-
-        let argon2 = Argon2::default();
-        if argon2.verify_password(password.as_bytes(), &parsed_hash).is_ok() {
+        let config = self.load_config()?;
+        if verify_password(&config, password)? {
             self.derive_and_store_key(password, &config.salt)?;
             Ok(true)
         } else {
@@ -84,34 +69,41 @@ impl SecurityManager {
         }
     }
 
-    fn derive_and_store_key(&self, password: &str, salt: &str) -> anyhow::Result<()> {
-        // We use the password and the stored salt to derive a stable 32-byte key
-        // Note: In a production app, we might use a separate KDF for the key vs the auth hash
-        // to prevent hash cracking leading to key compromise, but Argon2 is strong enough for both here.
-
-        // Actually, let's use a specific KDF for the key material to be safe.
-        // We'll use Argon2 with a specific output length (32 bytes) for the key.
-        let mut key_material = [0u8; 32];
-        let salt_bytes = salt.as_bytes();
-
-        // Custom params for key derivation (slower is better for keys)
-        let params = argon2::Params::new(
-            argon2::Params::DEFAULT_M_COST,
-            argon2::Params::DEFAULT_T_COST,
-            argon2::Params::DEFAULT_P_COST,
-            Some(32) // Output length
-        ).map_err(|e| anyhow::anyhow!("Argon2 params error: {}", e))?;
+    /// Checks `current` and derives the key for `new` without using it yet. Returns `None`
+    /// if `current` is wrong. Data under the old key is carried over with `reencrypt_data`
+    /// before `finish_password_change` makes the new password current
+    pub fn begin_password_change(&self, current: &str, new: &str) -> anyhow::Result<Option<PasswordChange>> {
+        if !verify_password(&self.load_config()?, current)? {
+            return Ok(None);
+        }
+        let config = hash_password(new)?;
+        let key = derive_key(new, &config.salt)?;
+        Ok(Some(PasswordChange { config, key }))
+    }
 
-        let argon2_kdf = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+    /// Re-encrypts a buffer produced by `encrypt_data` under the key of a pending change
+    pub fn reencrypt_data(&self, data: &[u8], change: &PasswordChange) -> anyhow::Result<Vec<u8>> {
+        encrypt_with(&change.key, &self.decrypt_data(data)?)
+    }
 
-        argon2_kdf.hash_password_into(password.as_bytes(), salt_bytes, &mut key_material)
-             .map_err(|e| anyhow::anyhow!("Key derivation failed: {}", e))?;
+    /// Saves the new password and switches to its key
+    pub fn finish_password_change(&self, change: PasswordChange) -> anyhow::Result<()> {
+        // Written aside and renamed, so the old password stays valid until the new one is complete
+        let staged = self.config_path().with_extension("json.new");
+        fs::write(&staged, serde_json::to_string_pretty(&change.config)?)?;
+        fs::rename(&staged, self.config_path())?;
 
-        *self.master_key.lock().unwrap() = Some(key_material.to_vec());
+        *self.master_key.lock().unwrap() = Some(change.key);
+        Ok(())
+    }
 
-        // Zeroize stack memory
-        key_material.zeroize();
+    fn load_config(&self) -> anyhow::Result<SecurityConfig> {
+        let config_data = fs::read_to_string(self.config_path())?;
+        Ok(serde_json::from_str(&config_data)?)
+    }
 
+    fn derive_and_store_key(&self, password: &str, salt: &str) -> anyhow::Result<()> {
+        *self.master_key.lock().unwrap() = Some(derive_key(password, salt)?);
         Ok(())
     }
 
@@ -121,34 +113,19 @@ impl SecurityManager {
 
     // --- Encryption Helpers ---
 
-    #[allow(dead_code)]
-    pub fn encrypt_file(&self, input_path: &PathBuf, output_path: &PathBuf) -> anyhow::Result<()> {
-        let key_vec = self.get_master_key().ok_or(anyhow::anyhow!("Vault locked"))?;
-        let key = Key::<Aes256Gcm>::from_slice(&key_vec);
-        let cipher = Aes256Gcm::new(key);
-
-        let nonce = Aes256Gcm::generate_nonce(&mut OsRng); // 96-bits; unique per message
-        let plaintext = fs::read(input_path)?;
-
-        let ciphertext = cipher.encrypt(&nonce, plaintext.as_ref())
-            .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
-
-        // Format: [Nonce (12 bytes)][Ciphertext]
-        let mut final_data = Vec::new();
-        final_data.extend_from_slice(nonce.as_slice());
-        final_data.extend_from_slice(&ciphertext);
-
-        fs::write(output_path, final_data)?;
-        Ok(())
+    /// Encrypts a buffer with the vault key.
+    /// Format: [Nonce (12 bytes)][Ciphertext]
+    pub fn encrypt_data(&self, plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let key = self.get_master_key().ok_or(anyhow::anyhow!("Vault locked"))?;
+        encrypt_with(&key, plaintext)
     }
 
-    #[allow(dead_code)]
-    pub fn decrypt_file(&self, input_path: &PathBuf, output_path: &PathBuf) -> anyhow::Result<()> {
+    /// Decrypts a buffer produced by `encrypt_data`.
+    pub fn decrypt_data(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
         let key_vec = self.get_master_key().ok_or(anyhow::anyhow!("Vault locked"))?;
         let key = Key::<Aes256Gcm>::from_slice(&key_vec);
         let cipher = Aes256Gcm::new(key);
 
-        let data = fs::read(input_path)?;
         if data.len() < 12 {
             return Err(anyhow::anyhow!("File too short"));
         }
@@ -156,10 +133,98 @@ impl SecurityManager {
         let nonce = Nonce::from_slice(&data[0..12]);
         let ciphertext = &data[12..];
 
-        let plaintext = cipher.decrypt(nonce, ciphertext)
-            .map_err(|e| anyhow::anyhow!("Decryption failed: {}", e))?;
+        cipher.decrypt(nonce, ciphertext)
+            .map_err(|e| anyhow::anyhow!("Decryption failed: {}", e))
+    }
 
-        fs::write(output_path, plaintext)?;
+    #[allow(dead_code)]
+    pub fn encrypt_file(&self, input_path: &PathBuf, output_path: &PathBuf) -> anyhow::Result<()> {
+        let plaintext = fs::read(input_path)?;
+        fs::write(output_path, self.encrypt_data(&plaintext)?)?;
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn decrypt_file(&self, input_path: &PathBuf, output_path: &PathBuf) -> anyhow::Result<()> {
+        let data = fs::read(input_path)?;
+        fs::write(output_path, self.decrypt_data(&data)?)?;
         Ok(())
     }
 }
+
+/// A new password whose key is derived but not in use yet, see `begin_password_change`
+pub struct PasswordChange {
+    config: SecurityConfig,
+    key: Vec<u8>,
+}
+
+impl PasswordChange {
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+fn hash_password(password: &str) -> anyhow::Result<SecurityConfig> {
+    let salt = SaltString::generate(&mut OsRng);
+    let argon2 = Argon2::default();
+    let password_hash = argon2.hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Hashing failed: {}", e))?
+        .to_string();
+
+    Ok(SecurityConfig {
+        password_hash,
+        salt: salt.as_str().to_string(),
+    })
+}
+
+fn verify_password(config: &SecurityConfig, password: &str) -> anyhow::Result<bool> {
+    let parsed_hash = PasswordHash::new(&config.password_hash)
+        .map_err(|e| anyhow::anyhow!("Invalid hash format: {}", e))?;
+
+    Ok(Argon2::default().verify_password(password.as_bytes(), &parsed_hash).is_ok())
+}
+
+fn derive_key(password: &str, salt: &str) -> anyhow::Result<Vec<u8>> {
+    // We use the password and the stored salt to derive a stable 32-byte key
+    // Note: In a production app, we might use a separate KDF for the key vs the auth hash
+    // to prevent hash cracking leading to key compromise, but Argon2 is strong enough for both here.
+
+    // Actually, let's use a specific KDF for the key material to be safe.
+    // We'll use Argon2 with a specific output length (32 bytes) for the key.
+    let mut key_material = [0u8; 32];
+    let salt_bytes = salt.as_bytes();
+
+    // Custom params for key derivation (slower is better for keys)
+    let params = argon2::Params::new(
+        argon2::Params::DEFAULT_M_COST,
+        argon2::Params::DEFAULT_T_COST,
+        argon2::Params::DEFAULT_P_COST,
+        Some(32) // Output length
+    ).map_err(|e| anyhow::anyhow!("Argon2 params error: {}", e))?;
+
+    let argon2_kdf = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+
+    argon2_kdf.hash_password_into(password.as_bytes(), salt_bytes, &mut key_material)
+         .map_err(|e| anyhow::anyhow!("Key derivation failed: {}", e))?;
+
+    let key = key_material.to_vec();
+
+    // Zeroize stack memory
+    key_material.zeroize();
+
+    Ok(key)
+}
+
+fn encrypt_with(key: &[u8], plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng); // 96-bits; unique per message
+
+    let ciphertext = cipher.encrypt(&nonce, plaintext)
+        .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
+
+    let mut final_data = Vec::with_capacity(nonce.len() + ciphertext.len());
+    final_data.extend_from_slice(nonce.as_slice());
+    final_data.extend_from_slice(&ciphertext);
+    Ok(final_data)
+}
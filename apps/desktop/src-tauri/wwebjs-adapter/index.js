@@ -0,0 +1,552 @@
+import wwebjs from 'whatsapp-web.js';
+import readline from 'readline';
+import { mkdtemp, readFile, rm, writeFile } from 'fs/promises';
+import { tmpdir } from 'os';
+import { basename, join } from 'path';
+import { createRequire } from 'module';
+
+const { Client, RemoteAuth, MessageMedia, MessageAck } = wwebjs;
+
+// Must match PROTOCOL_VERSION in src/backend/ipc.rs
+const PROTOCOL_VERSION = 2;
+// Must match EXIT_CODE_LOGGED_OUT in src/backend/ipc.rs
+const EXIT_CODE_LOGGED_OUT = 3;
+const { version: ADAPTER_VERSION } = createRequire(import.meta.url)('./package.json');
+
+// RemoteAuth refuses intervals below one minute
+const BACKUP_SYNC_INTERVAL_MS = 5 * 60 * 1000;
+
+// State
+let client = null;
+let dataPath = null;
+// Private (0700) directory for decrypted media waiting to be cached by Rust
+let mediaDir = null;
+
+// IPC Helper
+// `id` echoes the id of the command being answered (ack/error replies only)
+function sendEvent(type, payload, id) {
+    console.log(JSON.stringify({ type, payload, id }));
+}
+
+// RemoteAuth store backed by Rust's auth_store.
+// The browser profile is zipped by RemoteAuth; we only ship the archive across IPC.
+const createIPCStore = (initialSession) => {
+    let session = initialSession || null;
+
+    return {
+        sessionExists: async () => !!session?.zip,
+        save: async ({ session: name }) => {
+            const zip = await readFile(`${name}.zip`);
+            session = { name: basename(name), zip: zip.toString('base64') };
+            sendEvent('auth_update', { type: 'session', data: session });
+        },
+        extract: async ({ path }) => {
+            if (session?.zip) {
+                await writeFile(path, Buffer.from(session.zip, 'base64'));
+            }
+        },
+        delete: async () => {
+            session = null;
+            sendEvent('auth_update', { type: 'session_removed' });
+        },
+    };
+};
+
+// whatsapp-web.js addresses users as `<number>@c.us`; the core uses `@s.whatsapp.net`
+function toCoreJid(jid) {
+    const value = typeof jid === 'string' ? jid : jid?._serialized;
+    return value ? value.replace(/@c\.us$/, '@s.whatsapp.net') : value;
+}
+
+function toWebJid(jid) {
+    return jid.replace(/@s\.whatsapp\.net$/, '@c.us');
+}
+
+// WhatsApp Web addresses messages as `<fromMe>_<chat>_<id>[_<author>]`, the author only in groups
+function serializedId(chatId, messageId, fromMe, senderId) {
+    let serialized = `${!!fromMe}_${toWebJid(chatId)}_${messageId}`;
+    if (senderId && !fromMe && chatId.endsWith('@g.us')) {
+        serialized += `_${toWebJid(senderId)}`;
+    }
+    return serialized;
+}
+
+// Command Handlers
+async function handleHello(payload, id) {
+    sendEvent('hello', {
+        protocol_version: PROTOCOL_VERSION,
+        adapter: 'wwebjs',
+        adapter_version: ADAPTER_VERSION,
+        commands: Object.keys(handlers),
+    }, id);
+}
+
+async function handleInit(payload) {
+    await startClient(payload.auth_data?.session, !!payload.mark_online);
+}
+
+// Sent messages reach Rust through `message_create`, like those sent from the phone
+async function handleSendMessage(payload, id) {
+    if (!client) return sendEvent('error', { message: 'Not connected' }, id);
+    const { jid, content, quoted } = payload;
+    try {
+        // Read receipts are Rust's call (ghost mode), so never mark the chat seen implicitly
+        const options = { sendSeen: false };
+        if (quoted) {
+            options.quotedMessageId = serializedId(jid, quoted.message_id, quoted.from_me, quoted.sender_id);
+        }
+        const sent = await client.sendMessage(toWebJid(jid), content, options);
+        sendEvent('ack', { status: 'sent', jid, message_id: sent.id.id }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleSendMedia(payload, id) {
+    if (!client) return sendEvent('error', { message: 'Not connected' }, id);
+    const { jid, path, kind, mime_type, caption } = payload;
+    try {
+        const media = MessageMedia.fromFilePath(path);
+        media.mimetype = mime_type;
+        const sent = await client.sendMessage(toWebJid(jid), media, {
+            caption: caption ?? undefined,
+            sendMediaAsDocument: kind === 'document',
+            sendSeen: false,
+        });
+        sendEvent('ack', { status: 'sent', jid, message_id: sent.id.id }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+// Our own reactions come back through `message_reaction`. An empty emoji removes ours.
+async function handleSendReaction(payload, id) {
+    if (!client) return sendEvent('error', { message: 'Not connected' }, id);
+    const { chat_id, message_id, from_me, sender_id, emoji } = payload;
+    try {
+        const msg = await client.getMessageById(serializedId(chat_id, message_id, from_me, sender_id));
+        if (!msg) return sendEvent('error', { message: `Message not found: ${message_id}` }, id);
+        await msg.react(emoji);
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+// Receipts, chatstate and presence are only sent when Rust's privacy policy allows them.
+// WhatsApp Web can only mark a whole chat as seen, not individual messages.
+async function handleSendReceipt(payload, id) {
+    if (!client) return sendEvent('error', { message: 'Not connected' }, id);
+    try {
+        const chat = await client.getChatById(toWebJid(payload.chat_id));
+        await chat.sendSeen();
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleSendChatState(payload, id) {
+    if (!client) return sendEvent('error', { message: 'Not connected' }, id);
+    const { chat_id, state } = payload;
+    try {
+        const chat = await client.getChatById(toWebJid(chat_id));
+        switch (state) {
+            case 'composing': await chat.sendStateTyping(); break;
+            case 'recording': await chat.sendStateRecording(); break;
+            case 'paused': await chat.clearState(); break;
+            default: return sendEvent('error', { message: `Not a chat state: ${state}` }, id);
+        }
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleSendPresence(payload, id) {
+    if (!client) return sendEvent('error', { message: 'Not connected' }, id);
+    const { state } = payload;
+    try {
+        switch (state) {
+            case 'available': await client.sendPresenceAvailable(); break;
+            case 'unavailable': await client.sendPresenceUnavailable(); break;
+            default: return sendEvent('error', { message: `Not a presence state: ${state}` }, id);
+        }
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+// Group management. Metadata replies are normalised to GroupMetadata in src/backend/groups.rs
+async function getGroupChat(groupId) {
+    const chat = await client.getChatById(toWebJid(groupId));
+    if (!chat?.isGroup) throw new Error(`Not a group: ${groupId}`);
+    return chat;
+}
+
+async function handleGroupList(payload, id) {
+    if (!client) return sendEvent('error', { message: 'Not connected' }, id);
+    try {
+        const chats = await client.getChats();
+        sendEvent('ack', { groups: chats.filter((chat) => chat.isGroup).map(normalizeGroup) }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleGroupMetadata(payload, id) {
+    if (!client) return sendEvent('error', { message: 'Not connected' }, id);
+    try {
+        sendEvent('ack', normalizeGroup(await getGroupChat(payload.group_id)), id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleGroupCreate(payload, id) {
+    if (!client) return sendEvent('error', { message: 'Not connected' }, id);
+    const { subject, participants } = payload;
+    try {
+        const created = await client.createGroup(subject, participants.map(toWebJid));
+        if (typeof created === 'string') throw new Error(created);
+        sendEvent('ack', normalizeGroup(await getGroupChat(created.gid._serialized)), id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleGroupUpdateParticipants(payload, id) {
+    if (!client) return sendEvent('error', { message: 'Not connected' }, id);
+    const { group_id, participants, action } = payload;
+    try {
+        const chat = await getGroupChat(group_id);
+        const ids = participants.map(toWebJid);
+        switch (action) {
+            case 'add': await chat.addParticipants(ids); break;
+            case 'remove': await chat.removeParticipants(ids); break;
+            case 'promote': await chat.promoteParticipants(ids); break;
+            case 'demote': await chat.demoteParticipants(ids); break;
+            default: return sendEvent('error', { message: `Not a participant action: ${action}` }, id);
+        }
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleGroupUpdateSubject(payload, id) {
+    if (!client) return sendEvent('error', { message: 'Not connected' }, id);
+    try {
+        const chat = await getGroupChat(payload.group_id);
+        if (!await chat.setSubject(payload.subject)) throw new Error('Subject change was rejected');
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleGroupUpdateDescription(payload, id) {
+    if (!client) return sendEvent('error', { message: 'Not connected' }, id);
+    try {
+        const chat = await getGroupChat(payload.group_id);
+        if (!await chat.setDescription(payload.description ?? '')) throw new Error('Description change was rejected');
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+async function handleGroupLeave(payload, id) {
+    if (!client) return sendEvent('error', { message: 'Not connected' }, id);
+    try {
+        const chat = await getGroupChat(payload.group_id);
+        await chat.leave();
+        sendEvent('ack', { status: 'sent' }, id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, id);
+    }
+}
+
+// WhatsApp Web exposes no presence events, so there is nothing to subscribe to
+async function handlePresenceSubscribe(payload, id) {
+    sendEvent('error', { message: 'Presence updates are not available with whatsapp-web.js' }, id);
+}
+
+async function handleDisconnect() {
+    if (client) {
+        await client.destroy().catch(() => {});
+        client = null;
+    }
+    await cleanup();
+    process.exit(0); // Exit to let Rust restart us
+}
+
+async function cleanup() {
+    if (dataPath) {
+        await rm(dataPath, { recursive: true, force: true }).catch(() => {});
+        dataPath = null;
+    }
+    // Media files not yet picked up by Rust are lost with the process anyway
+    if (mediaDir) {
+        const dir = await mediaDir.catch(() => null);
+        mediaDir = null;
+        if (dir) await rm(dir, { recursive: true, force: true }).catch(() => {});
+    }
+}
+
+// Main Client Logic
+async function startClient(initialSession, markOnline) {
+    // The profile is rebuilt from the stored archive on every start
+    dataPath = await mkdtemp(join(tmpdir(), 'whaswapp-wwebjs-'));
+
+    client = new Client({
+        authStrategy: new RemoteAuth({
+            store: createIPCStore(initialSession),
+            dataPath,
+            backupSyncIntervalMs: BACKUP_SYNC_INTERVAL_MS,
+        }),
+        puppeteer: {
+            headless: true,
+            args: ['--no-sandbox', '--disable-setuid-sandbox'],
+        },
+    });
+
+    client.on('qr', (qr) => {
+        sendEvent('qr_code', qr);
+    });
+
+    client.on('authenticated', () => {
+        sendEvent('connection_status', 'connecting');
+    });
+
+    client.on('ready', async () => {
+        // WhatsApp Web goes online by itself; take that back under ghost mode
+        if (!markOnline) {
+            await client.sendPresenceUnavailable().catch(() => {});
+        }
+        sendEvent('connection_status', 'connected');
+    });
+
+    client.on('auth_failure', async (message) => {
+        sendEvent('connection_status', 'disconnected');
+        sendEvent('auth_failure', message || 'auth_failure');
+        await cleanup();
+        process.exit(EXIT_CODE_LOGGED_OUT);
+    });
+
+    client.on('disconnected', async (reason) => {
+        await cleanup();
+        if (reason === 'LOGOUT') {
+            sendEvent('connection_status', 'disconnected');
+            sendEvent('auth_failure', 'logged_out');
+            process.exit(EXIT_CODE_LOGGED_OUT);
+        } else {
+            // Let Rust restart us with the last saved session
+            sendEvent('connection_status', 'disconnected_reconnecting');
+            process.exit(0);
+        }
+    });
+
+    // Fires for incoming messages and for our own, wherever they were sent from
+    client.on('message_create', (msg) => {
+        if (msg.isStatus) return;
+        const normalized = normalizeMessage(msg);
+        sendEvent('message', normalized);
+        if (msg.hasMedia && ['image', 'video', 'audio', 'document'].includes(normalized.content.kind)) {
+            forwardMedia(msg, normalized);
+        }
+    });
+
+    client.on('message_revoke_everyone', (after, before) => {
+        sendEvent('revoke', {
+            chat_id: toCoreJid(after.id.remote),
+            message_id: after.id.id,
+            revoked_by: senderOf(after),
+            timestamp: Math.floor(Date.now() / 1000),
+        });
+    });
+
+    // Only the text (or caption) of a message can be edited
+    client.on('message_edit', (msg, newBody) => {
+        const content = extractContent(msg);
+        if (content.kind === 'text') {
+            content.text = newBody || '';
+        } else if ('caption' in content) {
+            content.caption = newBody || null;
+        }
+        sendEvent('edit', {
+            chat_id: toCoreJid(msg.id.remote),
+            message_id: msg.id.id,
+            edited_by: senderOf(msg),
+            content,
+            timestamp: Math.floor(Date.now() / 1000),
+        });
+    });
+
+    client.on('message_reaction', (reaction) => {
+        sendEvent('reaction', {
+            chat_id: toCoreJid(reaction.msgId.remote),
+            message_id: reaction.msgId.id,
+            sender_id: toCoreJid(reaction.senderId),
+            // An empty reaction is a removal
+            emoji: reaction.reaction || null,
+            timestamp: reaction.timestamp || Math.floor(Date.now() / 1000),
+        });
+    });
+
+    // Delivery/read updates for our own messages. Group acks are aggregated by
+    // WhatsApp Web, so they are reported against the chat rather than a participant.
+    client.on('message_ack', (msg, ack) => {
+        const kind = receiptKindFromAck(ack);
+        if (!kind || !msg.fromMe) return;
+        const chatId = toCoreJid(msg.id.remote);
+        sendEvent('receipt', {
+            chat_id: chatId,
+            sender_id: chatId,
+            message_ids: [msg.id.id],
+            kind,
+            timestamp: Math.floor(Date.now() / 1000),
+        });
+    });
+
+    await client.initialize();
+}
+
+// Message normalisation (mirrors ProviderEvent payloads on the Rust side)
+function senderOf(msg) {
+    if (msg.fromMe) return toCoreJid(client?.info?.wid) || 'me';
+    return toCoreJid(msg.author || msg.from);
+}
+
+function normalizeGroup(chat) {
+    return {
+        id: toCoreJid(chat.id),
+        subject: chat.name || '',
+        description: chat.description || null,
+        owner: toCoreJid(chat.owner) || null,
+        created_at: chat.createdAt ? Math.floor(chat.createdAt.getTime() / 1000) : null,
+        participants: (chat.participants || []).map((p) => ({
+            jid: toCoreJid(p.id),
+            role: p.isSuperAdmin ? 'super_admin' : p.isAdmin ? 'admin' : 'member',
+        })),
+    };
+}
+
+function receiptKindFromAck(ack) {
+    switch (ack) {
+        case MessageAck.ACK_DEVICE: return 'delivered';
+        case MessageAck.ACK_READ: return 'read';
+        case MessageAck.ACK_PLAYED: return 'played';
+        default: return null;
+    }
+}
+
+function extractContent(msg) {
+    const mimeType = msg._data?.mimetype || null;
+    switch (msg.type) {
+        case 'chat':
+            return { kind: 'text', text: msg.body || '' };
+        case 'image':
+            return { kind: 'image', caption: msg.body || null, mime_type: mimeType };
+        case 'video':
+            return { kind: 'video', caption: msg.body || null, mime_type: mimeType };
+        case 'audio':
+        case 'ptt':
+            return { kind: 'audio', mime_type: mimeType, voice_note: msg.type === 'ptt' };
+        case 'document':
+            return { kind: 'document', file_name: msg._data?.filename || null, mime_type: mimeType };
+        case 'sticker':
+            return { kind: 'sticker' };
+        default:
+            return { kind: 'unsupported' };
+    }
+}
+
+// Writes the downloaded media to a temp file and hands its path to Rust, which
+// encrypts it into the media cache and deletes the file.
+// whatsapp-web.js only returns media as base64, so the buffer is decoded here once
+async function forwardMedia(msg, normalized) {
+    mediaDir ??= mkdtemp(join(tmpdir(), 'whaswapp-media-'));
+    const path = join(await mediaDir, `${Buffer.from(normalized.id).toString('hex')}.bin`);
+    try {
+        const media = await msg.downloadMedia();
+        if (!media) return;
+        await writeFile(path, Buffer.from(media.data, 'base64'), { mode: 0o600 });
+        sendEvent('media', {
+            message_id: normalized.id,
+            chat_id: normalized.chat_id,
+            mime_type: media.mimetype || normalized.content.mime_type || 'application/octet-stream',
+            file_name: media.filename || normalized.content.file_name || null,
+            timestamp: normalized.timestamp,
+            path,
+        });
+    } catch (e) {
+        await rm(path, { force: true }).catch(() => {});
+        sendEvent('error', { message: `Media download failed for ${normalized.id}: ${e.message}` });
+    }
+}
+
+function normalizeMessage(msg) {
+    return {
+        id: msg.id.id,
+        chat_id: toCoreJid(msg.id.remote),
+        sender_id: senderOf(msg),
+        sender_name: msg._data?.notifyName || null,
+        timestamp: msg.timestamp || 0,
+        from_me: !!msg.fromMe,
+        content: extractContent(msg),
+        quoted: msg.hasQuotedMsg ? {
+            id: msg._data?.quotedStanzaID,
+            sender_id: toCoreJid(msg._data?.quotedParticipant) || null,
+            snippet: msg._data?.quotedMsg?.body || msg._data?.quotedMsg?.caption || '',
+        } : null,
+    };
+}
+
+// Command table; its keys are advertised to Rust in the hello reply
+const handlers = {
+    hello: handleHello,
+    init: handleInit,
+    send_message: handleSendMessage,
+    send_media: handleSendMedia,
+    send_reaction: handleSendReaction,
+    send_receipt: handleSendReceipt,
+    send_chat_state: handleSendChatState,
+    send_presence: handleSendPresence,
+    presence_subscribe: handlePresenceSubscribe,
+    group_list: handleGroupList,
+    group_metadata: handleGroupMetadata,
+    group_create: handleGroupCreate,
+    group_update_participants: handleGroupUpdateParticipants,
+    group_update_subject: handleGroupUpdateSubject,
+    group_update_description: handleGroupUpdateDescription,
+    group_leave: handleGroupLeave,
+    disconnect: handleDisconnect,
+};
+
+// Stdin Reader (IPC)
+const rl = readline.createInterface({
+    input: process.stdin,
+    output: process.stdout,
+    terminal: false
+});
+
+rl.on('line', async (line) => {
+    let command;
+    try {
+        if (!line.trim()) return;
+        command = JSON.parse(line);
+        const handler = handlers[command.type];
+        if (!handler) {
+            sendEvent('error', { message: `Unknown command: ${command.type}` }, command.id);
+            return;
+        }
+        await handler(command.payload, command.id);
+    } catch (e) {
+        sendEvent('error', { message: e.message }, command?.id);
+    }
+});
+
+// Keep process alive
+process.stdin.resume();
@@ -1,7 +1,6 @@
 import { useEffect } from 'react';
 import { useAuthStore } from './stores/authStore';
-import { initIPC } from './services/ipc';
-import { invoke } from '@tauri-apps/api/core';
+import { initIPC, resetSession } from './services/ipc';
 import { QRCodeSVG } from 'qrcode.react';
 import ChatList from './components/ChatList';
 import ChatWindow from './components/ChatWindow';
@@ -16,7 +15,7 @@ function App() {
 
   const handleResetSession = async () => {
     try {
-      await invoke('reset_session');
+      await resetSession();
       // Session temizlendi, yeniden bağlanmayı dene
       window.location.reload();
     } catch (error) {
@@ -2,7 +2,7 @@
 import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import { useAuthStore } from '../stores/authStore';
-import { useChatStore } from '../stores/chatStore';
+import { outboxKey, useChatStore, type OutboxEntry, type ScheduledMessage } from '../stores/chatStore';
 import { useTerminalStore } from '../stores/terminalStore';
 
 // Helper function to get timestamp with milliseconds
@@ -57,12 +57,17 @@ console.info = (...args: any[]) => {
   });
 };
 
+// Session commands act on the account shown in the UI
+const activeAccount = () => useAuthStore.getState().accountId;
+
 export const initIPC = async () => {
   console.log('Initializing Native IPC...');
 
   // Listen for generic backend events from our sidecar provider
   await listen('backend-event', (event: any) => {
     const payload = event.payload;
+    // Other accounts keep syncing into storage; the UI reloads from there when switching
+    if (payload.account_id !== activeAccount()) return;
     // console.log('Backend Event:', payload); // Verbose
 
     if (payload.type === 'qr') {
@@ -78,36 +83,157 @@ export const initIPC = async () => {
              useAuthStore.getState().setStatus('disconnected');
         }
     }
+    else if (payload.type === 'error') {
+        console.error('Backend error:', payload.payload.message);
+        useAuthStore.getState().setStatus('disconnected');
+    }
     else if (payload.type === 'message') {
         const msg = payload.payload;
         console.log('Message received:', msg);
         // Normalize message for store
         const normalized = {
-            key: msg.key,
-            fromMe: msg.key.fromMe,
-            content: msg.message,
-            timestamp: msg.messageTimestamp,
+            key: { fromMe: msg.from_me, remoteJid: msg.chat_id, id: msg.id, participant: msg.sender_id },
+            fromMe: msg.from_me,
+            content: msg.content.kind === 'text' ? { conversation: msg.content.text } : msg.content,
+            timestamp: msg.timestamp,
+            quoted: msg.quoted ?? null,
+            status: msg.from_me ? 'sent' : null,
         };
         useChatStore.getState().addMessage(normalized);
     }
+    else if (payload.type === 'message_revoked') {
+        const { message_id, revoked_by, timestamp } = payload.payload;
+        useChatStore.getState().markRevoked(message_id, revoked_by, timestamp);
+    }
+    else if (payload.type === 'message_edited') {
+        const { message_id, content, timestamp } = payload.payload;
+        const normalized = content.kind === 'text' ? { conversation: content.text } : content;
+        useChatStore.getState().applyEdit(message_id, normalized, timestamp);
+    }
+    else if (payload.type === 'history_sync_progress') {
+        // History batches go straight to storage; reload the chat list from there
+        const { chats, messages, progress } = payload.payload;
+        console.log(`History sync: ${chats} chats, ${messages} messages${progress != null ? ` (${progress}%)` : ''}`);
+        getChats().then((list) => useChatStore.getState().setChats(list)).catch((e) => console.error('Failed to reload chats', e));
+    }
+    else if (payload.type === 'presence') {
+        const { jid, chat_id, state, last_seen } = payload.payload;
+        useChatStore.getState().setPresence(jid, { chatId: chat_id, state, lastSeen: last_seen });
+    }
+    else if (payload.type === 'message_status_changed') {
+        const { message_id, status } = payload.payload;
+        useChatStore.getState().setMessageStatus(message_id, status);
+    }
+    else if (payload.type === 'outbox_updated') {
+        useChatStore.getState().applyOutboxUpdate(payload.payload);
+    }
+    else if (payload.type === 'schedule_updated') {
+        useChatStore.getState().upsertScheduled(payload.payload);
+    }
+    else if (payload.type === 'reaction') {
+        const { message_id, sender_id, emoji } = payload.payload;
+        useChatStore.getState().setReaction(message_id, sender_id, emoji);
+    }
   });
 };
 
 export const sendText = async (jid: string, text: string) => {
     try {
-        await invoke('send_message', { jid, content: text });
-        // Optimistically add to store
+        const entry = await invoke<OutboxEntry>('send_message', { accountId: activeAccount(), jid, content: text });
+        // Shown right away; `outbox_updated` events move it to its real id once sent
         useChatStore.getState().addMessage({
-            key: { fromMe: true, remoteJid: jid, id: 'temp-' + Date.now() },
+            key: { fromMe: true, remoteJid: jid, id: outboxKey(entry.id) },
             fromMe: true,
             content: { conversation: text },
-            timestamp: Date.now() / 1000
+            timestamp: entry.created_at,
+            status: 'pending',
         });
     } catch (e) {
         console.error("Failed to send message", e);
     }
 };
 
+export const sendReply = async (jid: string, text: string, quotedId: string) => {
+    try {
+        // Queued like any message; the backend echoes the reply as a message event once sent, quote included
+        return await invoke<OutboxEntry>('send_reply', { accountId: activeAccount(), jid, content: text, quotedId });
+    } catch (e) {
+        console.error("Failed to send reply", e);
+    }
+};
+
+// Queued, in-flight and failed sends of the active account
+export const getOutbox = async () => {
+    return invoke<OutboxEntry[]>('get_outbox', { accountId: activeAccount() });
+};
+
+export const retryOutbox = async (id: number) => {
+    return invoke<OutboxEntry>('retry_outbox', { accountId: activeAccount(), id });
+};
+
+// Fails if the message is already being sent
+export const cancelOutbox = async (id: number) => {
+    await invoke('cancel_outbox', { accountId: activeAccount(), id });
+    useChatStore.setState((state) => ({
+        messages: state.messages.filter((m) => m.key?.id !== outboxKey(id)),
+    }));
+};
+
+// `sendAt` is a unix time in seconds. `ifMissed` applies when the app is closed at that time
+export const scheduleMessage = async (jid: string, content: string, sendAt: number, ifMissed: ScheduledMessage['if_missed'] = 'send_late') => {
+    const schedule = await invoke<ScheduledMessage>('schedule_message', { accountId: activeAccount(), jid, content, sendAt, ifMissed });
+    useChatStore.getState().upsertScheduled(schedule);
+    return schedule;
+};
+
+export const getScheduledMessages = async () => {
+    const scheduled = await invoke<ScheduledMessage[]>('get_scheduled_messages', { accountId: activeAccount() });
+    useChatStore.getState().setScheduled(scheduled);
+    return scheduled;
+};
+
+// Only messages that are still waiting can be edited or cancelled
+export const editScheduledMessage = async (id: number, content: string, sendAt: number, ifMissed: ScheduledMessage['if_missed']) => {
+    const schedule = await invoke<ScheduledMessage>('edit_scheduled_message', { accountId: activeAccount(), id, content, sendAt, ifMissed });
+    useChatStore.getState().upsertScheduled(schedule);
+    return schedule;
+};
+
+export const cancelScheduledMessage = async (id: number) => {
+    await invoke('cancel_scheduled_message', { accountId: activeAccount(), id });
+    useChatStore.getState().removeScheduled(id);
+};
+
+export interface Account {
+    id: string;
+    backend: 'rust' | 'baileys' | 'wwebjs';
+    created_at: number;
+    active: boolean;
+}
+
+export const getAccounts = async () => {
+    return invoke<Account[]>('get_accounts');
+};
+
+// Starts (or restarts) a session; other accounts keep running
+export const setupSession = async (accountId: string, backend: Account['backend'], frontend = 'tauri') => {
+    return invoke('setup_session', { accountId, backend, frontend });
+};
+
+export const resetSession = async (accountId = activeAccount()) => {
+    return invoke('reset_session', { accountId });
+};
+
+// Disconnects the account and forgets its pairing; stored chats are kept
+export const removeAccount = async (accountId: string) => {
+    return invoke('remove_account', { accountId });
+};
+
+// Re-encrypts the database and media cache; resolves to false if the current password is wrong
+export const changePassword = async (currentPassword: string, newPassword: string) => {
+    return invoke<boolean>('change_password', { currentPassword, newPassword });
+};
+
 // Stubs for missing functions to satisfy restored components
 export const updateSettings = async (settings: any) => {
     console.log('Settings updated (stub):', settings);
@@ -120,10 +246,177 @@ export const generateDraft = async (jid: string) => {
     // return invoke('generate_draft', { jid });
 };
 
-export const markRead = async (_jid: string, _ids: string[]) => {
-    // console.log('Mark read stub');
+// An empty emoji removes our reaction
+export const react = async (jid: string, messageId: string, emoji: string) => {
+    return invoke('react', { accountId: activeAccount(), chatId: jid, messageId, emoji });
+};
+
+export const getReactions = async (messageId: string) => {
+    return invoke<{ message_id: string; chat_id: string; sender_id: string; emoji: string; timestamp: number }[]>('get_reactions', { accountId: activeAccount(), messageId });
+};
+
+// Per-recipient times for the "message info" view
+export const getMessageReceipts = async (messageId: string) => {
+    return invoke<{ message_id: string; participant: string; delivered_at: number | null; read_at: number | null; played_at: number | null }[]>('get_message_receipts', { accountId: activeAccount(), messageId });
+};
+
+export type PresenceState = 'available' | 'unavailable' | 'composing' | 'recording' | 'paused';
+
+// Suppressed by the backend when ghost mode hides chatstate
+export const setTyping = async (jid: string, state: 'composing' | 'recording' | 'paused') => {
+    return invoke('set_typing', { accountId: activeAccount(), chatId: jid, state });
+};
+
+// Updates arrive as 'presence' events; skipped while ghost mode hides our own presence
+export const subscribePresence = async (jid: string) => {
+    return invoke('subscribe_presence', { accountId: activeAccount(), jid });
+};
+
+// Suppressed by the backend when ghost mode hides read receipts for the chat
+export const markRead = async (jid: string, ids: string[], senderId?: string) => {
+    return invoke('mark_read', { accountId: activeAccount(), chatId: jid, senderId: senderId ?? null, messageIds: ids });
+};
+
+export interface PrivacyRule {
+    scope: string; // '*' for the global rule, otherwise a chat jid
+    read_receipts: boolean | null;
+    chat_state: boolean | null;
+    presence: boolean | null;
+}
+
+export const getPrivacyRules = async () => {
+    return invoke<PrivacyRule[]>('get_privacy_rules');
+};
+
+export const setPrivacyRule = async (rule: PrivacyRule) => {
+    return invoke('set_privacy_rule', { rule });
+};
+
+export const removePrivacyRule = async (scope: string) => {
+    return invoke('remove_privacy_rule', { scope });
+};
+
+export const getChats = async () => {
+    return invoke<any[]>('get_chats', { accountId: activeAccount() });
+};
+
+export const getContacts = async () => {
+    return invoke<{ jid: string; name: string | null; notify_name: string | null }[]>('get_contacts', { accountId: activeAccount() });
+};
+
+// Every revision of an edited message, the original first
+export const getMessageHistory = async (messageId: string) => {
+    return invoke<{ message_id: string; content: string; timestamp: number }[]>('get_message_history', { accountId: activeAccount(), messageId });
+};
+
+export const getMessages = async (jid: string, limit: number, offset = 0) => {
+    return invoke<any[]>('get_messages', { accountId: activeAccount(), chatId: jid, limit, offset });
+};
+
+export interface MessageCursor {
+    timestamp: number;
+    id: string;
+}
+
+export type MessageRange =
+    | { kind: 'latest' }
+    | { kind: 'before'; cursor: MessageCursor }
+    | { kind: 'after'; cursor: MessageCursor }
+    | { kind: 'around'; message_id: string };
+
+export interface MessagePage {
+    messages: any[]; // oldest first
+    has_before: boolean;
+    has_after: boolean;
+}
+
+export const cursorOf = (message: { id: string; timestamp: number }): MessageCursor => ({
+    timestamp: message.timestamp,
+    id: message.id,
+});
+
+// Keyset-paginated history; use `around` to open a chat at a search result
+export const getMessagePage = async (jid: string, range: MessageRange, limit = 50) => {
+    return invoke<MessagePage>('get_message_page', { accountId: activeAccount(), chatId: jid, range, limit });
+};
+
+export interface SearchFilters {
+    chat_id?: string;
+    sender_id?: string;
+    after?: number; // unix seconds, inclusive
+    before?: number; // unix seconds, exclusive
+    from_me?: boolean;
+    limit?: number;
+}
+
+export interface SearchHit {
+    message: any;
+    snippet: string;
+}
+
+// Matched terms in `snippet` are wrapped in these markers
+export const SNIPPET_START = '\u0002';
+export const SNIPPET_END = '\u0003';
+
+// Splits a snippet into plain and highlighted parts, for rendering as text
+export const snippetParts = (snippet: string) => {
+    return snippet.split(SNIPPET_START).flatMap((part, i) => {
+        if (i === 0) return [{ text: part, match: false }];
+        const [match, rest = ''] = part.split(SNIPPET_END);
+        return [{ text: match, match: true }, { text: rest, match: false }];
+    }).filter((part) => part.text !== '');
+};
+
+// Full-text search over stored messages, newest first
+export const searchMessages = async (query: string, filters: SearchFilters = {}) => {
+    return invoke<SearchHit[]>('search_messages', { accountId: activeAccount(), query, filters });
+};
+
+export type ParticipantRole = 'member' | 'admin' | 'super_admin';
+export type ParticipantAction = 'add' | 'remove' | 'promote' | 'demote';
+
+export interface GroupMetadata {
+    id: string;
+    subject: string;
+    description: string | null;
+    owner: string | null;
+    created_at: number | null;
+    participants: { jid: string; role: ParticipantRole }[];
+}
+
+// Groups as of the last sync, from local storage
+export const getGroups = async () => {
+    return invoke<{ id: string; subject: string; description: string | null; owner: string | null; created_at: number | null }[]>('get_groups', { accountId: activeAccount() });
+};
+
+export const getGroupParticipants = async (groupId: string) => {
+    return invoke<{ group_id: string; jid: string; role: ParticipantRole }[]>('get_group_participants', { accountId: activeAccount(), groupId });
+};
+
+export const syncGroups = async () => {
+    return invoke<GroupMetadata[]>('sync_groups', { accountId: activeAccount() });
+};
+
+export const getGroupMetadata = async (groupId: string) => {
+    return invoke<GroupMetadata>('get_group_metadata', { accountId: activeAccount(), groupId });
+};
+
+export const createGroup = async (subject: string, participants: string[]) => {
+    return invoke<GroupMetadata>('create_group', { accountId: activeAccount(), subject, participants });
+};
+
+export const updateGroupParticipants = async (groupId: string, participants: string[], action: ParticipantAction) => {
+    return invoke<GroupMetadata>('update_group_participants', { accountId: activeAccount(), groupId, participants, action });
+};
+
+export const setGroupSubject = async (groupId: string, subject: string) => {
+    return invoke<GroupMetadata>('set_group_subject', { accountId: activeAccount(), groupId, subject });
+};
+
+export const setGroupDescription = async (groupId: string, description: string | null) => {
+    return invoke<GroupMetadata>('set_group_description', { accountId: activeAccount(), groupId, description });
 };
 
-export const getMessages = async (_jid: string, _limit: number) => {
-    return [];
+export const leaveGroup = async (groupId: string) => {
+    return invoke('leave_group', { accountId: activeAccount(), groupId });
 };
@@ -1,9 +1,11 @@
 import { create } from 'zustand';
 
 interface AuthState {
+  accountId: string;
   status: 'disconnected' | 'connecting' | 'qr' | 'connected';
   qrCode: string | null;
   user: any | null;
+  setAccountId: (accountId: string) => void;
   setStatus: (status: AuthState['status']) => void;
   setQR: (qr: string) => void;
   setConnected: (user: any) => void;
@@ -11,9 +13,12 @@ interface AuthState {
 }
 
 export const useAuthStore = create<AuthState>((set) => ({
+  accountId: 'default',
   status: 'disconnected',
   qrCode: null,
   user: null,
+  // Connection state belongs to the previous account, so start over
+  setAccountId: (accountId) => set({ accountId, status: 'disconnected', qrCode: null, user: null }),
   setStatus: (status) => set({ status }),
   setQR: (qr) => set({ status: 'qr', qrCode: qr }),
   setConnected: (user) => set({ status: 'connected', user, qrCode: null }),
@@ -1,13 +1,66 @@
 import { create } from 'zustand';
 
+interface Presence {
+  chatId: string | null; // set for typing/recording, which are scoped to a chat
+  state: string;
+  lastSeen: number | null;
+}
+
+export type MessageStatus = 'pending' | 'failed' | 'sent' | 'delivered' | 'read' | 'played';
+
+// Mirrors `OutboxEntry` in the backend
+export interface OutboxEntry {
+  id: number;
+  account_id: string;
+  chat_id: string;
+  content: string;
+  quoted_id: string | null;
+  media_path: string | null;
+  mime_type: string | null;
+  state: 'pending' | 'sending' | 'sent' | 'failed';
+  attempts: number;
+  next_attempt_at: number;
+  last_error: string | null;
+  message_id: string | null;
+  created_at: number;
+}
+
+// Mirrors `ScheduledMessage` in the backend
+export interface ScheduledMessage {
+  id: number;
+  account_id: string;
+  chat_id: string;
+  content: string;
+  send_at: number;
+  if_missed: 'send_late' | 'skip';
+  state: 'scheduled' | 'queued' | 'skipped';
+  outbox_id: number | null;
+  created_at: number;
+}
+
+// Queued messages are shown under this key until WhatsApp assigns an id
+export const outboxKey = (id: number) => `outbox:${id}`;
+
 interface ChatState {
   chats: any[];
   activeChat: string | null;
   messages: any[];
   drafts: Record<string, string>;
+  presence: Record<string, Presence>;
+  scheduled: ScheduledMessage[];
   setDraft: (jid: string, text: string) => void;
   setActiveChat: (jid: string) => void;
+  setChats: (chats: any[]) => void;
+  setPresence: (jid: string, presence: Presence) => void;
   addMessage: (msg: any) => void;
+  markRevoked: (id: string, revokedBy: string, revokedAt: number) => void;
+  applyEdit: (id: string, content: any, editedAt: number) => void;
+  setReaction: (id: string, senderId: string, emoji: string | null) => void;
+  setMessageStatus: (id: string, status: MessageStatus) => void;
+  applyOutboxUpdate: (entry: OutboxEntry) => void;
+  setScheduled: (scheduled: ScheduledMessage[]) => void;
+  upsertScheduled: (schedule: ScheduledMessage) => void;
+  removeScheduled: (id: number) => void;
 }
 
 export const useChatStore = create<ChatState>((set) => ({
@@ -15,7 +68,75 @@ export const useChatStore = create<ChatState>((set) => ({
   activeChat: null,
   messages: [],
   drafts: {},
+  presence: {},
+  scheduled: [],
   setDraft: (jid, text) => set((state) => ({ drafts: { ...state.drafts, [jid]: text } })),
   setActiveChat: (jid) => set({ activeChat: jid }),
+  setChats: (chats) => set({ chats }),
+  // Keep the last known "last seen" when an update does not carry one
+  setPresence: (jid, presence) => set((state) => ({
+    presence: {
+      ...state.presence,
+      [jid]: { ...presence, lastSeen: presence.lastSeen ?? state.presence[jid]?.lastSeen ?? null },
+    },
+  })),
   addMessage: (msg) => set((state) => ({ messages: [...state.messages, msg] })),
+  // Anti-delete: keep the message and flag it
+  markRevoked: (id, revokedBy, revokedAt) => set((state) => ({
+    messages: state.messages.map((m) =>
+      m.key?.id === id ? { ...m, revoked: { by: revokedBy, at: revokedAt } } : m
+    ),
+  })),
+  applyEdit: (id, content, editedAt) => set((state) => ({
+    messages: state.messages.map((m) =>
+      m.key?.id === id ? { ...m, content, editedAt } : m
+    ),
+  })),
+  // One reaction per sender; null removes it
+  setReaction: (id, senderId, emoji) => set((state) => ({
+    messages: state.messages.map((m) => {
+      if (m.key?.id !== id) return m;
+      const reactions = { ...(m.reactions ?? {}) };
+      if (emoji) {
+        reactions[senderId] = emoji;
+      } else {
+        delete reactions[senderId];
+      }
+      return { ...m, reactions };
+    }),
+  })),
+  // Drives the ticks; the backend only ever reports a status moving forward
+  setMessageStatus: (id, status) => set((state) => ({
+    messages: state.messages.map((m) =>
+      m.key?.id === id ? { ...m, status } : m
+    ),
+  })),
+  // Once sent, the placeholder takes the real id, unless the echo of the message already arrived
+  applyOutboxUpdate: (entry) => set((state) => {
+    const key = outboxKey(entry.id);
+    if (entry.state === 'sent' && entry.message_id) {
+      if (state.messages.some((m) => m.key?.id === entry.message_id)) {
+        return { messages: state.messages.filter((m) => m.key?.id !== key) };
+      }
+      return {
+        messages: state.messages.map((m) =>
+          m.key?.id === key ? { ...m, key: { ...m.key, id: entry.message_id }, status: 'sent', error: null } : m
+        ),
+      };
+    }
+    const status: MessageStatus = entry.state === 'failed' ? 'failed' : 'pending';
+    return {
+      messages: state.messages.map((m) =>
+        m.key?.id === key ? { ...m, status, error: entry.last_error } : m
+      ),
+    };
+  }),
+  setScheduled: (scheduled) => set({ scheduled }),
+  upsertScheduled: (schedule) => set((state) => ({
+    scheduled: [...state.scheduled.filter((s) => s.id !== schedule.id), schedule]
+      .sort((a, b) => a.send_at - b.send_at || a.id - b.id),
+  })),
+  removeScheduled: (id) => set((state) => ({
+    scheduled: state.scheduled.filter((s) => s.id !== id),
+  })),
 }));
@@ -19,6 +19,12 @@ impl CustomAdapter {
     }
 }
 
+impl Default for CustomAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[async_trait]
 impl MessagingAdapter for CustomAdapter {
     fn name(&self) -> &str {
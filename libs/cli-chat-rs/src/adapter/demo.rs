@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use chrono::Utc;
 use tokio::sync::mpsc;
-use crate::adapter::{AdapterResult, MessagingAdapter};
+use crate::adapter::{AdapterEvent, AdapterResult, MessagingAdapter};
 use crate::types::*;
 
 /// Demo adapter for testing and demonstration purposes
@@ -11,6 +11,7 @@ pub struct DemoAdapter {
     chats: Vec<Chat>,
     contacts: Vec<Contact>,
     messages: Vec<Message>,
+    events: Option<mpsc::Sender<AdapterEvent>>,
 }
 
 impl DemoAdapter {
@@ -53,11 +54,39 @@ impl DemoAdapter {
             },
         ];
 
+        let now = Utc::now();
+        let messages = vec![
+            Message {
+                id: "demo_1".to_string(),
+                chat_id: "chat1".to_string(),
+                sender_id: "contact1".to_string(),
+                content: MessageContent::Text("Lunch tomorrow?".to_string()),
+                timestamp: now - chrono::Duration::minutes(5),
+                is_from_me: false,
+                status: MessageStatus::Read,
+                reactions: Vec::new(),
+            },
+            Message {
+                id: "demo_2".to_string(),
+                chat_id: "chat1".to_string(),
+                sender_id: "me".to_string(),
+                content: MessageContent::Text("Sure, 12:30 works".to_string()),
+                timestamp: now - chrono::Duration::minutes(4),
+                is_from_me: true,
+                status: MessageStatus::Read,
+                reactions: vec![Reaction {
+                    sender_id: "contact1".to_string(),
+                    emoji: "👍".to_string(),
+                }],
+            },
+        ];
+
         Self {
             status: ConnectionStatus::Disconnected,
             chats,
             contacts,
-            messages: Vec::new(),
+            messages,
+            events: None,
         }
     }
 }
@@ -108,11 +137,67 @@ impl MessagingAdapter for DemoAdapter {
             timestamp: Utc::now(),
             is_from_me: true,
             status: MessageStatus::Sent,
+            reactions: Vec::new(),
         };
         self.messages.push(message.clone());
+
+        // Pretend the other side receives and reads it, starts typing an answer, then gives up
+        if let (Some(events), Some(chat)) = (self.events.clone(), self.chats.iter().find(|c| &c.id == chat_id)) {
+            if let Some(contact_id) = chat.participants.first().cloned() {
+                let chat_id = chat_id.clone();
+                let message_id = message.id.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+                    let _ = events.send(AdapterEvent::MessageStatusChanged {
+                        message_id: message_id.clone(),
+                        status: MessageStatus::Delivered,
+                    }).await;
+                    tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+                    let _ = events.send(AdapterEvent::MessageStatusChanged { message_id, status: MessageStatus::Read }).await;
+                    let _ = events.send(AdapterEvent::TypingChanged {
+                        chat_id: chat_id.clone(),
+                        contact_id: contact_id.clone(),
+                        state: TypingState::Typing,
+                    }).await;
+                    tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+                    let _ = events.send(AdapterEvent::TypingChanged { chat_id, contact_id, state: TypingState::Paused }).await;
+                });
+            }
+        }
+
         Ok(message)
     }
 
+    async fn react(&mut self, chat_id: &ChatId, message_id: &MessageId, emoji: &str) -> AdapterResult<()> {
+        let message = self.messages
+            .iter_mut()
+            .find(|m| &m.chat_id == chat_id && &m.id == message_id)
+            .ok_or("Message not found")?;
+        message.reactions.retain(|r| r.sender_id != "me");
+        if !emoji.is_empty() {
+            message.reactions.push(Reaction {
+                sender_id: "me".to_string(),
+                emoji: emoji.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn subscribe_presence(&mut self, contact_id: &ContactId) -> AdapterResult<()> {
+        let contact = self.contacts
+            .iter()
+            .find(|c| &c.id == contact_id)
+            .ok_or("Contact not found")?;
+        if let Some(events) = &self.events {
+            let _ = events.send(AdapterEvent::ContactStatusChanged {
+                contact_id: contact.id.clone(),
+                is_online: contact.is_online,
+                last_seen: if contact.is_online { None } else { Some(Utc::now() - chrono::Duration::hours(2)) },
+            }).await;
+        }
+        Ok(())
+    }
+
     async fn mark_as_read(&mut self, _chat_id: &ChatId, _message_id: &MessageId) -> AdapterResult<()> {
         Ok(())
     }
@@ -135,6 +220,12 @@ impl MessagingAdapter for DemoAdapter {
         Ok(rx)
     }
 
+    async fn subscribe_to_events(&mut self) -> AdapterResult<mpsc::Receiver<AdapterEvent>> {
+        let (tx, rx) = mpsc::channel(100);
+        self.events = Some(tx);
+        Ok(rx)
+    }
+
     async fn search(&self, query: &str) -> AdapterResult<Vec<Chat>> {
         let query_lower = query.to_lowercase();
         let results: Vec<Chat> = self.chats
@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use crate::types::*;
 use super::AdapterResult;
 
@@ -27,6 +28,21 @@ pub trait MessagingAdapter: Send + Sync {
     /// Send a text message to a chat
     async fn send_message(&mut self, chat_id: &ChatId, content: MessageContent) -> AdapterResult<Message>;
 
+    /// React to a message; an empty emoji removes our reaction
+    async fn react(&mut self, _chat_id: &ChatId, _message_id: &MessageId, _emoji: &str) -> AdapterResult<()> {
+        Err(format!("{} does not support reactions", self.name()).into())
+    }
+
+    /// Tell the chat we are typing, recording or have stopped
+    async fn set_typing(&mut self, _chat_id: &ChatId, _state: TypingState) -> AdapterResult<()> {
+        Ok(())
+    }
+
+    /// Start receiving online/last seen updates for a contact as `ContactStatusChanged` events
+    async fn subscribe_presence(&mut self, _contact_id: &ContactId) -> AdapterResult<()> {
+        Ok(())
+    }
+
     /// Mark a message as read
     async fn mark_as_read(&mut self, chat_id: &ChatId, message_id: &MessageId) -> AdapterResult<()>;
 
@@ -39,6 +55,13 @@ pub trait MessagingAdapter: Send + Sync {
     /// Listen for incoming messages (returns a receiver for new messages)
     async fn subscribe_to_messages(&mut self) -> AdapterResult<tokio::sync::mpsc::Receiver<Message>>;
 
+    /// Listen for everything other than new messages (status, presence, typing...).
+    /// Adapters without such events return a channel that never yields
+    async fn subscribe_to_events(&mut self) -> AdapterResult<tokio::sync::mpsc::Receiver<AdapterEvent>> {
+        let (_tx, rx) = tokio::sync::mpsc::channel(1);
+        Ok(rx)
+    }
+
     /// Search for messages or chats
     async fn search(&self, query: &str) -> AdapterResult<Vec<Chat>>;
 }
@@ -48,7 +71,10 @@ pub trait MessagingAdapter: Send + Sync {
 pub enum AdapterEvent {
     MessageReceived(Message),
     MessageStatusChanged { message_id: MessageId, status: MessageStatus },
-    ContactStatusChanged { contact_id: ContactId, is_online: bool },
+    /// A participant reacted to a message; `None` means the reaction was removed
+    ReactionChanged { message_id: MessageId, sender_id: ContactId, emoji: Option<String> },
+    ContactStatusChanged { contact_id: ContactId, is_online: bool, last_seen: Option<DateTime<Utc>> },
+    TypingChanged { chat_id: ChatId, contact_id: ContactId, state: TypingState },
     ConnectionStatusChanged(ConnectionStatus),
     Error(String),
 }
@@ -17,6 +17,17 @@ pub struct Config {
 
     /// General application settings
     pub app: AppConfig,
+
+    /// What we reveal to other participants
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
+}
+
+/// Privacy settings applied on top of every adapter
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrivacyConfig {
+    /// Never send typing indicators or subscribe to other people's presence
+    pub ghost_mode: bool,
 }
 
 /// Configuration for a specific messaging adapter
@@ -93,6 +104,7 @@ impl Default for Config {
             adapters: HashMap::new(),
             shortcuts: ShortcutConfig::default(),
             app: AppConfig::default(),
+            privacy: PrivacyConfig::default(),
         }
     }
 }
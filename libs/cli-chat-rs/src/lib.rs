@@ -3,8 +3,8 @@ pub mod config;
 pub mod types;
 pub mod ui;
 
-pub use adapter::{MessagingAdapter, AdapterResult, DemoAdapter};
-pub use config::Config;
+pub use adapter::{MessagingAdapter, AdapterEvent, AdapterResult, DemoAdapter};
+pub use config::{Config, PrivacyConfig};
 pub use types::*;
 pub use ui::{Action, KeyboardHandler};
 
@@ -30,6 +30,22 @@ impl MessengerApp {
         self.adapter.as_mut()
     }
 
+    /// Send a typing indicator unless ghost mode is on
+    pub async fn set_typing(&mut self, chat_id: &ChatId, state: TypingState) -> AdapterResult<()> {
+        if self.config.privacy.ghost_mode {
+            return Ok(());
+        }
+        self.adapter.set_typing(chat_id, state).await
+    }
+
+    /// Follow a contact's presence unless ghost mode is on
+    pub async fn subscribe_presence(&mut self, contact_id: &ContactId) -> AdapterResult<()> {
+        if self.config.privacy.ghost_mode {
+            return Ok(());
+        }
+        self.adapter.subscribe_presence(contact_id).await
+    }
+
     /// Get reference to the configuration
     pub fn config(&self) -> &Config {
         &self.config
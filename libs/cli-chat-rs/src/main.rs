@@ -1,4 +1,6 @@
-use cli_chat_rs::{Config, DemoAdapter, MessengerApp, KeyboardHandler, Action};
+use chrono::{DateTime, Local, Utc};
+use cli_chat_rs::{AdapterEvent, Action, Chat, ChatId, Config, ContactId, DemoAdapter, KeyboardHandler, MessageId, MessageStatus, MessengerApp, TypingState};
+use cli_chat_rs::ui::message_lines;
 use crossterm::{
     event::{self, Event, KeyCode},
     execute,
@@ -9,11 +11,37 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Wrap},
     Terminal,
 };
+use std::collections::HashMap;
 use std::io;
 
+/// Last known online status of a contact
+struct ContactPresence {
+    is_online: bool,
+    last_seen: Option<DateTime<Utc>>,
+}
+
+/// Chat name followed by who is typing, or the contact's online status in 1:1 chats
+fn chat_title(chat: &Chat, typing: &HashMap<ChatId, TypingState>, presence: &HashMap<ContactId, ContactPresence>) -> String {
+    let status = match typing.get(&chat.id) {
+        Some(TypingState::Typing) => Some("typing...".to_string()),
+        Some(TypingState::Recording) => Some("recording audio...".to_string()),
+        _ if chat.is_group => None,
+        _ => chat.participants.first().and_then(|id| presence.get(id)).map(|p| match (p.is_online, p.last_seen) {
+            (true, _) => "online".to_string(),
+            (false, Some(seen)) => format!("last seen {}", seen.with_timezone(&Local).format("%H:%M")),
+            (false, None) => "offline".to_string(),
+        }),
+    };
+
+    match status {
+        Some(status) => format!("{} - {}", chat.name, status),
+        None => chat.name.clone(),
+    }
+}
+
 /// Active screen state for mobile/narrow view
 #[derive(PartialEq)]
 enum ActiveScreen {
@@ -83,6 +111,11 @@ async fn run_ui(
     let mut input_message = String::new();
     let mut show_help = false;
     let mut active_screen = ActiveScreen::ChatList;
+    let mut typing: HashMap<ChatId, TypingState> = HashMap::new();
+    let mut presence: HashMap<ContactId, ContactPresence> = HashMap::new();
+    let mut presence_chat: Option<ChatId> = None;
+    let mut statuses: HashMap<MessageId, MessageStatus> = HashMap::new();
+    let mut events = app.adapter_mut().subscribe_to_events().await.map_err(|e| format!("Failed to subscribe to events: {}", e))?;
 
     // Threshold for switching to mobile layout (columns)
     const MOBILE_THRESHOLD: u16 = 80;
@@ -90,6 +123,45 @@ async fn run_ui(
     loop {
         // Get chats
         let chats = app.adapter().get_chats().await.map_err(|e| format!("Failed to get chats: {}", e))?;
+        let mut messages = match chats.get(selected_chat) {
+            Some(chat) => app.adapter().get_messages(&chat.id, 100).await.map_err(|e| format!("Failed to get messages: {}", e))?,
+            None => Vec::new(),
+        };
+
+        while let Ok(event) = events.try_recv() {
+            match event {
+                AdapterEvent::TypingChanged { chat_id, state: TypingState::Paused, .. } => {
+                    typing.remove(&chat_id);
+                }
+                AdapterEvent::TypingChanged { chat_id, state, .. } => {
+                    typing.insert(chat_id, state);
+                }
+                AdapterEvent::ContactStatusChanged { contact_id, is_online, last_seen } => {
+                    presence.insert(contact_id, ContactPresence { is_online, last_seen });
+                }
+                AdapterEvent::MessageStatusChanged { message_id, status } => {
+                    statuses.insert(message_id, status);
+                }
+                _ => {}
+            }
+        }
+
+        // Receipts that arrived after the adapter handed out its copy
+        for message in messages.iter_mut() {
+            if let Some(status) = statuses.get(&message.id) {
+                message.status = *status;
+            }
+        }
+
+        // Follow the presence of whoever we are looking at
+        if let Some(chat) = chats.get(selected_chat) {
+            if presence_chat.as_ref() != Some(&chat.id) {
+                presence_chat = Some(chat.id.clone());
+                if let (false, Some(contact_id)) = (chat.is_group, chat.participants.first()) {
+                    let _ = app.subscribe_presence(contact_id).await;
+                }
+            }
+        }
 
         terminal.draw(|f| {
             let size = f.size();
@@ -155,7 +227,7 @@ async fn run_ui(
                 let messages_block = Block::default()
                     .borders(Borders::ALL)
                     .title(if selected_chat < chats.len() {
-                        chats[selected_chat].name.clone()
+                        chat_title(&chats[selected_chat], &typing, &presence)
                     } else {
                         "No chat selected".to_string()
                     });
@@ -172,6 +244,10 @@ async fn run_ui(
                         })
                         .collect();
                     Paragraph::new(lines).block(messages_block)
+                } else if !messages.is_empty() {
+                    Paragraph::new(message_lines(&messages))
+                        .wrap(Wrap { trim: false })
+                        .block(messages_block)
                 } else {
                     Paragraph::new(format!(
                         "Welcome to CLI Chat RS!\n\n\
@@ -215,28 +291,22 @@ async fn run_ui(
                 let is_mobile = size.width < MOBILE_THRESHOLD;
 
                 // Handle ESC for mobile back navigation
-                if is_mobile && key.code == KeyCode::Esc {
-                    if active_screen == ActiveScreen::ChatView {
-                        active_screen = ActiveScreen::ChatList;
-                        continue;
-                    }
+                if is_mobile && key.code == KeyCode::Esc && active_screen == ActiveScreen::ChatView {
+                    active_screen = ActiveScreen::ChatList;
+                    continue;
                 }
 
                 match action {
                     Action::Quit => break,
-                    Action::NextChat => {
-                        if !chats.is_empty() {
-                            selected_chat = (selected_chat + 1) % chats.len();
-                        }
+                    Action::NextChat if !chats.is_empty() => {
+                        selected_chat = (selected_chat + 1) % chats.len();
                     }
-                    Action::PrevChat => {
-                        if !chats.is_empty() {
-                            selected_chat = if selected_chat == 0 {
-                                chats.len() - 1
-                            } else {
-                                selected_chat - 1
-                            };
-                        }
+                    Action::PrevChat if !chats.is_empty() => {
+                        selected_chat = if selected_chat == 0 {
+                            chats.len() - 1
+                        } else {
+                            selected_chat - 1
+                        };
                     }
                     Action::SendMessage => {
                         // On mobile, Enter on ChatList enters the chat
@@ -245,6 +315,7 @@ async fn run_ui(
                         } else if !input_message.is_empty() && selected_chat < chats.len() {
                             let content = cli_chat_rs::MessageContent::Text(input_message.clone());
                             let _ = app.adapter_mut().send_message(&chats[selected_chat].id, content).await;
+                            let _ = app.set_typing(&chats[selected_chat].id, TypingState::Paused).await;
                             input_message.clear();
                         }
                     }
@@ -254,6 +325,7 @@ async fn run_ui(
                 // Handle text input
                 // Only allow typing if we are in ChatView (on mobile) or always on desktop
                 if !is_mobile || active_screen == ActiveScreen::ChatView {
+                    let was_empty = input_message.is_empty();
                     if let KeyCode::Char(c) = key.code {
                         if !key.modifiers.contains(crossterm::event::KeyModifiers::CONTROL) {
                             input_message.push(c);
@@ -263,6 +335,15 @@ async fn run_ui(
                     } else if let KeyCode::Backspace = key.code {
                         input_message.pop();
                     }
+
+                    // Announce typing when a draft starts and stop when it is cleared
+                    if let Some(chat) = chats.get(selected_chat) {
+                        if was_empty && !input_message.is_empty() {
+                            let _ = app.set_typing(&chat.id, TypingState::Typing).await;
+                        } else if !was_empty && input_message.is_empty() && key.code == KeyCode::Backspace {
+                            let _ = app.set_typing(&chat.id, TypingState::Paused).await;
+                        }
+                    }
                 }
             }
         }
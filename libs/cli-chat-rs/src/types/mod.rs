@@ -20,6 +20,30 @@ pub struct Message {
     pub timestamp: DateTime<Utc>,
     pub is_from_me: bool,
     pub status: MessageStatus,
+    /// Current reactions, at most one per participant
+    #[serde(default)]
+    pub reactions: Vec<Reaction>,
+}
+
+impl Message {
+    /// Reactions grouped by emoji with their counts, in order of first appearance
+    pub fn reaction_summary(&self) -> Vec<(String, usize)> {
+        let mut summary: Vec<(String, usize)> = Vec::new();
+        for reaction in &self.reactions {
+            match summary.iter_mut().find(|(emoji, _)| *emoji == reaction.emoji) {
+                Some((_, count)) => *count += 1,
+                None => summary.push((reaction.emoji.clone(), 1)),
+            }
+        }
+        summary
+    }
+}
+
+/// An emoji reaction to a message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reaction {
+    pub sender_id: ContactId,
+    pub emoji: String,
 }
 
 /// Different types of message content
@@ -43,6 +67,14 @@ pub enum MessageStatus {
     Failed,
 }
 
+/// Chat state shown while a participant is composing
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TypingState {
+    Typing,
+    Recording,
+    Paused,
+}
+
 /// Represents a contact in the messaging system
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Contact {
@@ -0,0 +1,71 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use crate::types::{Message, MessageContent, MessageStatus};
+
+/// Render a chat's messages as lines for the message view
+pub fn message_lines(messages: &[Message]) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    for message in messages {
+        let (sender, sender_style) = if message.is_from_me {
+            ("You".to_string(), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
+        } else {
+            (message.sender_id.clone(), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+        };
+
+        let mut spans = vec![
+            Span::styled(message.timestamp.format("%H:%M ").to_string(), Style::default().fg(Color::DarkGray)),
+            Span::styled(format!("{}: ", sender), sender_style),
+            Span::raw(content_preview(&message.content)),
+        ];
+        if message.is_from_me {
+            spans.push(status_ticks(message.status));
+        }
+        lines.push(Line::from(spans));
+
+        let summary = message.reaction_summary();
+        if !summary.is_empty() {
+            let reactions = summary
+                .iter()
+                .map(|(emoji, count)| if *count > 1 { format!("{} {}", emoji, count) } else { emoji.clone() })
+                .collect::<Vec<_>>()
+                .join("  ");
+            lines.push(Line::from(Span::styled(
+                format!("      {}", reactions),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+    }
+    lines
+}
+
+/// WhatsApp-style ticks: one when sent, two once delivered, blue once read
+fn status_ticks(status: MessageStatus) -> Span<'static> {
+    let (ticks, color) = match status {
+        MessageStatus::Sending => (" ○", Color::DarkGray),
+        MessageStatus::Sent => (" ✓", Color::DarkGray),
+        MessageStatus::Delivered => (" ✓✓", Color::DarkGray),
+        MessageStatus::Read => (" ✓✓", Color::Blue),
+        MessageStatus::Failed => (" !", Color::Red),
+    };
+    Span::styled(ticks, Style::default().fg(color))
+}
+
+fn content_preview(content: &MessageContent) -> String {
+    match content {
+        MessageContent::Text(text) => text.clone(),
+        MessageContent::Image { caption, .. } => with_caption("[Image]", caption),
+        MessageContent::Video { caption, .. } => with_caption("[Video]", caption),
+        MessageContent::Audio { .. } => "[Audio]".to_string(),
+        MessageContent::Document { filename, .. } => format!("[Document] {}", filename),
+        MessageContent::Location { name, .. } => with_caption("[Location]", name),
+    }
+}
+
+fn with_caption(label: &str, caption: &Option<String>) -> String {
+    match caption {
+        Some(caption) => format!("{} {}", label, caption),
+        None => label.to_string(),
+    }
+}
@@ -1,3 +1,5 @@
 pub mod keyboard;
+pub mod messages;
 
 pub use keyboard::{Action, KeyboardHandler};
+pub use messages::message_lines;